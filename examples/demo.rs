@@ -3,6 +3,7 @@ use exstreamer::{
     models::{BinanceRequest, BybitRequest, KrakenChannel},
 };
 use futures_util::StreamExt;
+use std::time::Duration;
 
 #[tokio::main]
 async fn main() {
@@ -86,11 +87,12 @@ async fn main() {
     }
 
     // Shutdown the connections
+    let timeout = Duration::from_secs(5);
     tokio::try_join!(
-        binance_handler.shutdown(),
-        bybit_handler.shutdown(),
-        coinbase_handler.shutdown(),
-        kraken_handler.shutdown(),
+        binance_handler.shutdown(timeout),
+        bybit_handler.shutdown(timeout),
+        coinbase_handler.shutdown(timeout),
+        kraken_handler.shutdown(timeout),
     )
     .expect("Failed to shutdown streamers");
 
@@ -1,5 +1,5 @@
 use base64::Engine as _;
-use exstreamer::{StreamBuilder, models::KrakenChannel};
+use exstreamer::{StreamBuilder, models::KrakenChannel, orderbook::KrakenL3Books};
 use futures_util::StreamExt;
 use hmac::{Hmac, Mac};
 use sha2::{Digest, Sha256, Sha512};
@@ -13,7 +13,7 @@ async fn main() {
     let token = get_token().await;
     tracing::info!("Received token: {}", token);
 
-    let (mut kraken_stream, kraken_handler) = StreamBuilder::kraken(KrakenChannel::L3)
+    let (kraken_stream, kraken_handler) = StreamBuilder::kraken(KrakenChannel::L3)
         .with_id(1)
         .with_token(token)
         .with_depth(1000)
@@ -22,12 +22,21 @@ async fn main() {
         .await
         .unwrap();
 
-    // Receive messages
+    let (books, mut book_events) = KrakenL3Books::new()
+        .with_precision("BTC/USD", 1, 8)
+        .track(kraken_stream, kraken_handler.handle());
+
+    // Receive book changes
     loop {
         tokio::select! {
-            message = kraken_stream.next() => {
-                if let Some(msg) = message {
-                    tracing::info!("Received Kraken message: {:?}", msg);
+            event = book_events.next() => {
+                if let Some(event) = event {
+                    tracing::info!("Received book event: {:?}", event);
+                    let books = books.read().unwrap();
+                    if let Some(book) = books.book("BTC/USD") {
+                        let l2 = book.to_l2();
+                        tracing::info!("{} orders, {:?} / {:?}", book.len(), l2.best_bid(), l2.best_ask());
+                    }
                 } else {
                     tracing::info!("No more messages to receive.");
                     break;
@@ -42,7 +51,7 @@ async fn main() {
 
     // Shutdown the connections;
     kraken_handler
-        .shutdown()
+        .shutdown(std::time::Duration::from_secs(5))
         .await
         .expect("Failed to shutdown streamers");
 
@@ -85,7 +94,7 @@ async fn get_token() -> String {
     let response: TokenResponse =
         serde_json::from_str(&text).expect("Failed to parse token response");
 
-    if response.error.len() > 0 {
+    if !response.error.is_empty() {
         tracing::error!("Error getting token: {:?}", response.error);
         panic!("Failed to get token");
     }
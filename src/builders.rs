@@ -1,9 +1,90 @@
+/// Options of the `config: ConnectionConfig` field shared by every builder.
+/// `@stream` only sets the options that apply to streams without subscriptions, e.g. user data.
+macro_rules! connection_options {
+    () => {
+        connection_options!(@stream);
+
+        /// Reconnect when the feed goes silent for longer than the watchdog timeouts
+        pub fn with_watchdog(mut self, watchdog: $crate::transport::Watchdog) -> Self {
+            self.config.watchdog = Some(watchdog);
+            self
+        }
+
+        /// Time to wait for the exchange ack of `subscribe_with_ack` and `unsubscribe_with_ack`
+        pub fn with_ack_timeout(mut self, timeout: std::time::Duration) -> Self {
+            self.config.ack_timeout = timeout;
+            self
+        }
+    };
+    (@stream) => {
+        /// Set the policy used to reconnect when the connection is lost
+        pub fn with_reconnect_policy(mut self, policy: $crate::transport::ReconnectPolicy) -> Self {
+            self.config.reconnect = policy;
+            self
+        }
+
+        /// Bound the inbound buffer, applying the backpressure policy when the consumer falls behind
+        pub fn with_buffer(
+            mut self,
+            capacity: usize,
+            backpressure: $crate::transport::Backpressure,
+        ) -> Self {
+            self.config.buffer = Some($crate::transport::BufferPolicy::new(capacity, backpressure));
+            self
+        }
+    };
+}
+
+/// `with_endpoint` of the builders with an `endpoint: Option<String>` field
+macro_rules! endpoint_option {
+    () => {
+        /// Connect to a custom endpoint, e.g. a local mock server
+        pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+            self.endpoint = Some(endpoint.into());
+            self
+        }
+    };
+}
+
+/// Heartbeat options of the builders for exchanges expecting application level pings
+macro_rules! heartbeat_options {
+    () => {
+        /// Replace the default heartbeat, e.g. to tune the ping interval
+        pub fn with_heartbeat(mut self, heartbeat: $crate::transport::Heartbeat) -> Self {
+            self.config.heartbeat = Some(heartbeat);
+            self
+        }
+
+        /// Stop sending application level pings
+        pub fn without_heartbeat(mut self) -> Self {
+            self.config.heartbeat = None;
+            self
+        }
+    };
+}
+
+/// `connect_normalized` on top of the builder's `connect`
+macro_rules! connect_normalized {
+    ($exchange:ty) => {
+        /// Connect and return the stream converted to normalized market data
+        pub async fn connect_normalized(self) -> $crate::transport::NormalizedResult<$exchange> {
+            let (stream, handler) = self.connect().await?;
+            Ok((
+                $crate::models::normalized::normalize_stream(stream),
+                handler,
+            ))
+        }
+    };
+}
+
 mod binance;
+mod binance_user_data;
 mod bybit;
 mod coinbase;
 mod kraken;
 
 pub use binance::*;
+pub use binance_user_data::*;
 pub use bybit::*;
 pub use coinbase::*;
 pub use kraken::*;
@@ -19,6 +100,11 @@ impl StreamBuilder {
         BinanceBuilder::default()
     }
 
+    /// Start building a Binance user data stream, with the client managing its listen key
+    pub fn binance_user_data(client: impl BinanceListenKeyClient) -> BinanceUserDataBuilder {
+        BinanceUserDataBuilder::new(client)
+    }
+
     /// Start building a Bybit stream
     pub fn bybit() -> BybitBuilder {
         BybitBuilder::default()
@@ -1,20 +1,35 @@
 use crate::{
     error::ExStreamError,
-    models::{BinanceMessage, BinanceRequest},
-    transport::{ConnectionResult, connect_ws},
+    models::{Binance, BinanceMarket, BinanceRequest, BinanceStream, IntoSymbol},
+    transport::{ConnectionConfig, ConnectionHandle, ConnectionResult, connect_ws},
 };
 
 #[derive(Debug, Clone)]
 pub struct BinanceBuilder {
     request: BinanceRequest,
+    config: ConnectionConfig,
+    endpoint: Option<String>,
+    market: BinanceMarket,
+    testnet: bool,
+    combined: bool,
 }
 
 impl BinanceBuilder {
     pub const ENDPOINT: &str = "wss://stream.binance.com:9443/ws";
+    pub const TESTNET_ENDPOINT: &str = "wss://stream.testnet.binance.vision/ws";
+    pub const USD_M_ENDPOINT: &str = "wss://fstream.binance.com/ws";
+    pub const USD_M_TESTNET_ENDPOINT: &str = "wss://stream.binancefuture.com/ws";
+    pub const COIN_M_ENDPOINT: &str = "wss://dstream.binance.com/ws";
+    pub const COIN_M_TESTNET_ENDPOINT: &str = "wss://dstream.binancefuture.com/ws";
 
     pub fn new() -> Self {
         BinanceBuilder {
             request: BinanceRequest::new_subscribe(),
+            config: ConnectionConfig::default(),
+            endpoint: None,
+            market: BinanceMarket::Spot,
+            testnet: false,
+            combined: false,
         }
     }
 
@@ -23,24 +38,74 @@ impl BinanceBuilder {
         self
     }
 
-    pub fn with_trade(mut self, symbol: impl Into<String>) -> Self {
+    pub fn with_trade(mut self, symbol: impl IntoSymbol) -> Self {
         self.request.add_trade(symbol);
         self
     }
 
-    pub fn with_trades(mut self, symbols: Vec<String>) -> Self {
+    pub fn with_trades(mut self, symbols: Vec<impl IntoSymbol>) -> Self {
         self.request.add_trades(symbols);
         self
     }
 
+    /// Subscribe to a stream of a symbol, e.g. `BinanceStream::PartialDepth(5)`
+    pub fn with_stream(mut self, symbol: impl IntoSymbol, stream: BinanceStream) -> Self {
+        self.request.add_stream(symbol, stream);
+        self
+    }
+
+    pub fn with_streams(mut self, symbols: Vec<impl IntoSymbol>, stream: BinanceStream) -> Self {
+        self.request.add_streams(symbols, stream);
+        self
+    }
+
+    /// Stream from the spot or one of the futures markets, spot by default
+    pub fn with_market(mut self, market: BinanceMarket) -> Self {
+        self.market = market;
+        self
+    }
+
+    /// Use the combined endpoint, which wraps every message in `BinanceMessage::Combined`
+    /// with the name of its stream. Partial books only carry their symbol there.
+    pub fn with_combined_streams(mut self) -> Self {
+        self.combined = true;
+        self
+    }
+
+    endpoint_option!();
+
+    /// Connect to the testnet of the market instead of the live exchange
+    pub fn with_testnet(mut self) -> Self {
+        self.testnet = true;
+        self
+    }
+
+    connection_options!();
+
     // Connect and return the stream
-    pub async fn connect(self) -> ConnectionResult<BinanceMessage> {
+    pub async fn connect(self) -> ConnectionResult<Binance> {
         if self.request.is_empty() {
             return Err(ExStreamError::EmptySubscriptionList);
         }
 
-        connect_ws(Self::ENDPOINT, self.request).await
+        let endpoint = match (self.market, self.testnet) {
+            (BinanceMarket::Spot, false) => Self::ENDPOINT,
+            (BinanceMarket::Spot, true) => Self::TESTNET_ENDPOINT,
+            (BinanceMarket::UsdM, false) => Self::USD_M_ENDPOINT,
+            (BinanceMarket::UsdM, true) => Self::USD_M_TESTNET_ENDPOINT,
+            (BinanceMarket::CoinM, false) => Self::COIN_M_ENDPOINT,
+            (BinanceMarket::CoinM, true) => Self::COIN_M_TESTNET_ENDPOINT,
+        };
+        // The combined endpoint is served from the same host under `/stream`
+        let endpoint = match (&self.endpoint, self.combined) {
+            (Some(endpoint), _) => endpoint.clone(),
+            (None, true) => format!("{}/stream", endpoint.trim_end_matches("/ws")),
+            (None, false) => endpoint.to_string(),
+        };
+        connect_ws::<Binance>(endpoint, vec![self.request], self.config).await
     }
+
+    connect_normalized!(Binance);
 }
 
 impl Default for BinanceBuilder {
@@ -48,3 +113,31 @@ impl Default for BinanceBuilder {
         Self::new()
     }
 }
+
+impl ConnectionHandle<Binance> {
+    /// Subscribe to the trades of a symbol, mirrors `BinanceBuilder::with_trade`
+    pub fn subscribe_trade(&self, symbol: impl IntoSymbol) -> Result<(), ExStreamError> {
+        self.subscribe(BinanceRequest::new_subscribe().with_trade(symbol))
+    }
+
+    pub fn unsubscribe_trade(&self, symbol: impl IntoSymbol) -> Result<(), ExStreamError> {
+        self.unsubscribe(BinanceRequest::new_unsubscribe().with_trade(symbol))
+    }
+
+    /// Subscribe to a stream of a symbol, mirrors `BinanceBuilder::with_stream`
+    pub fn subscribe_stream(
+        &self,
+        symbol: impl IntoSymbol,
+        stream: BinanceStream,
+    ) -> Result<(), ExStreamError> {
+        self.subscribe(BinanceRequest::new_subscribe().with_stream(symbol, stream))
+    }
+
+    pub fn unsubscribe_stream(
+        &self,
+        symbol: impl IntoSymbol,
+        stream: BinanceStream,
+    ) -> Result<(), ExStreamError> {
+        self.unsubscribe(BinanceRequest::new_unsubscribe().with_stream(symbol, stream))
+    }
+}
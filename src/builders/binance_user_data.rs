@@ -0,0 +1,407 @@
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_util::StreamExt as _;
+#[cfg(feature = "binance-rest")]
+use serde::Deserialize;
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    error::ExStreamError,
+    models::{BinanceUserData, BinanceUserDataMessage},
+    transport::{ConnectionConfig, ConnectionHandler, WsMsgStream, connect_ws, inbound},
+};
+
+/// REST calls managing the listen key of a user data stream
+#[async_trait]
+pub trait BinanceListenKeyClient: Send + Sync + 'static {
+    /// Create a listen key, or get the one already active for the account
+    async fn create(&self) -> Result<String, ExStreamError>;
+
+    /// Extend the validity of the listen key by 60 minutes
+    async fn keepalive(&self, listen_key: &str) -> Result<(), ExStreamError>;
+
+    /// Invalidate the listen key
+    async fn close(&self, listen_key: &str) -> Result<(), ExStreamError>;
+}
+
+/// Manage listen keys through the Binance REST `/api/v3/userDataStream` endpoint
+#[cfg(feature = "binance-rest")]
+#[derive(Debug, Clone)]
+pub struct BinanceListenKeyRest {
+    endpoint: String,
+    api_key: String,
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "binance-rest")]
+#[derive(Deserialize)]
+struct ListenKeyResponse {
+    #[serde(rename = "listenKey")]
+    listen_key: String,
+}
+
+#[cfg(feature = "binance-rest")]
+impl BinanceListenKeyRest {
+    pub const ENDPOINT: &str = "https://api.binance.com";
+    pub const TESTNET_ENDPOINT: &str = "https://testnet.binance.vision";
+    const PATH: &str = "/api/v3/userDataStream";
+
+    /// Listen keys only need the API key, requests are not signed
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self {
+            endpoint: Self::ENDPOINT.to_string(),
+            api_key: api_key.into(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Call a custom base URL, e.g. a local mock server
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_testnet(self) -> Self {
+        self.with_endpoint(Self::TESTNET_ENDPOINT)
+    }
+
+    async fn send(
+        &self,
+        method: reqwest::Method,
+        listen_key: Option<&str>,
+    ) -> Result<String, ExStreamError> {
+        let mut request = self
+            .client
+            .request(method, format!("{}{}", self.endpoint, Self::PATH))
+            .header("X-MBX-APIKEY", &self.api_key);
+        if let Some(listen_key) = listen_key {
+            request = request.query(&[("listenKey", listen_key)]);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| ExStreamError::RestError(e.to_string()))?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| ExStreamError::RestError(e.to_string()))?;
+        if !status.is_success() {
+            return Err(ExStreamError::RestError(format!("{status}: {text}")));
+        }
+        Ok(text)
+    }
+}
+
+#[cfg(feature = "binance-rest")]
+#[async_trait]
+impl BinanceListenKeyClient for BinanceListenKeyRest {
+    async fn create(&self) -> Result<String, ExStreamError> {
+        let text = self.send(reqwest::Method::POST, None).await?;
+        let response: ListenKeyResponse =
+            serde_json::from_str(&text).map_err(|error| ExStreamError::ParseError {
+                error,
+                raw_content: text,
+            })?;
+        Ok(response.listen_key)
+    }
+
+    async fn keepalive(&self, listen_key: &str) -> Result<(), ExStreamError> {
+        self.send(reqwest::Method::PUT, Some(listen_key)).await?;
+        Ok(())
+    }
+
+    async fn close(&self, listen_key: &str) -> Result<(), ExStreamError> {
+        self.send(reqwest::Method::DELETE, Some(listen_key)).await?;
+        Ok(())
+    }
+}
+
+/// Builder of the Binance user data stream. The listen key is kept alive on schedule,
+/// and replaced with a new connection when it expires or can no longer be extended.
+#[derive(Clone)]
+pub struct BinanceUserDataBuilder {
+    client: Arc<dyn BinanceListenKeyClient>,
+    config: ConnectionConfig,
+    endpoint: Option<String>,
+    keepalive: Duration,
+}
+
+impl BinanceUserDataBuilder {
+    pub const ENDPOINT: &str = "wss://stream.binance.com:9443";
+    pub const TESTNET_ENDPOINT: &str = "wss://stream.testnet.binance.vision";
+    /// Binance expires listen keys after 60 minutes without keepalive
+    pub const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
+    pub fn new(client: impl BinanceListenKeyClient) -> Self {
+        Self {
+            client: Arc::new(client),
+            config: ConnectionConfig::default(),
+            endpoint: None,
+            keepalive: Self::KEEPALIVE_INTERVAL,
+        }
+    }
+
+    /// Connect to a custom base URL, e.g. a local mock server. `/ws/<listenKey>` is appended to it.
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = Some(endpoint.into().trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Connect to the spot testnet, the listen key client must use the testnet too
+    pub fn with_testnet(self) -> Self {
+        self.with_endpoint(Self::TESTNET_ENDPOINT)
+    }
+
+    /// Time between two listen key keepalives, 30 minutes by default
+    pub fn with_keepalive_interval(mut self, interval: Duration) -> Self {
+        self.keepalive = interval;
+        self
+    }
+
+    connection_options!(@stream);
+
+    /// Create a listen key, connect and return the stream
+    pub async fn connect(
+        self,
+    ) -> Result<(WsMsgStream<BinanceUserDataMessage>, BinanceUserDataHandler), ExStreamError> {
+        let endpoint = self.endpoint.as_deref().unwrap_or(Self::ENDPOINT);
+        let session = UserDataSession {
+            client: self.client,
+            config: self.config,
+            endpoint: endpoint.to_string(),
+            listen_key: Arc::default(),
+        };
+        let (stream, handler) = session.open().await?;
+
+        let (messages_tx, messages_rx) = inbound::channel(session.config.buffer);
+        let listen_key = session.listen_key.clone();
+        let shutdown = CancellationToken::new();
+        let task = tokio::spawn(session.run(
+            stream,
+            handler,
+            self.keepalive,
+            messages_tx,
+            shutdown.clone(),
+        ));
+
+        let handler = BinanceUserDataHandler {
+            listen_key,
+            shutdown,
+            task,
+        };
+        Ok((messages_rx.into_stream(), handler))
+    }
+}
+
+/// Owns the user data stream, which is shut down when the handler is dropped
+#[derive(Debug)]
+pub struct BinanceUserDataHandler {
+    listen_key: Arc<RwLock<String>>,
+    shutdown: CancellationToken,
+    task: JoinHandle<()>,
+}
+
+impl BinanceUserDataHandler {
+    /// Listen key of the current connection
+    pub fn listen_key(&self) -> String {
+        self.listen_key
+            .read()
+            .expect("listen key lock poisoned")
+            .clone()
+    }
+
+    /// Close the listen key and the connection, aborting when still running after the timeout
+    pub async fn shutdown(mut self, timeout: Duration) -> Result<(), ExStreamError> {
+        tracing::info!("Shutting down Binance user data stream");
+        self.shutdown.cancel();
+
+        match tokio::time::timeout(timeout, &mut self.task).await {
+            Ok(result) => result.map_err(ExStreamError::TaskError),
+            Err(_) => {
+                tracing::warn!("User data task still running after {:?}, aborting", timeout);
+                self.task.abort();
+                Err(ExStreamError::ShutdownTimeout(timeout))
+            }
+        }
+    }
+
+    /// Check if the user data task is still running
+    pub fn tasks_running(&self) -> bool {
+        !self.task.is_finished()
+    }
+}
+
+impl Drop for BinanceUserDataHandler {
+    fn drop(&mut self) {
+        self.shutdown.cancel();
+    }
+}
+
+type UserDataSender = inbound::InboundSender<BinanceUserDataMessage>;
+
+/// Forward a message to the consumer, returns false when the stream no longer accepts messages
+async fn forward(
+    messages_tx: &UserDataSender,
+    message: Result<BinanceUserDataMessage, ExStreamError>,
+    shutdown: &CancellationToken,
+) -> bool {
+    tokio::select! {
+        result = messages_tx.send(message) => result.is_ok(),
+        _ = shutdown.cancelled() => false,
+    }
+}
+
+/// Listen key and connection state owned by the user data task
+struct UserDataSession {
+    client: Arc<dyn BinanceListenKeyClient>,
+    config: ConnectionConfig,
+    endpoint: String,
+    listen_key: Arc<RwLock<String>>,
+}
+
+impl UserDataSession {
+    /// Create a listen key and connect to its stream
+    async fn open(
+        &self,
+    ) -> Result<
+        (
+            WsMsgStream<BinanceUserDataMessage>,
+            ConnectionHandler<BinanceUserData>,
+        ),
+        ExStreamError,
+    > {
+        let listen_key = self.client.create().await?;
+        let endpoint = format!("{}/ws/{}", self.endpoint, listen_key);
+        let connection =
+            match connect_ws::<BinanceUserData>(endpoint, Vec::new(), self.config.clone()).await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    self.close(&listen_key).await;
+                    return Err(e);
+                }
+            };
+        *self.listen_key.write().expect("listen key lock poisoned") = listen_key;
+        Ok(connection)
+    }
+
+    async fn close(&self, listen_key: &str) {
+        if let Err(e) = self.client.close(listen_key).await {
+            tracing::warn!("Failed to close Binance listen key: {}", e);
+        }
+    }
+
+    /// Replace the listen key and the connection, retrying following the reconnect policy.
+    /// The previous listen key is closed once the new connection is open, unless Binance
+    /// handed the same key back. It is left open when giving up, for the caller to close.
+    async fn renew(
+        &self,
+        handler: &ConnectionHandler<BinanceUserData>,
+        messages_tx: &UserDataSender,
+        shutdown: &CancellationToken,
+    ) -> Option<(
+        WsMsgStream<BinanceUserDataMessage>,
+        ConnectionHandler<BinanceUserData>,
+    )> {
+        handler.shutdown_sync();
+        let previous_key = self
+            .listen_key
+            .read()
+            .expect("listen key lock poisoned")
+            .clone();
+        let policy = &self.config.reconnect;
+        let mut attempt = 0;
+        loop {
+            match self.open().await {
+                Ok(connection) => {
+                    let replaced =
+                        *self.listen_key.read().expect("listen key lock poisoned") != previous_key;
+                    if replaced {
+                        self.close(&previous_key).await;
+                    }
+                    return Some(connection);
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to renew Binance listen key: {}", e);
+                    if !forward(messages_tx, Err(e), shutdown).await {
+                        return None;
+                    }
+                }
+            }
+
+            attempt += 1;
+            if !policy.allows(attempt) {
+                tracing::warn!("Giving up renewing Binance listen key");
+                return None;
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(policy.backoff(attempt)) => {}
+                _ = shutdown.cancelled() => return None,
+            }
+        }
+    }
+
+    async fn run(
+        self,
+        mut stream: WsMsgStream<BinanceUserDataMessage>,
+        mut handler: ConnectionHandler<BinanceUserData>,
+        keepalive: Duration,
+        messages_tx: UserDataSender,
+        shutdown: CancellationToken,
+    ) {
+        let mut keepalive = tokio::time::interval_at(Instant::now() + keepalive, keepalive);
+        keepalive.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            let renew = tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = keepalive.tick() => {
+                    let listen_key = self.listen_key.read().expect("listen key lock poisoned").clone();
+                    match self.client.keepalive(&listen_key).await {
+                        Ok(()) => false,
+                        Err(e) => {
+                            // The key is most likely gone, a new one is needed
+                            tracing::warn!("Failed to keep Binance listen key alive: {}", e);
+                            true
+                        }
+                    }
+                }
+                message = stream.next() => match message {
+                    Some(message) => {
+                        let expired = matches!(message, Ok(BinanceUserDataMessage::ListenKeyExpired(_)));
+                        if !forward(&messages_tx, message, &shutdown).await {
+                            break;
+                        }
+                        expired
+                    }
+                    // The connection gave up reconnecting
+                    None => break,
+                },
+            };
+
+            if renew {
+                match self.renew(&handler, &messages_tx, &shutdown).await {
+                    Some((new_stream, new_handler)) => {
+                        stream = new_stream;
+                        handler = new_handler;
+                        keepalive.reset();
+                    }
+                    None => break,
+                }
+            }
+        }
+
+        handler.shutdown_sync();
+        let listen_key = self
+            .listen_key
+            .read()
+            .expect("listen key lock poisoned")
+            .clone();
+        self.close(&listen_key).await;
+    }
+}
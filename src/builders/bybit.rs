@@ -1,49 +1,153 @@
 use crate::{
     error::ExStreamError,
-    models::{BybitMessage, BybitRequest},
-    transport::{ConnectionResult, connect_ws},
+    models::{
+        Bybit, BybitCategory, BybitRequest, IntoSymbol, merge_tickers, normalized::normalize_stream,
+    },
+    transport::{
+        ConnectionConfig, ConnectionHandle, ConnectionResult, Heartbeat, NormalizedResult,
+        connect_ws,
+    },
 };
 
 #[derive(Debug, Clone)]
 pub struct BybitBuilder {
     request: BybitRequest,
+    config: ConnectionConfig,
+    endpoint: Option<String>,
+    category: BybitCategory,
+    testnet: bool,
 }
 
 impl BybitBuilder {
     pub const ENDPOINT: &str = "wss://stream.bybit.com/v5/public/spot";
+    pub const TESTNET_ENDPOINT: &str = "wss://stream-testnet.bybit.com/v5/public/spot";
+    pub const LINEAR_ENDPOINT: &str = "wss://stream.bybit.com/v5/public/linear";
+    pub const LINEAR_TESTNET_ENDPOINT: &str = "wss://stream-testnet.bybit.com/v5/public/linear";
+    pub const INVERSE_ENDPOINT: &str = "wss://stream.bybit.com/v5/public/inverse";
+    pub const INVERSE_TESTNET_ENDPOINT: &str = "wss://stream-testnet.bybit.com/v5/public/inverse";
+    pub const OPTION_ENDPOINT: &str = "wss://stream.bybit.com/v5/public/option";
+    pub const OPTION_TESTNET_ENDPOINT: &str = "wss://stream-testnet.bybit.com/v5/public/option";
 
     pub fn with_id(mut self, id_str: String) -> Self {
         self.request.id = Some(id_str);
         self
     }
 
-    pub fn with_trade(mut self, symbol: impl Into<String>) -> Self {
+    pub fn with_trade(mut self, symbol: impl IntoSymbol) -> Self {
         self.request.add_trade(symbol);
         self
     }
 
-    pub fn with_trades(mut self, symbols: Vec<impl Into<String>>) -> Self {
+    pub fn with_trades(mut self, symbols: Vec<impl IntoSymbol>) -> Self {
         self.request.add_trades(symbols);
         self
     }
 
-    pub fn with_orderbook(mut self, symbol: impl Into<String>, depth: u64) -> Self {
+    /// Subscribe to the orderbook of a symbol, the depth must be one of
+    /// `BybitCategory::orderbook_depths` of the category, checked on connect
+    pub fn with_orderbook(mut self, symbol: impl IntoSymbol, depth: u64) -> Self {
         self.request.add_orderbook(symbol, depth);
         self
     }
 
-    pub fn with_orderbooks(mut self, symbols: Vec<impl Into<String>>, depth: u64) -> Self {
+    pub fn with_orderbooks(mut self, symbols: Vec<impl IntoSymbol>, depth: u64) -> Self {
         self.request.add_orderbooks(symbols, depth);
         self
     }
 
+    /// Subscribe to the ticker of a symbol, derivatives send deltas after the first snapshot
+    pub fn with_ticker(mut self, symbol: impl IntoSymbol) -> Self {
+        self.request.add_ticker(symbol);
+        self
+    }
+
+    pub fn with_tickers(mut self, symbols: Vec<impl IntoSymbol>) -> Self {
+        self.request.add_tickers(symbols);
+        self
+    }
+
+    /// Subscribe to the klines of a symbol, e.g. an interval of `5` minutes or `D`
+    pub fn with_kline(mut self, symbol: impl IntoSymbol, interval: impl Into<String>) -> Self {
+        self.request.add_kline(symbol, interval);
+        self
+    }
+
+    /// Subscribe to the liquidations of a perpetual or future
+    pub fn with_liquidation(mut self, symbol: impl IntoSymbol) -> Self {
+        self.request.add_liquidation(symbol);
+        self
+    }
+
+    /// Subscribe to the ticker of a leveraged token, e.g. `EOS3LUSDT`
+    pub fn with_lt_ticker(mut self, symbol: impl IntoSymbol) -> Self {
+        self.request.add_lt_ticker(symbol);
+        self
+    }
+
+    pub fn with_lt_kline(mut self, symbol: impl IntoSymbol, interval: impl Into<String>) -> Self {
+        self.request.add_lt_kline(symbol, interval);
+        self
+    }
+
+    /// Subscribe to the net asset value of a leveraged token
+    pub fn with_lt_nav(mut self, symbol: impl IntoSymbol) -> Self {
+        self.request.add_lt_nav(symbol);
+        self
+    }
+
+    /// Stream the spot, perpetuals and futures, or options category, spot by default.
+    /// Option trades are subscribed by base coin, e.g. `BTC`.
+    pub fn with_category(mut self, category: BybitCategory) -> Self {
+        self.category = category;
+        self
+    }
+
+    endpoint_option!();
+
+    /// Connect to the testnet of the category instead of the live exchange
+    pub fn with_testnet(mut self) -> Self {
+        self.testnet = true;
+        self
+    }
+
+    connection_options!();
+
+    heartbeat_options!();
+
     // Connect and return the stream
-    pub async fn connect(self) -> ConnectionResult<BybitMessage> {
+    pub async fn connect(self) -> ConnectionResult<Bybit> {
         if self.request.is_empty() {
             return Err(ExStreamError::EmptySubscriptionList);
         }
 
-        connect_ws(Self::ENDPOINT, self.request).await
+        self.category.validate(&self.request)?;
+
+        let endpoint = match (self.category, self.testnet) {
+            (BybitCategory::Spot, false) => Self::ENDPOINT,
+            (BybitCategory::Spot, true) => Self::TESTNET_ENDPOINT,
+            (BybitCategory::Linear, false) => Self::LINEAR_ENDPOINT,
+            (BybitCategory::Linear, true) => Self::LINEAR_TESTNET_ENDPOINT,
+            (BybitCategory::Inverse, false) => Self::INVERSE_ENDPOINT,
+            (BybitCategory::Inverse, true) => Self::INVERSE_TESTNET_ENDPOINT,
+            (BybitCategory::Option, false) => Self::OPTION_ENDPOINT,
+            (BybitCategory::Option, true) => Self::OPTION_TESTNET_ENDPOINT,
+        };
+        let endpoint = self.endpoint.as_deref().unwrap_or(endpoint);
+        let category = self.category;
+        let (stream, handler) =
+            connect_ws::<Bybit>(endpoint, vec![self.request], self.config).await?;
+        // Subscriptions added later go to the same endpoint, check them the same way
+        Ok((
+            stream,
+            handler.with_request_check(move |request| category.validate(request)),
+        ))
+    }
+
+    /// Connect and return the stream converted to normalized market data.
+    /// Ticker deltas are merged into the snapshot of their symbol first.
+    pub async fn connect_normalized(self) -> NormalizedResult<Bybit> {
+        let (stream, handler) = self.connect().await?;
+        Ok((normalize_stream(merge_tickers(stream)), handler))
     }
 }
 
@@ -51,6 +155,77 @@ impl Default for BybitBuilder {
     fn default() -> Self {
         BybitBuilder {
             request: BybitRequest::new_subscribe(),
+            config: ConnectionConfig {
+                heartbeat: Some(Heartbeat::bybit()),
+                ..ConnectionConfig::default()
+            },
+            endpoint: None,
+            category: BybitCategory::Spot,
+            testnet: false,
         }
     }
 }
+
+impl ConnectionHandle<Bybit> {
+    /// Subscribe to the trades of a symbol, mirrors `BybitBuilder::with_trade`
+    pub fn subscribe_trade(&self, symbol: impl IntoSymbol) -> Result<(), ExStreamError> {
+        self.subscribe(BybitRequest::new_subscribe().with_trade(symbol))
+    }
+
+    pub fn unsubscribe_trade(&self, symbol: impl IntoSymbol) -> Result<(), ExStreamError> {
+        self.unsubscribe(BybitRequest::new_unsubscribe().with_trade(symbol))
+    }
+
+    /// Subscribe to the orderbook of a symbol, mirrors `BybitBuilder::with_orderbook`.
+    /// The depth is checked against the category of the connection.
+    pub fn subscribe_orderbook(
+        &self,
+        symbol: impl IntoSymbol,
+        depth: u64,
+    ) -> Result<(), ExStreamError> {
+        self.subscribe(BybitRequest::new_subscribe().with_orderbook(symbol, depth))
+    }
+
+    pub fn unsubscribe_orderbook(
+        &self,
+        symbol: impl IntoSymbol,
+        depth: u64,
+    ) -> Result<(), ExStreamError> {
+        self.unsubscribe(BybitRequest::new_unsubscribe().with_orderbook(symbol, depth))
+    }
+
+    /// Subscribe to the ticker of a symbol, mirrors `BybitBuilder::with_ticker`
+    pub fn subscribe_ticker(&self, symbol: impl IntoSymbol) -> Result<(), ExStreamError> {
+        self.subscribe(BybitRequest::new_subscribe().with_ticker(symbol))
+    }
+
+    pub fn unsubscribe_ticker(&self, symbol: impl IntoSymbol) -> Result<(), ExStreamError> {
+        self.unsubscribe(BybitRequest::new_unsubscribe().with_ticker(symbol))
+    }
+
+    /// Subscribe to the klines of a symbol, mirrors `BybitBuilder::with_kline`
+    pub fn subscribe_kline(
+        &self,
+        symbol: impl IntoSymbol,
+        interval: impl Into<String>,
+    ) -> Result<(), ExStreamError> {
+        self.subscribe(BybitRequest::new_subscribe().with_kline(symbol, interval))
+    }
+
+    pub fn unsubscribe_kline(
+        &self,
+        symbol: impl IntoSymbol,
+        interval: impl Into<String>,
+    ) -> Result<(), ExStreamError> {
+        self.unsubscribe(BybitRequest::new_unsubscribe().with_kline(symbol, interval))
+    }
+
+    /// Subscribe to the liquidations of a symbol, mirrors `BybitBuilder::with_liquidation`
+    pub fn subscribe_liquidation(&self, symbol: impl IntoSymbol) -> Result<(), ExStreamError> {
+        self.subscribe(BybitRequest::new_subscribe().with_liquidation(symbol))
+    }
+
+    pub fn unsubscribe_liquidation(&self, symbol: impl IntoSymbol) -> Result<(), ExStreamError> {
+        self.unsubscribe(BybitRequest::new_unsubscribe().with_liquidation(symbol))
+    }
+}
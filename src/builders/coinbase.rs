@@ -1,41 +1,69 @@
 use crate::{
     error::ExStreamError,
-    models::{CoinbaseMessage, CoinbaseRequest},
-    transport::{ConnectionResult, connect_ws},
+    models::{Coinbase, CoinbaseRequest, IntoSymbol},
+    transport::{ConnectionConfig, ConnectionHandle, ConnectionResult, connect_ws},
 };
 
 #[derive(Debug, Clone)]
 pub struct CoinbaseBuilder {
     request: CoinbaseRequest,
+    config: ConnectionConfig,
+    endpoint: Option<String>,
 }
 
 impl CoinbaseBuilder {
     pub const ENDPOINT: &str = "wss://ws-feed.exchange.coinbase.com";
+    pub const SANDBOX_ENDPOINT: &str = "wss://ws-feed-public.sandbox.exchange.coinbase.com";
 
-    pub fn with_trade(mut self, symbol: impl Into<String>) -> Self {
+    pub fn with_trade(mut self, symbol: impl IntoSymbol) -> Self {
         self.request.add_trade(symbol);
         self
     }
 
-    pub fn with_trades(mut self, symbols: Vec<impl Into<String>>) -> Self {
+    pub fn with_trades(mut self, symbols: Vec<impl IntoSymbol>) -> Self {
         self.request.add_trades(symbols);
         self
     }
 
+    endpoint_option!();
+
+    /// Connect to the sandbox instead of the live exchange
+    pub fn with_sandbox(self) -> Self {
+        self.with_endpoint(Self::SANDBOX_ENDPOINT)
+    }
+
+    connection_options!();
+
     // Connect and return the stream
-    pub async fn connect(self) -> ConnectionResult<CoinbaseMessage> {
+    pub async fn connect(self) -> ConnectionResult<Coinbase> {
         if self.request.is_empty() {
             return Err(ExStreamError::EmptySubscriptionList);
         }
 
-        connect_ws(Self::ENDPOINT, self.request).await
+        let endpoint = self.endpoint.as_deref().unwrap_or(Self::ENDPOINT);
+        connect_ws::<Coinbase>(endpoint, vec![self.request], self.config).await
     }
+
+    connect_normalized!(Coinbase);
 }
 
 impl Default for CoinbaseBuilder {
     fn default() -> Self {
         CoinbaseBuilder {
             request: CoinbaseRequest::new_subscribe(),
+            config: ConnectionConfig::default(),
+            endpoint: None,
         }
     }
 }
+
+impl ConnectionHandle<Coinbase> {
+    /// Subscribe to the trades of a product, mirrors `CoinbaseBuilder::with_trade`
+    pub fn subscribe_trade(&self, symbol: impl IntoSymbol) -> Result<(), ExStreamError> {
+        self.subscribe(CoinbaseRequest::new_subscribe().with_trade(symbol))
+    }
+
+    pub fn unsubscribe_trade(&self, symbol: impl IntoSymbol) -> Result<(), ExStreamError> {
+        self.unsubscribe(CoinbaseRequest::new_unsubscribe().with_trade(symbol))
+    }
+}
@@ -1,66 +1,218 @@
 use crate::{
     error::ExStreamError,
-    models::{KrakenChannel, KrakenMessage, KrakenRequest},
-    transport::{ConnectionResult, connect_ws},
+    models::{IntoSymbol, Kraken, KrakenChannel, KrakenEventTrigger, KrakenRequest},
+    transport::{ConnectionConfig, ConnectionHandle, ConnectionResult, Heartbeat, connect_ws},
 };
 
 #[derive(Debug, Clone)]
 pub struct KrakenBuilder {
-    request: KrakenRequest,
+    /// One subscribe request per channel, the last one is being configured
+    requests: Vec<KrakenRequest>,
+    token: Option<String>,
+    config: ConnectionConfig,
+    endpoint: Option<String>,
+    auth_endpoint: Option<String>,
 }
 
 impl KrakenBuilder {
     pub const ENDPOINT: &str = "wss://ws.kraken.com/v2";
     pub const ENDPOINT_AUTH: &str = "wss://ws-auth.kraken.com/v2";
+    pub const BETA_ENDPOINT: &str = "wss://beta-ws.kraken.com/v2";
+    pub const BETA_ENDPOINT_AUTH: &str = "wss://beta-ws-auth.kraken.com/v2";
 
     pub fn new(channel: KrakenChannel) -> Self {
         KrakenBuilder {
-            request: KrakenRequest::new_subscribe(channel),
+            requests: vec![KrakenRequest::new_subscribe(channel)],
+            token: None,
+            config: ConnectionConfig {
+                heartbeat: Some(Heartbeat::kraken()),
+                ..ConnectionConfig::default()
+            },
+            endpoint: None,
+            auth_endpoint: None,
         }
     }
 
-    /// Only used for channels requiring authentication
+    /// Subscribe to another channel on the same connection. The symbols and options set
+    /// afterwards apply to this channel, it is sent as a separate subscribe message.
+    pub fn with_channel(mut self, channel: KrakenChannel) -> Self {
+        let mut request = KrakenRequest::new_subscribe(channel);
+        if let Some(token) = &self.token {
+            request.set_token(token.clone());
+        }
+        self.requests.push(request);
+        self
+    }
+
+    /// Only used for channels requiring authentication, applies to every channel
     pub fn with_token(mut self, token: String) -> Self {
-        self.request.set_token(token);
+        for request in &mut self.requests {
+            request.set_token(token.clone());
+        }
+        self.token = Some(token);
         self
     }
 
-    /// Set the depth for L3 channels, possible values 10, 100, 1000
+    /// Set the depth of book channels, possible values 10, 25, 100, 500, 1000 for `Book`
+    /// and 10, 100, 1000 for `L3`
     pub fn with_depth(mut self, depth: u64) -> Self {
-        self.request.set_depth(depth);
+        self.current().set_depth(depth);
+        self
+    }
+
+    /// Set the candle interval of the `Ohlc` channel in minutes,
+    /// possible values 1, 5, 15, 30, 60, 240, 1440, 10080, 21600
+    pub fn with_interval(mut self, interval: u64) -> Self {
+        self.current().set_interval(interval);
+        self
+    }
+
+    /// Set when the `Ticker` channel sends updates, on trades by default
+    pub fn with_event_trigger(mut self, trigger: KrakenEventTrigger) -> Self {
+        self.current().set_event_trigger(trigger);
         self
     }
 
+    /// Set the request id of the channel being configured
     pub fn with_id(mut self, id: u64) -> Self {
-        self.request.set_id(id);
+        self.current().set_id(id);
         self
     }
 
-    pub fn with_symbol(mut self, symbol: impl Into<String>) -> Self {
-        self.request.add_symbol(symbol);
+    pub fn with_symbol(mut self, symbol: impl IntoSymbol) -> Self {
+        self.current().add_symbol(symbol);
         self
     }
 
-    pub fn with_symbols(mut self, symbols: Vec<impl Into<String>>) -> Self {
-        self.request.add_symbols(symbols);
+    pub fn with_symbols(mut self, symbols: Vec<impl IntoSymbol>) -> Self {
+        self.current().add_symbols(symbols);
         self
     }
 
-    // Connect and return the stream
-    pub async fn connect(self) -> ConnectionResult<KrakenMessage> {
-        if self.request.is_empty() {
+    /// Connect to a custom endpoint for public channels, e.g. a local mock server
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = Some(endpoint.into());
+        self
+    }
+
+    /// Connect to a custom endpoint for channels requiring authentication
+    pub fn with_auth_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.auth_endpoint = Some(endpoint.into());
+        self
+    }
+
+    /// Connect to the beta environment instead of the live exchange
+    pub fn with_beta(self) -> Self {
+        self.with_endpoint(Self::BETA_ENDPOINT)
+            .with_auth_endpoint(Self::BETA_ENDPOINT_AUTH)
+    }
+
+    connection_options!();
+
+    heartbeat_options!();
+
+    fn current(&mut self) -> &mut KrakenRequest {
+        self.requests
+            .last_mut()
+            .expect("the builder always holds a request")
+    }
+
+    // Connect and return the stream. The authenticated endpoint, which also serves
+    // the public channels, is used as soon as one channel requires authentication.
+    pub async fn connect(self) -> ConnectionResult<Kraken> {
+        if self.requests.iter().any(KrakenRequest::is_empty) {
             return Err(ExStreamError::EmptySubscriptionList);
         }
 
-        if self.request.is_missing_auth() {
+        if self.requests.iter().any(KrakenRequest::is_missing_auth) {
             return Err(ExStreamError::MissingAuth);
         }
 
-        let endpoint = match self.request.is_auth_required() {
-            true => Self::ENDPOINT_AUTH,
-            false => Self::ENDPOINT,
+        let endpoint = match self.requests.iter().any(KrakenRequest::is_auth_required) {
+            true => self.auth_endpoint.as_deref().unwrap_or(Self::ENDPOINT_AUTH),
+            false => self.endpoint.as_deref().unwrap_or(Self::ENDPOINT),
         };
 
-        connect_ws(endpoint, self.request).await
+        connect_ws::<Kraken>(endpoint, self.requests, self.config).await
+    }
+
+    connect_normalized!(Kraken);
+}
+
+impl ConnectionHandle<Kraken> {
+    /// Subscribe to the trades of a symbol, e.g. "BTC/USD"
+    pub fn subscribe_trade(&self, symbol: impl IntoSymbol) -> Result<(), ExStreamError> {
+        let mut request = KrakenRequest::new_subscribe(KrakenChannel::Trade);
+        request.add_symbol(symbol);
+        self.subscribe(request)
+    }
+
+    pub fn unsubscribe_trade(&self, symbol: impl IntoSymbol) -> Result<(), ExStreamError> {
+        let mut request = KrakenRequest::new_unsubscribe(KrakenChannel::Trade);
+        request.add_symbol(symbol);
+        self.unsubscribe(request)
+    }
+
+    /// Subscribe to the L2 book of a symbol, mirrors `KrakenBuilder::with_depth`
+    pub fn subscribe_book(&self, symbol: impl IntoSymbol, depth: u64) -> Result<(), ExStreamError> {
+        let mut request = KrakenRequest::new_subscribe(KrakenChannel::Book);
+        request.set_depth(depth);
+        request.add_symbol(symbol);
+        self.subscribe(request)
+    }
+
+    pub fn unsubscribe_book(
+        &self,
+        symbol: impl IntoSymbol,
+        depth: u64,
+    ) -> Result<(), ExStreamError> {
+        let mut request = KrakenRequest::new_unsubscribe(KrakenChannel::Book);
+        request.set_depth(depth);
+        request.add_symbol(symbol);
+        self.unsubscribe(request)
+    }
+
+    pub fn subscribe_ticker(&self, symbol: impl IntoSymbol) -> Result<(), ExStreamError> {
+        let mut request = KrakenRequest::new_subscribe(KrakenChannel::Ticker);
+        request.add_symbol(symbol);
+        self.subscribe(request)
+    }
+
+    pub fn unsubscribe_ticker(&self, symbol: impl IntoSymbol) -> Result<(), ExStreamError> {
+        let mut request = KrakenRequest::new_unsubscribe(KrakenChannel::Ticker);
+        request.add_symbol(symbol);
+        self.unsubscribe(request)
+    }
+
+    /// Subscribe to the candles of a symbol, mirrors `KrakenBuilder::with_interval`
+    pub fn subscribe_ohlc(
+        &self,
+        symbol: impl IntoSymbol,
+        interval: u64,
+    ) -> Result<(), ExStreamError> {
+        let mut request = KrakenRequest::new_subscribe(KrakenChannel::Ohlc);
+        request.set_interval(interval);
+        request.add_symbol(symbol);
+        self.subscribe(request)
+    }
+
+    pub fn unsubscribe_ohlc(
+        &self,
+        symbol: impl IntoSymbol,
+        interval: u64,
+    ) -> Result<(), ExStreamError> {
+        let mut request = KrakenRequest::new_unsubscribe(KrakenChannel::Ohlc);
+        request.set_interval(interval);
+        request.add_symbol(symbol);
+        self.unsubscribe(request)
+    }
+
+    /// Subscribe to the reference data of every asset and pair
+    pub fn subscribe_instruments(&self) -> Result<(), ExStreamError> {
+        self.subscribe(KrakenRequest::new_subscribe(KrakenChannel::Instrument))
+    }
+
+    pub fn unsubscribe_instruments(&self) -> Result<(), ExStreamError> {
+        self.unsubscribe(KrakenRequest::new_unsubscribe(KrakenChannel::Instrument))
     }
 }
@@ -22,6 +22,22 @@ pub enum ExStreamError {
     TaskError(#[from] tokio::task::JoinError),
     #[error("Handler error: sending a message after the stream is closed")]
     StreamClosed,
+    #[error("Subscription rejected: {reason}")]
+    SubscriptionRejected { reason: String },
+    #[error("Unsupported request: {0}")]
+    UnsupportedRequest(String),
+    #[error("Inbound buffer overflow, {dropped} messages dropped")]
+    Lagged { dropped: u64 },
+    #[error("Failed to normalize message: {0}")]
+    NormalizeError(String),
+    #[error("Unknown price and quantity precision of {0}, the book checksum cannot be verified")]
+    MissingPrecision(String),
+    #[error("Gave up fetching the {symbol} snapshot after {attempts} attempts")]
+    SnapshotFailed { symbol: String, attempts: u32 },
+    #[error("REST request failed: {0}")]
+    RestError(String),
+    #[error("Shutdown did not complete within {0:?}, tasks were aborted")]
+    ShutdownTimeout(std::time::Duration),
 }
 
 impl From<tokio_tungstenite::tungstenite::Error> for ExStreamError {
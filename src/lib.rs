@@ -1,6 +1,7 @@
 pub mod builders;
 pub mod error;
 pub mod models;
+pub mod orderbook;
 pub mod transport;
 
 pub use builders::StreamBuilder;
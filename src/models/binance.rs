@@ -1,6 +1,21 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::value::RawValue;
 
-use crate::models::{RequestKind, to_upper};
+use crate::error::ExStreamError;
+use crate::models::normalized::{self, BestBidOffer, BookUpdate, MarketData, Normalize, Side};
+use crate::models::{
+    Exchange, ExchangeMessage, Instrument, InstrumentKind, IntoSymbol, RequestKind, StrDecimal,
+    Subscription, SubscriptionAck, SubscriptionRequest, empty_string, to_upper,
+};
+
+/// Marker type for Binance connections
+#[derive(Debug, Clone, Copy)]
+pub struct Binance;
+
+impl Exchange for Binance {
+    type Request = BinanceRequest;
+    type Message = BinanceMessage;
+}
 
 #[derive(Serialize, Debug, Clone)]
 pub struct BinanceRequest {
@@ -10,11 +25,266 @@ pub struct BinanceRequest {
     pub id: Option<u64>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
-#[serde(untagged)]
+/// Binance market, each streamed from its own endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BinanceMarket {
+    #[default]
+    Spot,
+    /// USDⓈ-M futures, margined in USDT or USDC
+    UsdM,
+    /// COIN-M futures, margined in the base asset
+    CoinM,
+}
+
+impl BinanceMarket {
+    /// Instrument of a symbol streamed from this market. Futures symbols are perpetuals,
+    /// dated contracts such as `BTCUSDT_250627` have no [`Instrument`].
+    pub fn instrument(&self, symbol: &str) -> Option<Instrument> {
+        let kind = match self {
+            BinanceMarket::Spot => InstrumentKind::Spot,
+            BinanceMarket::UsdM if symbol.contains('_') => return None,
+            BinanceMarket::CoinM if !symbol.to_uppercase().ends_with("_PERP") => return None,
+            BinanceMarket::UsdM | BinanceMarket::CoinM => InstrumentKind::Perpetual,
+        };
+        Instrument::parse(normalized::Exchange::Binance, symbol, kind)
+    }
+}
+
+/// Contract type of the futures continuous klines
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum BinanceContractType {
+    Perpetual,
+    CurrentQuarter,
+    NextQuarter,
+}
+
+impl BinanceContractType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BinanceContractType::Perpetual => "perpetual",
+            BinanceContractType::CurrentQuarter => "current_quarter",
+            BinanceContractType::NextQuarter => "next_quarter",
+        }
+    }
+}
+
+/// Streams available per symbol
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum BinanceStream {
+    Trade,
+    /// Trades aggregated by taker order and price
+    AggTrade,
+    /// Best bid and offer, pushed on every change
+    BookTicker,
+    /// Top levels of the book every 100ms, possible values 5, 10, 20.
+    /// Futures send them as `BinanceMessage::DepthUpdate`.
+    PartialDepth(u64),
+    /// Changed levels of the book every 100ms, see `BinanceDepthUpdate`
+    DiffDepth,
+    /// Candles of the interval, e.g. `1m`, `4h` or `1d`
+    Kline(String),
+    /// Rolling 24h statistics without the best bid and offer
+    MiniTicker,
+    /// Rolling 24h statistics
+    Ticker,
+    /// Futures mark price and funding rate, every second or every 3 seconds
+    MarkPrice {
+        every_second: bool,
+    },
+    /// Futures liquidation orders
+    ForceOrder,
+    /// Futures candles of a contract type, subscribed with the pair, e.g. `btcusdt`
+    ContinuousKline {
+        contract_type: BinanceContractType,
+        interval: String,
+    },
+}
+
+impl BinanceStream {
+    /// Stream name without the symbol, e.g. `depth5@100ms`
+    pub fn name(&self) -> String {
+        match self {
+            BinanceStream::Trade => "trade".to_string(),
+            BinanceStream::AggTrade => "aggTrade".to_string(),
+            BinanceStream::BookTicker => "bookTicker".to_string(),
+            BinanceStream::PartialDepth(levels) => format!("depth{levels}@100ms"),
+            BinanceStream::DiffDepth => "depth@100ms".to_string(),
+            BinanceStream::Kline(interval) => format!("kline_{interval}"),
+            BinanceStream::MiniTicker => "miniTicker".to_string(),
+            BinanceStream::Ticker => "ticker".to_string(),
+            BinanceStream::MarkPrice { every_second: true } => "markPrice@1s".to_string(),
+            BinanceStream::MarkPrice {
+                every_second: false,
+            } => "markPrice".to_string(),
+            BinanceStream::ForceOrder => "forceOrder".to_string(),
+            BinanceStream::ContinuousKline { interval, .. } => {
+                format!("continuousKline_{interval}")
+            }
+        }
+    }
+
+    /// Full stream name, e.g. `btcusdt@trade` or `btcusdt_perpetual@continuousKline_1m`
+    pub fn stream_name(&self, symbol: &str) -> String {
+        let symbol = symbol.to_lowercase();
+        match self {
+            BinanceStream::ContinuousKline { contract_type, .. } => {
+                format!("{symbol}_{}@{}", contract_type.as_str(), self.name())
+            }
+            _ => format!("{symbol}@{}", self.name()),
+        }
+    }
+}
+
+/// Messages are told apart by their event type `e` rather than by trying every variant.
+/// Book tickers and partial books carry no event type and are recognised by their fields.
+#[derive(Debug, Clone)]
 pub enum BinanceMessage {
     SubscriptionAck(BinanceAck),
+    Error(BinanceError),
     Trade(BinanceTrade),
+    AggTrade(BinanceAggTrade),
+    BookTicker(BinanceBookTicker),
+    PartialDepth(BinancePartialDepth),
+    DepthUpdate(BinanceDepthUpdate),
+    Kline(BinanceKline),
+    MiniTicker(BinanceMiniTicker),
+    Ticker(Box<BinanceTicker>),
+    MarkPrice(BinanceMarkPrice),
+    ForceOrder(BinanceForceOrder),
+    ContinuousKline(BinanceContinuousKline),
+    /// Message of the combined endpoint, tagged with the stream it belongs to
+    Combined {
+        /// Stream name, e.g. `btcusdt@depth5@100ms`
+        stream: String,
+        data: Box<BinanceMessage>,
+    },
+}
+
+/// Fields telling the messages apart
+#[derive(Deserialize)]
+struct BinanceFrame {
+    stream: Option<String>,
+    data: Option<Box<RawValue>>,
+    #[serde(rename = "e")]
+    event_type: Option<String>,
+    #[serde(rename = "lastUpdateId")]
+    last_update_id: Option<u64>,
+    #[serde(rename = "u")]
+    update_id: Option<u64>,
+}
+
+/// Answers to requests, which have no event type
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BinanceReply {
+    Ack(BinanceAck),
+    Error(BinanceError),
+}
+
+/// `<symbol>@depth<levels>` with or without an update speed, unlike the diff `<symbol>@depth`
+fn is_partial_depth_stream(stream: &str) -> bool {
+    stream
+        .split('@')
+        .nth(1)
+        .and_then(|channel| channel.strip_prefix("depth"))
+        .is_some_and(|levels| levels.starts_with(|c: char| c.is_ascii_digit()))
+}
+
+impl BinanceMessage {
+    /// Parse a message, the stream name of the combined endpoint gives the symbol
+    /// of partial books which do not carry it
+    fn parse(text: &str, stream: Option<&str>) -> Result<Self, serde_json::Error> {
+        use serde::de::Error;
+
+        let frame: BinanceFrame = serde_json::from_str(text)?;
+        if let (Some(stream), Some(data)) = (frame.stream, frame.data) {
+            let data = Self::parse(data.get(), Some(&stream))?;
+            return Ok(BinanceMessage::Combined {
+                stream,
+                data: Box::new(data),
+            });
+        }
+
+        let message = match frame.event_type.as_deref() {
+            Some("trade") => BinanceMessage::Trade(serde_json::from_str(text)?),
+            Some("aggTrade") => BinanceMessage::AggTrade(serde_json::from_str(text)?),
+            Some("bookTicker") => BinanceMessage::BookTicker(serde_json::from_str(text)?),
+            // Futures send their partial books as depth updates, only the stream tells
+            Some("depthUpdate") if stream.is_some_and(is_partial_depth_stream) => {
+                let update: BinanceDepthUpdate = serde_json::from_str(text)?;
+                BinanceMessage::PartialDepth(BinancePartialDepth {
+                    last_update_id: update.final_update_id,
+                    bids: update.bids,
+                    asks: update.asks,
+                    symbol: Some(update.symbol),
+                })
+            }
+            Some("depthUpdate") => BinanceMessage::DepthUpdate(serde_json::from_str(text)?),
+            Some("kline") => BinanceMessage::Kline(serde_json::from_str(text)?),
+            Some("24hrMiniTicker") => BinanceMessage::MiniTicker(serde_json::from_str(text)?),
+            Some("24hrTicker") => BinanceMessage::Ticker(serde_json::from_str(text)?),
+            Some("markPriceUpdate") => BinanceMessage::MarkPrice(serde_json::from_str(text)?),
+            Some("forceOrder") => BinanceMessage::ForceOrder(serde_json::from_str(text)?),
+            Some("continuous_kline") => {
+                BinanceMessage::ContinuousKline(serde_json::from_str(text)?)
+            }
+            Some(other) => {
+                return Err(serde_json::Error::custom(format!(
+                    "unknown Binance event type {other}"
+                )));
+            }
+            None if frame.last_update_id.is_some() => {
+                let mut depth: BinancePartialDepth = serde_json::from_str(text)?;
+                depth.symbol = stream
+                    .and_then(|stream| stream.split('@').next())
+                    .map(str::to_uppercase);
+                BinanceMessage::PartialDepth(depth)
+            }
+            None if frame.update_id.is_some() => {
+                BinanceMessage::BookTicker(serde_json::from_str(text)?)
+            }
+            None => match serde_json::from_str(text)? {
+                BinanceReply::Ack(ack) => BinanceMessage::SubscriptionAck(ack),
+                BinanceReply::Error(error) => BinanceMessage::Error(error),
+            },
+        };
+        Ok(message)
+    }
+
+    /// Stream name, only known on the combined endpoint
+    pub fn stream(&self) -> Option<&str> {
+        match self {
+            BinanceMessage::Combined { stream, .. } => Some(stream),
+            _ => None,
+        }
+    }
+
+    /// The message itself, without the combined endpoint wrapper
+    pub fn data(&self) -> &BinanceMessage {
+        match self {
+            BinanceMessage::Combined { data, .. } => data.data(),
+            message => message,
+        }
+    }
+
+    /// Instrument of a market data message received on a connection to `market`,
+    /// the kind follows the market whatever the payload
+    pub fn instrument_in(&self, market: BinanceMarket) -> Option<Instrument> {
+        market.instrument(self.symbol()?)
+    }
+}
+
+impl<'de> Deserialize<'de> for BinanceMessage {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        let raw = Box::<RawValue>::deserialize(deserializer)?;
+        Self::parse(raw.get(), None).map_err(D::Error::custom)
+    }
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -24,6 +294,54 @@ pub struct BinanceAck {
     pub id: Option<u64>,
 }
 
+/// Error answer to a request, e.g. an invalid subscription
+#[derive(Deserialize, Debug, Clone)]
+#[serde(from = "BinanceErrorFormat")]
+pub struct BinanceError {
+    pub code: i64,
+    #[serde(rename = "msg")]
+    pub message: String,
+    pub id: Option<u64>,
+}
+
+/// Binance sends errors either flat or nested under an `error` field
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BinanceErrorFormat {
+    Nested {
+        error: BinanceErrorDetail,
+        id: Option<u64>,
+    },
+    Flat {
+        code: i64,
+        msg: String,
+        id: Option<u64>,
+    },
+}
+
+#[derive(Deserialize)]
+struct BinanceErrorDetail {
+    code: i64,
+    msg: String,
+}
+
+impl From<BinanceErrorFormat> for BinanceError {
+    fn from(format: BinanceErrorFormat) -> Self {
+        match format {
+            BinanceErrorFormat::Nested { error, id } => BinanceError {
+                code: error.code,
+                message: error.msg,
+                id,
+            },
+            BinanceErrorFormat::Flat { code, msg, id } => BinanceError {
+                code,
+                message: msg,
+                id,
+            },
+        }
+    }
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct BinanceTrade {
     /// Event type
@@ -40,21 +358,379 @@ pub struct BinanceTrade {
     pub trade_id: u64,
     /// Price of the trade
     #[serde(rename = "p")]
-    pub price: String,
+    pub price: StrDecimal,
     /// Quantity of the trade
     #[serde(rename = "q")]
-    pub quantity: String,
+    pub quantity: StrDecimal,
     /// Trade time
     #[serde(rename = "T")]
     pub trade_time: u64,
     /// Is the buyer the market maker?
     #[serde(rename = "m")]
     pub is_market_maker: bool,
-    /// Ignore field
-    #[serde(rename = "M")]
+    /// Ignore field, not sent by futures
+    #[serde(rename = "M", default)]
     pub ignore: bool,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+pub struct BinanceAggTrade {
+    #[serde(rename = "e")]
+    pub event_type: String,
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    /// Aggregate trade ID
+    #[serde(rename = "a")]
+    pub agg_trade_id: u64,
+    #[serde(rename = "p")]
+    pub price: StrDecimal,
+    #[serde(rename = "q")]
+    pub quantity: StrDecimal,
+    /// First trade ID of the aggregate
+    #[serde(rename = "f")]
+    pub first_trade_id: u64,
+    /// Last trade ID of the aggregate
+    #[serde(rename = "l")]
+    pub last_trade_id: u64,
+    #[serde(rename = "T")]
+    pub trade_time: u64,
+    /// Is the buyer the market maker?
+    #[serde(rename = "m")]
+    pub is_market_maker: bool,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BinanceBookTicker {
+    /// Order book update ID
+    #[serde(rename = "u")]
+    pub update_id: u64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "b")]
+    pub bid_price: StrDecimal,
+    #[serde(rename = "B")]
+    pub bid_quantity: StrDecimal,
+    #[serde(rename = "a")]
+    pub ask_price: StrDecimal,
+    #[serde(rename = "A")]
+    pub ask_quantity: StrDecimal,
+    /// Transaction time, not sent by spot
+    #[serde(rename = "T")]
+    pub transaction_time: Option<u64>,
+}
+
+pub type BinanceLevel = Vec<StrDecimal>; // [price, quantity]
+
+/// Top levels of the book, replacing the previous ones
+#[derive(Deserialize, Debug, Clone)]
+pub struct BinancePartialDepth {
+    #[serde(rename = "lastUpdateId")]
+    pub last_update_id: u64,
+    pub bids: Vec<BinanceLevel>,
+    pub asks: Vec<BinanceLevel>,
+    /// Not part of the message, taken from the stream name on the combined endpoint
+    #[serde(skip)]
+    pub symbol: Option<String>,
+}
+
+/// Levels changed between two update IDs, a quantity of zero removes the level
+#[derive(Deserialize, Debug, Clone)]
+pub struct BinanceDepthUpdate {
+    #[serde(rename = "e")]
+    pub event_type: String,
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    /// First update ID in event
+    #[serde(rename = "U")]
+    pub first_update_id: u64,
+    /// Final update ID in event
+    #[serde(rename = "u")]
+    pub final_update_id: u64,
+    /// Final update ID of the previous event, futures only. Futures update IDs are not
+    /// consecutive, this is what tells whether an event was missed.
+    #[serde(rename = "pu")]
+    pub previous_final_update_id: Option<u64>,
+    /// Transaction time, futures only
+    #[serde(rename = "T")]
+    pub transaction_time: Option<u64>,
+    #[serde(rename = "b")]
+    pub bids: Vec<BinanceLevel>,
+    #[serde(rename = "a")]
+    pub asks: Vec<BinanceLevel>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BinanceKline {
+    #[serde(rename = "e")]
+    pub event_type: String,
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "k")]
+    pub kline: BinanceKlineData,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BinanceKlineData {
+    #[serde(rename = "t")]
+    pub start_time: u64,
+    #[serde(rename = "T")]
+    pub close_time: u64,
+    /// Interval, e.g. `1m`
+    #[serde(rename = "i")]
+    pub interval: String,
+    /// First trade ID, -1 without trades
+    #[serde(rename = "f")]
+    pub first_trade_id: i64,
+    /// Last trade ID, -1 without trades
+    #[serde(rename = "L")]
+    pub last_trade_id: i64,
+    #[serde(rename = "o")]
+    pub open: StrDecimal,
+    #[serde(rename = "c")]
+    pub close: StrDecimal,
+    #[serde(rename = "h")]
+    pub high: StrDecimal,
+    #[serde(rename = "l")]
+    pub low: StrDecimal,
+    /// Base asset volume
+    #[serde(rename = "v")]
+    pub volume: StrDecimal,
+    #[serde(rename = "n")]
+    pub trades: u64,
+    /// Is the kline closed?
+    #[serde(rename = "x")]
+    pub is_closed: bool,
+    /// Quote asset volume
+    #[serde(rename = "q")]
+    pub quote_volume: StrDecimal,
+    /// Taker buy base asset volume
+    #[serde(rename = "V")]
+    pub taker_buy_volume: StrDecimal,
+    /// Taker buy quote asset volume
+    #[serde(rename = "Q")]
+    pub taker_buy_quote_volume: StrDecimal,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BinanceMiniTicker {
+    #[serde(rename = "e")]
+    pub event_type: String,
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "c")]
+    pub close: StrDecimal,
+    #[serde(rename = "o")]
+    pub open: StrDecimal,
+    #[serde(rename = "h")]
+    pub high: StrDecimal,
+    #[serde(rename = "l")]
+    pub low: StrDecimal,
+    /// Base asset volume
+    #[serde(rename = "v")]
+    pub volume: StrDecimal,
+    /// Quote asset volume
+    #[serde(rename = "q")]
+    pub quote_volume: StrDecimal,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BinanceTicker {
+    #[serde(rename = "e")]
+    pub event_type: String,
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "p")]
+    pub price_change: StrDecimal,
+    #[serde(rename = "P")]
+    pub price_change_percent: StrDecimal,
+    #[serde(rename = "w")]
+    pub weighted_average_price: StrDecimal,
+    /// Last price before the 24h window, not sent by futures
+    #[serde(rename = "x")]
+    pub previous_close: Option<StrDecimal>,
+    #[serde(rename = "c")]
+    pub last_price: StrDecimal,
+    #[serde(rename = "Q")]
+    pub last_quantity: StrDecimal,
+    /// Best bid and offer, not sent by futures
+    #[serde(rename = "b")]
+    pub bid_price: Option<StrDecimal>,
+    #[serde(rename = "B")]
+    pub bid_quantity: Option<StrDecimal>,
+    #[serde(rename = "a")]
+    pub ask_price: Option<StrDecimal>,
+    #[serde(rename = "A")]
+    pub ask_quantity: Option<StrDecimal>,
+    #[serde(rename = "o")]
+    pub open: StrDecimal,
+    #[serde(rename = "h")]
+    pub high: StrDecimal,
+    #[serde(rename = "l")]
+    pub low: StrDecimal,
+    /// Base asset volume
+    #[serde(rename = "v")]
+    pub volume: StrDecimal,
+    /// Quote asset volume
+    #[serde(rename = "q")]
+    pub quote_volume: StrDecimal,
+    #[serde(rename = "O")]
+    pub open_time: u64,
+    #[serde(rename = "C")]
+    pub close_time: u64,
+    #[serde(rename = "F")]
+    pub first_trade_id: i64,
+    #[serde(rename = "L")]
+    pub last_trade_id: i64,
+    /// Number of trades
+    #[serde(rename = "n")]
+    pub trades: u64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BinanceMarkPrice {
+    #[serde(rename = "e")]
+    pub event_type: String,
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "p")]
+    pub mark_price: StrDecimal,
+    #[serde(rename = "i")]
+    pub index_price: StrDecimal,
+    /// Only useful in the last hour before settlement
+    #[serde(rename = "P")]
+    pub estimated_settle_price: StrDecimal,
+    /// Empty for delivery contracts
+    #[serde(rename = "r", default, deserialize_with = "empty_string::deserialize")]
+    pub funding_rate: Option<StrDecimal>,
+    /// Zero for delivery contracts
+    #[serde(rename = "T")]
+    pub next_funding_time: u64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BinanceForceOrder {
+    #[serde(rename = "e")]
+    pub event_type: String,
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    #[serde(rename = "o")]
+    pub order: BinanceLiquidation,
+}
+
+/// Liquidation order, only the latest of each symbol within 1000ms is pushed
+#[derive(Deserialize, Debug, Clone)]
+pub struct BinanceLiquidation {
+    #[serde(rename = "s")]
+    pub symbol: String,
+    /// `BUY` or `SELL`
+    #[serde(rename = "S")]
+    pub side: String,
+    #[serde(rename = "o")]
+    pub order_type: String,
+    #[serde(rename = "f")]
+    pub time_in_force: String,
+    #[serde(rename = "q")]
+    pub quantity: StrDecimal,
+    #[serde(rename = "p")]
+    pub price: StrDecimal,
+    #[serde(rename = "ap")]
+    pub average_price: StrDecimal,
+    #[serde(rename = "X")]
+    pub status: String,
+    /// Quantity of the last fill
+    #[serde(rename = "l")]
+    pub last_filled_quantity: StrDecimal,
+    /// Accumulated filled quantity
+    #[serde(rename = "z")]
+    pub filled_quantity: StrDecimal,
+    #[serde(rename = "T")]
+    pub trade_time: u64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BinanceContinuousKline {
+    #[serde(rename = "e")]
+    pub event_type: String,
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    /// Pair, e.g. `BTCUSDT`
+    #[serde(rename = "ps")]
+    pub pair: String,
+    #[serde(rename = "ct")]
+    pub contract_type: BinanceContractType,
+    #[serde(rename = "k")]
+    pub kline: BinanceKlineData,
+}
+
+impl ExchangeMessage for BinanceMessage {
+    fn symbol(&self) -> Option<&str> {
+        match self {
+            BinanceMessage::SubscriptionAck(_) | BinanceMessage::Error(_) => None,
+            BinanceMessage::Trade(trade) => Some(&trade.symbol),
+            BinanceMessage::AggTrade(trade) => Some(&trade.symbol),
+            BinanceMessage::BookTicker(ticker) => Some(&ticker.symbol),
+            BinanceMessage::PartialDepth(depth) => depth.symbol.as_deref(),
+            BinanceMessage::DepthUpdate(update) => Some(&update.symbol),
+            BinanceMessage::Kline(kline) => Some(&kline.symbol),
+            BinanceMessage::MiniTicker(ticker) => Some(&ticker.symbol),
+            BinanceMessage::Ticker(ticker) => Some(&ticker.symbol),
+            BinanceMessage::MarkPrice(mark) => Some(&mark.symbol),
+            BinanceMessage::ForceOrder(force) => Some(&force.order.symbol),
+            BinanceMessage::ContinuousKline(kline) => Some(&kline.pair),
+            BinanceMessage::Combined { data, .. } => data.symbol(),
+        }
+    }
+
+    /// Spot and USDⓈ-M symbols look the same, so without the market only COIN-M perpetuals,
+    /// e.g. `BTCUSD_PERP`, and futures only messages are perpetuals. Prefer
+    /// [`BinanceMessage::instrument_in`] with the market of the connection.
+    fn instrument(&self) -> Option<Instrument> {
+        let symbol = self.symbol()?;
+        let (symbol, kind) = match (symbol.strip_suffix("_PERP"), self.data()) {
+            (Some(symbol), _) => (symbol, InstrumentKind::Perpetual),
+            (
+                None,
+                BinanceMessage::MarkPrice(_)
+                | BinanceMessage::ForceOrder(_)
+                | BinanceMessage::ContinuousKline(_),
+            ) => (symbol, InstrumentKind::Perpetual),
+            (None, _) => (symbol, InstrumentKind::Spot),
+        };
+        Instrument::parse(normalized::Exchange::Binance, symbol, kind)
+    }
+
+    fn ack(&self) -> Option<SubscriptionAck> {
+        match self {
+            BinanceMessage::SubscriptionAck(ack) => Some(SubscriptionAck {
+                request_id: ack.id?.to_string(),
+                result: Ok(()),
+                symbol: None,
+                rejected: Vec::new(),
+            }),
+            BinanceMessage::Error(error) => Some(SubscriptionAck {
+                request_id: error.id?.to_string(),
+                result: Err(format!("{} (code {})", error.message, error.code)),
+                symbol: None,
+                rejected: Vec::new(),
+            }),
+            BinanceMessage::Combined { data, .. } => data.ack(),
+            _ => None,
+        }
+    }
+}
+
 impl BinanceRequest {
     pub fn new(kind: RequestKind, params: Vec<impl Into<String>>) -> Self {
         let params = params.into_iter().map(|p| p.into()).collect();
@@ -91,27 +767,260 @@ impl BinanceRequest {
         self
     }
 
-    pub fn with_trade(mut self, symbol: impl Into<String>) -> Self {
-        self.add_trade(symbol);
+    pub fn with_trade(self, symbol: impl IntoSymbol) -> Self {
+        self.with_stream(symbol, BinanceStream::Trade)
+    }
+
+    pub fn with_trades(self, symbols: Vec<impl IntoSymbol>) -> Self {
+        self.with_streams(symbols, BinanceStream::Trade)
+    }
+
+    pub fn add_trade(&mut self, symbol: impl IntoSymbol) {
+        self.add_stream(symbol, BinanceStream::Trade);
+    }
+
+    pub fn add_trades(&mut self, symbols: Vec<impl IntoSymbol>) {
+        self.add_streams(symbols, BinanceStream::Trade);
+    }
+
+    pub fn with_stream(mut self, symbol: impl IntoSymbol, stream: BinanceStream) -> Self {
+        self.add_stream(symbol, stream);
         self
     }
 
-    pub fn with_trades(mut self, symbols: Vec<impl Into<String>>) -> Self {
-        self.add_trades(symbols);
+    pub fn with_streams(mut self, symbols: Vec<impl IntoSymbol>, stream: BinanceStream) -> Self {
+        self.add_streams(symbols, stream);
         self
     }
 
-    pub fn add_trade(&mut self, symbol: impl Into<String>) {
-        self.params.push(Self::format_trade(symbol));
+    pub fn add_stream(&mut self, symbol: impl IntoSymbol, stream: BinanceStream) {
+        self.params.push(Self::format_stream(symbol, &stream));
     }
 
-    pub fn add_trades(&mut self, symbols: Vec<impl Into<String>>) {
+    pub fn add_streams(&mut self, symbols: Vec<impl IntoSymbol>, stream: BinanceStream) {
         for symbol in symbols {
-            self.params.push(Self::format_trade(symbol));
+            self.params.push(Self::format_stream(symbol, &stream));
+        }
+    }
+
+    fn format_stream(symbol: impl IntoSymbol, stream: &BinanceStream) -> String {
+        stream.stream_name(&symbol.into_symbol(normalized::Exchange::Binance))
+    }
+}
+
+impl SubscriptionRequest for BinanceRequest {
+    fn kind(&self) -> RequestKind {
+        self.kind
+    }
+
+    fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+
+    fn remove_topics(&mut self, other: &Self) {
+        self.params.retain(|param| !other.params.contains(param));
+    }
+
+    fn set_request_id(&mut self, id: u64) -> bool {
+        self.id = Some(id);
+        true
+    }
+
+    fn request_id(&self) -> Option<String> {
+        self.id.map(|id| id.to_string())
+    }
+
+    /// Streams are named `<symbol>@<channel>`, the channel may hold another `@`, e.g. `depth5@100ms`
+    fn topics(&self) -> Vec<Subscription> {
+        self.params
+            .iter()
+            .filter_map(|param| param.split_once('@'))
+            .map(|(symbol, channel)| Subscription::new(channel, symbol))
+            .collect()
+    }
+
+    fn from_topics(kind: RequestKind, topics: &[Subscription], _active: &[Self]) -> Vec<Self> {
+        if topics.is_empty() {
+            return Vec::new();
         }
+
+        let params = topics
+            .iter()
+            .map(|topic| format!("{}@{}", topic.symbol.to_lowercase(), topic.channel))
+            .collect::<Vec<_>>();
+        vec![Self::new(kind, params)]
+    }
+}
+
+impl TryFrom<&BinanceTrade> for normalized::Trade {
+    type Error = ExStreamError;
+
+    fn try_from(trade: &BinanceTrade) -> Result<Self, Self::Error> {
+        Ok(normalized::Trade {
+            exchange: normalized::Exchange::Binance,
+            symbol: normalized::canonical_symbol(normalized::Exchange::Binance, &trade.symbol),
+            price: normalized::parse_number("price", &trade.price)?,
+            size: normalized::parse_number("size", &trade.quantity)?,
+            // The buyer is the maker when the seller took liquidity
+            side: match trade.is_market_maker {
+                true => Side::Sell,
+                false => Side::Buy,
+            },
+            trade_id: trade.trade_id.to_string(),
+            exchange_time: trade.trade_time,
+            local_time: normalized::now_ms(),
+        })
+    }
+}
+
+impl TryFrom<&BinanceAggTrade> for normalized::Trade {
+    type Error = ExStreamError;
+
+    fn try_from(trade: &BinanceAggTrade) -> Result<Self, Self::Error> {
+        Ok(normalized::Trade {
+            exchange: normalized::Exchange::Binance,
+            symbol: normalized::canonical_symbol(normalized::Exchange::Binance, &trade.symbol),
+            price: normalized::parse_number("price", &trade.price)?,
+            size: normalized::parse_number("size", &trade.quantity)?,
+            side: match trade.is_market_maker {
+                true => Side::Sell,
+                false => Side::Buy,
+            },
+            trade_id: trade.agg_trade_id.to_string(),
+            exchange_time: trade.trade_time,
+            local_time: normalized::now_ms(),
+        })
+    }
+}
+
+impl TryFrom<&BinanceBookTicker> for BestBidOffer {
+    type Error = ExStreamError;
+
+    fn try_from(ticker: &BinanceBookTicker) -> Result<Self, Self::Error> {
+        let local_time = normalized::now_ms();
+        Ok(BestBidOffer {
+            exchange: normalized::Exchange::Binance,
+            symbol: normalized::canonical_symbol(normalized::Exchange::Binance, &ticker.symbol),
+            bid_price: normalized::parse_number("price", &ticker.bid_price)?,
+            bid_size: normalized::parse_number("size", &ticker.bid_quantity)?,
+            ask_price: normalized::parse_number("price", &ticker.ask_price)?,
+            ask_size: normalized::parse_number("size", &ticker.ask_quantity)?,
+            // Spot book tickers carry no timestamp
+            exchange_time: ticker.transaction_time.unwrap_or(local_time),
+            local_time,
+        })
+    }
+}
+
+fn parse_levels(entries: &[BinanceLevel]) -> Result<Vec<normalized::PriceLevel>, ExStreamError> {
+    entries
+        .iter()
+        .map(|entry| normalized::parse_level(entry))
+        .collect()
+}
+
+impl TryFrom<&BinancePartialDepth> for BookUpdate {
+    type Error = ExStreamError;
+
+    fn try_from(depth: &BinancePartialDepth) -> Result<Self, Self::Error> {
+        let symbol = depth.symbol.as_deref().ok_or_else(|| {
+            ExStreamError::NormalizeError(
+                "partial book without symbol, use the combined endpoint".to_string(),
+            )
+        })?;
+        let local_time = normalized::now_ms();
+
+        Ok(BookUpdate {
+            exchange: normalized::Exchange::Binance,
+            symbol: normalized::canonical_symbol(normalized::Exchange::Binance, symbol),
+            is_snapshot: true,
+            bids: parse_levels(&depth.bids)?,
+            asks: parse_levels(&depth.asks)?,
+            sequence: Some(depth.last_update_id),
+            exchange_time: local_time,
+            local_time,
+        })
+    }
+}
+
+impl TryFrom<&BinanceDepthUpdate> for BookUpdate {
+    type Error = ExStreamError;
+
+    fn try_from(update: &BinanceDepthUpdate) -> Result<Self, Self::Error> {
+        Ok(BookUpdate {
+            exchange: normalized::Exchange::Binance,
+            symbol: normalized::canonical_symbol(normalized::Exchange::Binance, &update.symbol),
+            is_snapshot: false,
+            bids: parse_levels(&update.bids)?,
+            asks: parse_levels(&update.asks)?,
+            sequence: Some(update.final_update_id),
+            exchange_time: update.event_time,
+            local_time: normalized::now_ms(),
+        })
     }
+}
 
-    fn format_trade(symbol: impl Into<String>) -> String {
-        format!("{}@trade", symbol.into().to_lowercase())
+impl TryFrom<&BinanceMiniTicker> for normalized::Ticker {
+    type Error = ExStreamError;
+
+    fn try_from(ticker: &BinanceMiniTicker) -> Result<Self, Self::Error> {
+        Ok(normalized::Ticker {
+            exchange: normalized::Exchange::Binance,
+            symbol: normalized::canonical_symbol(normalized::Exchange::Binance, &ticker.symbol),
+            last_price: normalized::parse_number("price", &ticker.close)?,
+            open_24h: Some(normalized::parse_number("price", &ticker.open)?),
+            high_24h: Some(normalized::parse_number("price", &ticker.high)?),
+            low_24h: Some(normalized::parse_number("price", &ticker.low)?),
+            volume_24h: Some(normalized::parse_number("volume", &ticker.volume)?),
+            exchange_time: ticker.event_time,
+            local_time: normalized::now_ms(),
+        })
+    }
+}
+
+impl TryFrom<&BinanceTicker> for normalized::Ticker {
+    type Error = ExStreamError;
+
+    fn try_from(ticker: &BinanceTicker) -> Result<Self, Self::Error> {
+        Ok(normalized::Ticker {
+            exchange: normalized::Exchange::Binance,
+            symbol: normalized::canonical_symbol(normalized::Exchange::Binance, &ticker.symbol),
+            last_price: normalized::parse_number("price", &ticker.last_price)?,
+            open_24h: Some(normalized::parse_number("price", &ticker.open)?),
+            high_24h: Some(normalized::parse_number("price", &ticker.high)?),
+            low_24h: Some(normalized::parse_number("price", &ticker.low)?),
+            volume_24h: Some(normalized::parse_number("volume", &ticker.volume)?),
+            exchange_time: ticker.event_time,
+            local_time: normalized::now_ms(),
+        })
+    }
+}
+
+impl Normalize for BinanceMessage {
+    fn normalize(&self) -> Result<Vec<MarketData>, ExStreamError> {
+        match self {
+            BinanceMessage::Trade(trade) => Ok(vec![MarketData::Trade(trade.try_into()?)]),
+            BinanceMessage::AggTrade(trade) => Ok(vec![MarketData::Trade(trade.try_into()?)]),
+            BinanceMessage::BookTicker(ticker) => {
+                Ok(vec![MarketData::BestBidOffer(ticker.try_into()?)])
+            }
+            BinanceMessage::PartialDepth(depth) => {
+                Ok(vec![MarketData::BookUpdate(depth.try_into()?)])
+            }
+            BinanceMessage::DepthUpdate(update) => {
+                Ok(vec![MarketData::BookUpdate(update.try_into()?)])
+            }
+            BinanceMessage::MiniTicker(ticker) => Ok(vec![MarketData::Ticker(ticker.try_into()?)]),
+            BinanceMessage::Ticker(ticker) => {
+                Ok(vec![MarketData::Ticker(ticker.as_ref().try_into()?)])
+            }
+            BinanceMessage::Combined { data, .. } => data.normalize(),
+            BinanceMessage::Kline(_)
+            | BinanceMessage::MarkPrice(_)
+            | BinanceMessage::ForceOrder(_)
+            | BinanceMessage::ContinuousKline(_)
+            | BinanceMessage::SubscriptionAck(_)
+            | BinanceMessage::Error(_) => Ok(Vec::new()),
+        }
     }
 }
@@ -0,0 +1,200 @@
+use serde::Deserialize;
+
+use crate::models::{
+    BinanceRequest, Exchange, ExchangeMessage, Instrument, InstrumentKind, StrDecimal,
+    normalized::{self, Side},
+};
+
+/// Marker type for Binance user data connections, which have no subscriptions
+#[derive(Debug, Clone, Copy)]
+pub struct BinanceUserData;
+
+impl Exchange for BinanceUserData {
+    type Request = BinanceRequest;
+    type Message = BinanceUserDataMessage;
+}
+
+/// Private account events pushed on the `/ws/<listenKey>` endpoint
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "e")]
+pub enum BinanceUserDataMessage {
+    /// Order placed, filled, canceled, rejected or expired
+    #[serde(rename = "executionReport")]
+    ExecutionReport(Box<BinanceExecutionReport>),
+    /// Balances of the assets changed by an order or a transfer
+    #[serde(rename = "outboundAccountPosition")]
+    AccountPosition(BinanceAccountPosition),
+    /// Deposit, withdrawal or transfer
+    #[serde(rename = "balanceUpdate")]
+    BalanceUpdate(BinanceBalanceUpdate),
+    /// The listen key is no longer valid, a new one is needed to keep receiving events
+    #[serde(rename = "listenKeyExpired")]
+    ListenKeyExpired(BinanceListenKeyExpired),
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum BinanceOrderSide {
+    Buy,
+    Sell,
+}
+
+impl From<BinanceOrderSide> for Side {
+    fn from(side: BinanceOrderSide) -> Self {
+        match side {
+            BinanceOrderSide::Buy => Side::Buy,
+            BinanceOrderSide::Sell => Side::Sell,
+        }
+    }
+}
+
+/// Why the execution report was sent
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum BinanceExecutionType {
+    New,
+    Canceled,
+    Replaced,
+    Rejected,
+    Trade,
+    Expired,
+    TradePrevention,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum BinanceOrderStatus {
+    New,
+    PartiallyFilled,
+    Filled,
+    Canceled,
+    PendingCancel,
+    Rejected,
+    Expired,
+    ExpiredInMatch,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BinanceExecutionReport {
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "c")]
+    pub client_order_id: String,
+    #[serde(rename = "S")]
+    pub side: BinanceOrderSide,
+    /// e.g. `LIMIT` or `MARKET`
+    #[serde(rename = "o")]
+    pub order_type: String,
+    #[serde(rename = "f")]
+    pub time_in_force: String,
+    #[serde(rename = "q")]
+    pub quantity: StrDecimal,
+    #[serde(rename = "p")]
+    pub price: StrDecimal,
+    #[serde(rename = "P")]
+    pub stop_price: StrDecimal,
+    /// Client order id of the order being canceled, empty otherwise
+    #[serde(rename = "C")]
+    pub original_client_order_id: String,
+    #[serde(rename = "x")]
+    pub execution_type: BinanceExecutionType,
+    #[serde(rename = "X")]
+    pub order_status: BinanceOrderStatus,
+    /// `NONE` unless the order was rejected
+    #[serde(rename = "r")]
+    pub reject_reason: String,
+    #[serde(rename = "i")]
+    pub order_id: u64,
+    #[serde(rename = "l")]
+    pub last_executed_quantity: StrDecimal,
+    #[serde(rename = "z")]
+    pub cumulative_filled_quantity: StrDecimal,
+    #[serde(rename = "L")]
+    pub last_executed_price: StrDecimal,
+    #[serde(rename = "n")]
+    pub commission: StrDecimal,
+    /// Asset the commission is paid in, only set for trades
+    #[serde(rename = "N")]
+    pub commission_asset: Option<String>,
+    #[serde(rename = "T")]
+    pub transaction_time: u64,
+    /// Trade ID, -1 when the report is not about a trade
+    #[serde(rename = "t")]
+    pub trade_id: i64,
+    /// Is the order on the book?
+    #[serde(rename = "w")]
+    pub is_working: bool,
+    /// Is this trade the maker side?
+    #[serde(rename = "m")]
+    pub is_maker: bool,
+    #[serde(rename = "O")]
+    pub order_creation_time: u64,
+    #[serde(rename = "Z")]
+    pub cumulative_quote_quantity: StrDecimal,
+    #[serde(rename = "Y")]
+    pub last_quote_quantity: StrDecimal,
+    #[serde(rename = "Q")]
+    pub quote_order_quantity: StrDecimal,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BinanceAccountPosition {
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    /// Time of the last account update
+    #[serde(rename = "u")]
+    pub last_update_time: u64,
+    /// Assets whose balance changed
+    #[serde(rename = "B")]
+    pub balances: Vec<BinanceBalance>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BinanceBalance {
+    #[serde(rename = "a")]
+    pub asset: String,
+    #[serde(rename = "f")]
+    pub free: StrDecimal,
+    #[serde(rename = "l")]
+    pub locked: StrDecimal,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BinanceBalanceUpdate {
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    #[serde(rename = "a")]
+    pub asset: String,
+    /// Signed change of the balance
+    #[serde(rename = "d")]
+    pub delta: StrDecimal,
+    #[serde(rename = "T")]
+    pub clear_time: u64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BinanceListenKeyExpired {
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    #[serde(rename = "listenKey")]
+    pub listen_key: String,
+}
+
+impl ExchangeMessage for BinanceUserDataMessage {
+    fn symbol(&self) -> Option<&str> {
+        match self {
+            BinanceUserDataMessage::ExecutionReport(report) => Some(&report.symbol),
+            _ => None,
+        }
+    }
+
+    fn instrument(&self) -> Option<Instrument> {
+        Instrument::parse(
+            normalized::Exchange::Binance,
+            self.symbol()?,
+            InstrumentKind::Spot,
+        )
+    }
+}
@@ -1,8 +1,98 @@
-use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
-use crate::models::{RequestKind, to_lower};
+use futures_util::StreamExt as _;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::value::RawValue;
 
-pub type BybitOrderEntry = Vec<String>; // [price, size]
+use crate::error::ExStreamError;
+use crate::models::normalized::{self, BookUpdate, MarketData, Normalize};
+use crate::models::{
+    Exchange, ExchangeMessage, Instrument, InstrumentKind, IntoSymbol, RequestKind, StrDecimal,
+    Subscription, SubscriptionAck, SubscriptionRequest, empty_string, to_lower,
+};
+use crate::transport::WsMsgStream;
+
+pub type BybitOrderEntry = Vec<StrDecimal>; // [price, size]
+
+/// Marker type for Bybit connections
+#[derive(Debug, Clone, Copy)]
+pub struct Bybit;
+
+impl Exchange for Bybit {
+    type Request = BybitRequest;
+    type Message = BybitMessage;
+}
+
+/// Product line of the public streams, each served from its own endpoint
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BybitCategory {
+    #[default]
+    Spot,
+    /// USDT and USDC perpetuals and futures
+    Linear,
+    /// Perpetuals and futures margined in the base coin
+    Inverse,
+    Option,
+}
+
+impl BybitCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BybitCategory::Spot => "spot",
+            BybitCategory::Linear => "linear",
+            BybitCategory::Inverse => "inverse",
+            BybitCategory::Option => "option",
+        }
+    }
+
+    /// Orderbook depths the category can be subscribed to
+    pub fn orderbook_depths(&self) -> &'static [u64] {
+        match self {
+            BybitCategory::Spot => &[1, 50, 200, 1000],
+            BybitCategory::Linear | BybitCategory::Inverse => &[1, 50, 200, 500, 1000],
+            BybitCategory::Option => &[25, 100],
+        }
+    }
+
+    /// Check if the category streams a channel, e.g. liquidations are only sent for
+    /// perpetuals and futures, and leveraged tokens only trade on spot
+    pub fn supports(&self, channel: &str) -> bool {
+        match channel {
+            "allLiquidation" => matches!(self, BybitCategory::Linear | BybitCategory::Inverse),
+            "kline" => !matches!(self, BybitCategory::Option),
+            "kline_lt" | "tickers_lt" | "lt" => matches!(self, BybitCategory::Spot),
+            _ => true,
+        }
+    }
+
+    /// Check the topics of the request against the channels and orderbook depths of the category
+    pub fn validate(&self, request: &BybitRequest) -> Result<(), ExStreamError> {
+        let depths = self.orderbook_depths();
+        for topic in request.topics() {
+            if !self.supports(&topic.channel) {
+                return Err(ExStreamError::UnsupportedRequest(format!(
+                    "{} is not available for {}",
+                    topic.channel,
+                    self.as_str()
+                )));
+            }
+            if let Some(depth) = topic.depth
+                && topic.channel == "orderbook"
+                && !depths.contains(&depth)
+            {
+                return Err(ExStreamError::UnsupportedRequest(format!(
+                    "orderbook depth {} of {} is not available for {}, use one of {:?}",
+                    depth,
+                    topic.symbol,
+                    self.as_str(),
+                    depths
+                )));
+            }
+        }
+        Ok(())
+    }
+}
 
 #[derive(Serialize, Debug, Clone)]
 pub struct BybitRequest {
@@ -14,22 +104,135 @@ pub struct BybitRequest {
     pub id: Option<String>,
 }
 
-#[derive(Deserialize, Debug)]
-#[serde(untagged)]
+/// Messages are told apart by the channel of their topic
+#[derive(Debug)]
 pub enum BybitMessage {
     SubscriptionAck {
         success: bool,
-        #[serde(rename = "ret_msg")]
         message: String,
-        #[serde(rename = "conn_id")]
         connection_id: String,
-        #[serde(rename = "req_id")]
         request_id: Option<String>,
-        #[serde(rename = "op")]
+        /// `op` of the request, `COMMAND_RESP` for options
         operation: String,
+        /// Topics of an option request which failed, the others are subscribed
+        failed_topics: Vec<String>,
     },
     OrderBook(BybitOrderBook),
     Trade(BybitTrade),
+    /// `tickers.{symbol}`, derivatives send a snapshot then deltas, see [`BybitTickers`]
+    Ticker(BybitTicker),
+    /// `kline.{interval}.{symbol}`
+    Kline(BybitKline),
+    /// `allLiquidation.{symbol}`
+    Liquidation(BybitLiquidation),
+    /// `kline_lt.{interval}.{symbol}`, klines of a leveraged token
+    LtKline(BybitKline),
+    /// `tickers_lt.{symbol}`, ticker of a leveraged token
+    LtTicker(BybitTicker),
+    /// `lt.{symbol}`, net asset value of a leveraged token
+    LtNav(BybitLtNav),
+}
+
+/// Fields telling the messages apart
+#[derive(Deserialize)]
+struct BybitFrame {
+    topic: Option<String>,
+}
+
+/// Answer to a request. Options answer with neither `ret_msg` nor `op`, but with the
+/// `COMMAND_RESP` type and the topics which failed.
+#[derive(Deserialize)]
+struct BybitReply {
+    success: bool,
+    #[serde(default)]
+    ret_msg: String,
+    conn_id: String,
+    req_id: Option<String>,
+    #[serde(default)]
+    op: String,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    data: Option<BybitOptionReply>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BybitOptionReply {
+    #[serde(default)]
+    fail_topics: Vec<String>,
+}
+
+/// Type of the option answers
+const OPTION_REPLY: &str = "COMMAND_RESP";
+
+impl BybitMessage {
+    /// Parse a message from the channel of its topic, messages without topic answer requests
+    fn parse(text: &str) -> Result<Self, serde_json::Error> {
+        use serde::de::Error;
+
+        let frame: BybitFrame = serde_json::from_str(text)?;
+        let Some(topic) = frame.topic else {
+            let reply: BybitReply = serde_json::from_str(text)?;
+            let failed = reply.data.map(|data| data.fail_topics).unwrap_or_default();
+            let (success, message) = match failed.is_empty() {
+                true => (reply.success, reply.ret_msg),
+                false => (false, format!("failed topics {}", failed.join(", "))),
+            };
+            return Ok(BybitMessage::SubscriptionAck {
+                success,
+                message,
+                connection_id: reply.conn_id,
+                request_id: reply.req_id,
+                operation: match reply.kind {
+                    Some(kind) if reply.op.is_empty() => kind,
+                    _ => reply.op,
+                },
+                failed_topics: failed,
+            });
+        };
+
+        let message = match topic.split('.').next().unwrap_or_default() {
+            "orderbook" => BybitMessage::OrderBook(serde_json::from_str(text)?),
+            "publicTrade" => BybitMessage::Trade(serde_json::from_str(text)?),
+            "tickers" => BybitMessage::Ticker(serde_json::from_str(text)?),
+            "kline" => BybitMessage::Kline(serde_json::from_str(text)?),
+            "allLiquidation" => BybitMessage::Liquidation(serde_json::from_str(text)?),
+            "kline_lt" => BybitMessage::LtKline(serde_json::from_str(text)?),
+            "tickers_lt" => BybitMessage::LtTicker(serde_json::from_str(text)?),
+            "lt" => BybitMessage::LtNav(serde_json::from_str(text)?),
+            other => {
+                return Err(serde_json::Error::custom(format!(
+                    "unknown Bybit channel {other}"
+                )));
+            }
+        };
+        Ok(message)
+    }
+
+    /// Topic the message was published on, `None` for answers to requests
+    pub fn topic(&self) -> Option<&str> {
+        match self {
+            BybitMessage::SubscriptionAck { .. } => None,
+            BybitMessage::OrderBook(book) => Some(&book.topic),
+            BybitMessage::Trade(trade) => Some(&trade.topic),
+            BybitMessage::Ticker(ticker) | BybitMessage::LtTicker(ticker) => Some(&ticker.topic),
+            BybitMessage::Kline(kline) | BybitMessage::LtKline(kline) => Some(&kline.topic),
+            BybitMessage::Liquidation(liquidation) => Some(&liquidation.topic),
+            BybitMessage::LtNav(nav) => Some(&nav.topic),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for BybitMessage {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        let raw = Box::<RawValue>::deserialize(deserializer)?;
+        Self::parse(raw.get()).map_err(D::Error::custom)
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -51,9 +254,10 @@ pub struct BybitOrderBook {
     pub correlated_timestamp: u64,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum BybitDataType {
+    #[default]
     Snapshot,
     Delta,
 }
@@ -95,6 +299,15 @@ pub struct BybitTrade {
     pub data: Vec<BybitTradeData>,
 }
 
+/// Direction of the last price change, only sent by derivatives
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BybitTickDirection {
+    PlusTick,
+    ZeroPlusTick,
+    MinusTick,
+    ZeroMinusTick,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct BybitTradeData {
     /// The timestamp (ms) that the order is filled
@@ -108,22 +321,411 @@ pub struct BybitTradeData {
     pub side: String,
     /// Trade ID
     #[serde(rename = "v")]
-    pub size: String,
+    pub size: StrDecimal,
     /// Price
     #[serde(rename = "p")]
-    pub price: String,
-    /// Direction of price change, this is documented but not provided
-    // #[serde(rename = "L")]
-    // pub direction: String,
+    pub price: StrDecimal,
+    /// Direction of price change, only sent for perpetuals and futures
+    #[serde(rename = "L")]
+    pub tick_direction: Option<BybitTickDirection>,
     /// Trade ID
     #[serde(rename = "i")]
     pub trade_id: String,
-    /// Undocumented on Bybit documentation
-    #[serde(rename = "BT")]
+    /// Whether it is a block trade
+    #[serde(rename = "BT", default)]
     pub bt: bool,
-    /// Undocumented on Bybit documentation
-    #[serde(rename = "RPI")]
+    /// Whether it is a RPI trade, not sent for options
+    #[serde(rename = "RPI", default)]
     pub rpi: bool,
+    /// Cross sequence
+    #[serde(rename = "seq")]
+    pub sequence: Option<u64>,
+    /// Mark price, options only
+    #[serde(rename = "mP")]
+    pub mark_price: Option<StrDecimal>,
+    /// Index price, options only
+    #[serde(rename = "iP")]
+    pub index_price: Option<StrDecimal>,
+    /// Mark implied volatility, options only
+    #[serde(rename = "mIv")]
+    pub mark_iv: Option<StrDecimal>,
+    /// Implied volatility, options only
+    #[serde(rename = "iv")]
+    pub iv: Option<StrDecimal>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BybitTicker {
+    /// Topic name
+    pub topic: String,
+    /// The timestamp (ms) that the system generates the data
+    #[serde(rename = "ts")]
+    pub timestamp: u64,
+    /// Data type: snapshot,delta. Spot and options only send snapshots.
+    #[serde(rename = "type", default)]
+    pub data_type: BybitDataType,
+    /// Cross sequence
+    #[serde(rename = "cs")]
+    pub sequence: Option<u64>,
+    /// Ticker data, deltas only carry the fields that changed
+    pub data: BybitTickerData,
+}
+
+/// Ticker fields of every category, all optional since deltas only carry the fields that
+/// changed. Merge them into the snapshot with [`BybitTickerData::merge`] or [`BybitTickers`].
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BybitTickerData {
+    /// Symbol name, e.g. BTCUSDT
+    pub symbol: String,
+    pub last_price: Option<StrDecimal>,
+    /// Market price 24 hours ago
+    pub prev_price_24h: Option<StrDecimal>,
+    pub high_price_24h: Option<StrDecimal>,
+    pub low_price_24h: Option<StrDecimal>,
+    /// Percentage change of the price over 24 hours
+    pub price_24h_pcnt: Option<StrDecimal>,
+    /// Volume in base coin over 24 hours
+    pub volume_24h: Option<StrDecimal>,
+    /// Turnover in quote coin over 24 hours
+    pub turnover_24h: Option<StrDecimal>,
+    /// USD index price, spot only
+    #[serde(default, deserialize_with = "empty_string::deserialize")]
+    pub usd_index_price: Option<StrDecimal>,
+    /// Perpetuals and futures only
+    pub tick_direction: Option<BybitTickDirection>,
+    /// Market price an hour ago, perpetuals and futures only
+    pub prev_price_1h: Option<StrDecimal>,
+    pub mark_price: Option<StrDecimal>,
+    pub index_price: Option<StrDecimal>,
+    /// Open interest size
+    pub open_interest: Option<StrDecimal>,
+    /// Open interest value, perpetuals and futures only
+    pub open_interest_value: Option<StrDecimal>,
+    /// Empty for futures
+    #[serde(default, deserialize_with = "empty_string::deserialize")]
+    pub funding_rate: Option<StrDecimal>,
+    /// Next funding timestamp (ms)
+    pub next_funding_time: Option<String>,
+    /// Best bid and ask, perpetuals and futures only
+    pub bid1_price: Option<StrDecimal>,
+    pub bid1_size: Option<StrDecimal>,
+    pub ask1_price: Option<StrDecimal>,
+    pub ask1_size: Option<StrDecimal>,
+    /// Delivery date time (UTC+0), empty for perpetuals
+    pub delivery_time: Option<String>,
+    /// Basis rate, futures only
+    #[serde(default, deserialize_with = "empty_string::deserialize")]
+    pub basis_rate: Option<StrDecimal>,
+    /// Delivery fee rate, futures only
+    #[serde(default, deserialize_with = "empty_string::deserialize")]
+    pub delivery_fee_rate: Option<StrDecimal>,
+    /// Predicated delivery price, futures and options only
+    #[serde(default, deserialize_with = "empty_string::deserialize")]
+    pub predicted_delivery_price: Option<StrDecimal>,
+    /// Best bid and ask with their implied volatility, options only
+    pub bid_price: Option<StrDecimal>,
+    pub bid_size: Option<StrDecimal>,
+    pub bid_iv: Option<StrDecimal>,
+    pub ask_price: Option<StrDecimal>,
+    pub ask_size: Option<StrDecimal>,
+    pub ask_iv: Option<StrDecimal>,
+    /// Mark price implied volatility, options only
+    pub mark_price_iv: Option<StrDecimal>,
+    /// Underlying price, options only
+    pub underlying_price: Option<StrDecimal>,
+    /// Total volume and turnover, options only
+    pub total_volume: Option<StrDecimal>,
+    pub total_turnover: Option<StrDecimal>,
+    /// Greeks, options only
+    pub delta: Option<StrDecimal>,
+    pub gamma: Option<StrDecimal>,
+    pub vega: Option<StrDecimal>,
+    pub theta: Option<StrDecimal>,
+    /// Price change over 24 hours, options only
+    pub change_24h: Option<StrDecimal>,
+}
+
+/// Overwrite the fields set in the delta
+macro_rules! merge_fields {
+    ($target:expr, $delta:expr, $($field:ident),+ $(,)?) => {
+        $(
+            if $delta.$field.is_some() {
+                $target.$field = $delta.$field;
+            }
+        )+
+    };
+}
+
+impl BybitTickerData {
+    /// Apply a delta, keeping the fields it does not carry
+    pub fn merge(&mut self, delta: BybitTickerData) {
+        merge_fields!(
+            self,
+            delta,
+            last_price,
+            prev_price_24h,
+            high_price_24h,
+            low_price_24h,
+            price_24h_pcnt,
+            volume_24h,
+            turnover_24h,
+            usd_index_price,
+            tick_direction,
+            prev_price_1h,
+            mark_price,
+            index_price,
+            open_interest,
+            open_interest_value,
+            funding_rate,
+            next_funding_time,
+            bid1_price,
+            bid1_size,
+            ask1_price,
+            ask1_size,
+            delivery_time,
+            basis_rate,
+            delivery_fee_rate,
+            predicted_delivery_price,
+            bid_price,
+            bid_size,
+            bid_iv,
+            ask_price,
+            ask_size,
+            ask_iv,
+            mark_price_iv,
+            underlying_price,
+            total_volume,
+            total_turnover,
+            delta,
+            gamma,
+            vega,
+            theta,
+            change_24h,
+        );
+    }
+}
+
+/// Latest ticker of every symbol, with the derivatives deltas merged into their snapshot
+#[derive(Debug, Clone, Default)]
+pub struct BybitTickers {
+    tickers: HashMap<String, BybitTickerData>,
+}
+
+impl BybitTickers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply a ticker message and return the merged ticker of its symbol.
+    /// Snapshots replace the ticker, deltas update the fields they carry.
+    pub fn apply(&mut self, ticker: &BybitTicker) -> &BybitTickerData {
+        let entry = self.tickers.entry(ticker.data.symbol.clone()).or_default();
+        match ticker.data_type {
+            BybitDataType::Snapshot => *entry = ticker.data.clone(),
+            BybitDataType::Delta => entry.merge(ticker.data.clone()),
+        }
+        entry
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<&BybitTickerData> {
+        self.tickers.get(symbol)
+    }
+
+    /// Apply a ticker message and return it with the merged ticker, as a snapshot
+    fn merged(&mut self, mut ticker: BybitTicker) -> BybitTicker {
+        ticker.data = self.apply(&ticker).clone();
+        ticker.data_type = BybitDataType::Snapshot;
+        ticker
+    }
+}
+
+/// Merge the ticker deltas of the stream into the snapshot of their symbol,
+/// every ticker then carries the fields left out of the deltas
+pub(crate) fn merge_tickers(stream: WsMsgStream<BybitMessage>) -> WsMsgStream<BybitMessage> {
+    let mut tickers = BybitTickers::new();
+    Box::pin(stream.map(move |message| match message {
+        Ok(BybitMessage::Ticker(ticker)) => Ok(BybitMessage::Ticker(tickers.merged(ticker))),
+        Ok(BybitMessage::LtTicker(ticker)) => Ok(BybitMessage::LtTicker(tickers.merged(ticker))),
+        message => message,
+    }))
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BybitKline {
+    /// Topic name
+    pub topic: String,
+    /// The timestamp (ms) that the system generates the data
+    #[serde(rename = "ts")]
+    pub timestamp: u64,
+    /// Data type: snapshot
+    #[serde(rename = "type")]
+    pub data_type: BybitDataType,
+    pub data: Vec<BybitKlineData>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BybitKlineData {
+    /// The start timestamp (ms)
+    pub start: u64,
+    /// The end timestamp (ms)
+    pub end: u64,
+    /// Kline interval, e.g. `5` or `D`
+    pub interval: String,
+    pub open: StrDecimal,
+    pub close: StrDecimal,
+    pub high: StrDecimal,
+    pub low: StrDecimal,
+    /// Trade volume, not sent for leveraged tokens
+    pub volume: Option<StrDecimal>,
+    /// Turnover, not sent for leveraged tokens
+    pub turnover: Option<StrDecimal>,
+    /// Whether the kline is closed
+    pub confirm: bool,
+    /// The timestamp (ms) of the last matched order in the candle
+    pub timestamp: u64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BybitLiquidation {
+    /// Topic name
+    pub topic: String,
+    /// The timestamp (ms) that the system generates the data
+    #[serde(rename = "ts")]
+    pub timestamp: u64,
+    /// Data type: snapshot
+    #[serde(rename = "type")]
+    pub data_type: BybitDataType,
+    pub data: Vec<BybitLiquidationData>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BybitLiquidationData {
+    /// The updated timestamp (ms)
+    #[serde(rename = "T")]
+    pub timestamp: u64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    /// Side of the liquidated position, `Buy` is a long position
+    #[serde(rename = "S")]
+    pub side: String,
+    /// Executed size
+    #[serde(rename = "v")]
+    pub size: StrDecimal,
+    /// Bankruptcy price
+    #[serde(rename = "p")]
+    pub price: StrDecimal,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BybitLtNav {
+    /// Topic name
+    pub topic: String,
+    /// The timestamp (ms) that the system generates the data
+    #[serde(rename = "ts")]
+    pub timestamp: u64,
+    /// Data type: snapshot
+    #[serde(rename = "type")]
+    pub data_type: BybitDataType,
+    pub data: BybitLtNavData,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BybitLtNavData {
+    /// Leveraged token name, e.g. EOS3LUSDT
+    pub symbol: String,
+    /// The generated timestamp (ms) of the nav
+    pub time: u64,
+    /// Net asset value
+    pub nav: StrDecimal,
+    /// Total position value = basket value * total circulation
+    pub basket_position: StrDecimal,
+    /// Leverage
+    pub leverage: StrDecimal,
+    /// Basket loan
+    pub basket_loan: StrDecimal,
+    /// Circulating supply in the secondary market
+    pub circulation: StrDecimal,
+    /// Basket
+    pub basket: StrDecimal,
+}
+
+impl ExchangeMessage for BybitMessage {
+    fn symbol(&self) -> Option<&str> {
+        match self {
+            BybitMessage::SubscriptionAck { .. } => None,
+            BybitMessage::OrderBook(book) => Some(&book.data.symbol),
+            BybitMessage::Trade(trade) => trade.data.first().map(|data| data.symbol.as_str()),
+            BybitMessage::Ticker(ticker) | BybitMessage::LtTicker(ticker) => {
+                Some(&ticker.data.symbol)
+            }
+            BybitMessage::Kline(kline) | BybitMessage::LtKline(kline) => {
+                kline.topic.rsplit('.').next()
+            }
+            BybitMessage::Liquidation(liquidation) => {
+                liquidation.data.first().map(|data| data.symbol.as_str())
+            }
+            BybitMessage::LtNav(nav) => Some(&nav.data.symbol),
+        }
+    }
+
+    /// Dated futures and options have no [`Instrument`]. Order books and klines carry nothing
+    /// telling the category apart, so only trades, tickers and liquidations report perpetuals.
+    fn instrument(&self) -> Option<Instrument> {
+        let derivative = match self {
+            BybitMessage::Trade(trade) => trade
+                .data
+                .first()
+                .is_some_and(|data| data.tick_direction.is_some()),
+            BybitMessage::Ticker(ticker) => {
+                ticker.data.tick_direction.is_some() || ticker.data.mark_price.is_some()
+            }
+            BybitMessage::Liquidation(_) => true,
+            _ => false,
+        };
+        let kind = if derivative {
+            InstrumentKind::Perpetual
+        } else {
+            InstrumentKind::Spot
+        };
+        Instrument::from_bybit_topic(self.topic()?, kind)
+    }
+
+    fn ack(&self) -> Option<SubscriptionAck> {
+        let BybitMessage::SubscriptionAck {
+            success,
+            message,
+            request_id: Some(request_id),
+            operation,
+            failed_topics,
+            ..
+        } = self
+        else {
+            return None;
+        };
+
+        if !matches!(
+            operation.as_str(),
+            "subscribe" | "unsubscribe" | OPTION_REPLY
+        ) {
+            return None;
+        }
+
+        Some(SubscriptionAck {
+            request_id: request_id.clone(),
+            result: if *success {
+                Ok(())
+            } else {
+                Err(message.clone())
+            },
+            symbol: None,
+            rejected: failed_topics
+                .iter()
+                .filter_map(|t| parse_topic(t))
+                .collect(),
+        })
+    }
 }
 
 impl BybitRequest {
@@ -162,51 +764,285 @@ impl BybitRequest {
         self
     }
 
-    pub fn with_trade(mut self, symbol: impl Into<String>) -> Self {
+    pub fn with_trade(mut self, symbol: impl IntoSymbol) -> Self {
         self.add_trade(symbol);
         self
     }
 
-    pub fn with_trades(mut self, symbols: Vec<impl Into<String>>) -> Self {
+    pub fn with_trades(mut self, symbols: Vec<impl IntoSymbol>) -> Self {
         self.add_trades(symbols);
         self
     }
 
-    pub fn with_orderbook(mut self, symbol: impl Into<String>, depth: u64) -> Self {
+    pub fn with_orderbook(mut self, symbol: impl IntoSymbol, depth: u64) -> Self {
         self.add_orderbook(symbol, depth);
         self
     }
 
-    pub fn with_orderbooks(mut self, symbols: Vec<impl Into<String>>, depth: u64) -> Self {
+    pub fn with_orderbooks(mut self, symbols: Vec<impl IntoSymbol>, depth: u64) -> Self {
         self.add_orderbooks(symbols, depth);
         self
     }
 
-    pub fn add_trade(&mut self, symbol: impl Into<String>) {
-        self.params.push(Self::format_trade(symbol));
+    pub fn with_ticker(mut self, symbol: impl IntoSymbol) -> Self {
+        self.add_ticker(symbol);
+        self
     }
 
-    pub fn add_trades(&mut self, symbols: Vec<impl Into<String>>) {
+    pub fn with_tickers(mut self, symbols: Vec<impl IntoSymbol>) -> Self {
+        self.add_tickers(symbols);
+        self
+    }
+
+    /// Interval as Bybit names it: 1, 3, 5, 15, 30, 60, 120, 240, 360, 720 minutes, D, W or M
+    pub fn with_kline(mut self, symbol: impl IntoSymbol, interval: impl Into<String>) -> Self {
+        self.add_kline(symbol, interval);
+        self
+    }
+
+    pub fn with_liquidation(mut self, symbol: impl IntoSymbol) -> Self {
+        self.add_liquidation(symbol);
+        self
+    }
+
+    pub fn with_lt_ticker(mut self, symbol: impl IntoSymbol) -> Self {
+        self.add_lt_ticker(symbol);
+        self
+    }
+
+    pub fn with_lt_kline(mut self, symbol: impl IntoSymbol, interval: impl Into<String>) -> Self {
+        self.add_lt_kline(symbol, interval);
+        self
+    }
+
+    pub fn with_lt_nav(mut self, symbol: impl IntoSymbol) -> Self {
+        self.add_lt_nav(symbol);
+        self
+    }
+
+    pub fn add_trade(&mut self, symbol: impl IntoSymbol) {
+        self.params.push(Self::format_topic("publicTrade", symbol));
+    }
+
+    pub fn add_trades(&mut self, symbols: Vec<impl IntoSymbol>) {
         for symbol in symbols {
             self.add_trade(symbol);
         }
     }
 
-    pub fn add_orderbook(&mut self, symbol: impl Into<String>, depth: u64) {
-        self.params.push(Self::format_orderbook(symbol, depth));
+    pub fn add_orderbook(&mut self, symbol: impl IntoSymbol, depth: u64) {
+        self.params
+            .push(Self::format_topic(&format!("orderbook.{depth}"), symbol));
     }
 
-    pub fn add_orderbooks(&mut self, symbols: Vec<impl Into<String>>, depth: u64) {
+    pub fn add_orderbooks(&mut self, symbols: Vec<impl IntoSymbol>, depth: u64) {
         for symbol in symbols {
             self.add_orderbook(symbol, depth);
         }
     }
 
-    fn format_trade(symbol: impl Into<String>) -> String {
-        format!("publicTrade.{}", symbol.into().to_uppercase())
+    pub fn add_ticker(&mut self, symbol: impl IntoSymbol) {
+        self.params.push(Self::format_topic("tickers", symbol));
+    }
+
+    pub fn add_tickers(&mut self, symbols: Vec<impl IntoSymbol>) {
+        for symbol in symbols {
+            self.add_ticker(symbol);
+        }
+    }
+
+    pub fn add_kline(&mut self, symbol: impl IntoSymbol, interval: impl Into<String>) {
+        let channel = format!("kline.{}", interval.into());
+        self.params.push(Self::format_topic(&channel, symbol));
+    }
+
+    pub fn add_liquidation(&mut self, symbol: impl IntoSymbol) {
+        self.params
+            .push(Self::format_topic("allLiquidation", symbol));
+    }
+
+    pub fn add_lt_ticker(&mut self, symbol: impl IntoSymbol) {
+        self.params.push(Self::format_topic("tickers_lt", symbol));
+    }
+
+    pub fn add_lt_kline(&mut self, symbol: impl IntoSymbol, interval: impl Into<String>) {
+        let channel = format!("kline_lt.{}", interval.into());
+        self.params.push(Self::format_topic(&channel, symbol));
+    }
+
+    pub fn add_lt_nav(&mut self, symbol: impl IntoSymbol) {
+        self.params.push(Self::format_topic("lt", symbol));
+    }
+
+    fn format_topic(channel: &str, symbol: impl IntoSymbol) -> String {
+        format!(
+            "{}.{}",
+            channel,
+            symbol
+                .into_symbol(normalized::Exchange::Bybit)
+                .to_uppercase()
+        )
+    }
+}
+
+impl SubscriptionRequest for BybitRequest {
+    fn kind(&self) -> RequestKind {
+        self.kind
+    }
+
+    fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+
+    fn remove_topics(&mut self, other: &Self) {
+        self.params.retain(|param| !other.params.contains(param));
+    }
+
+    fn set_request_id(&mut self, id: u64) -> bool {
+        self.id = Some(id.to_string());
+        true
+    }
+
+    fn request_id(&self) -> Option<String> {
+        self.id.clone()
     }
 
-    fn format_orderbook(symbol: impl Into<String>, depth: u64) -> String {
-        format!("orderbook.{}.{}", depth, symbol.into().to_uppercase())
+    fn topics(&self) -> Vec<Subscription> {
+        self.params
+            .iter()
+            .filter_map(|param| parse_topic(param))
+            .collect()
+    }
+
+    fn from_topics(kind: RequestKind, topics: &[Subscription], _active: &[Self]) -> Vec<Self> {
+        if topics.is_empty() {
+            return Vec::new();
+        }
+
+        let params = topics
+            .iter()
+            .map(|topic| {
+                let symbol = topic.symbol.to_uppercase();
+                match (topic.depth, &topic.interval) {
+                    (Some(depth), _) => format!("{}.{}.{}", topic.channel, depth, symbol),
+                    (None, Some(interval)) => {
+                        format!("{}.{}.{}", topic.channel, interval, symbol)
+                    }
+                    (None, None) => format!("{}.{}", topic.channel, symbol),
+                }
+            })
+            .collect::<Vec<_>>();
+        vec![Self::new(kind, params)]
+    }
+}
+
+/// Topics are named `<channel>.<symbol>`, `<channel>.<depth>.<symbol>` for orderbooks
+/// or `<channel>.<interval>.<symbol>` for klines
+fn parse_topic(topic: &str) -> Option<Subscription> {
+    let mut parts = topic.split('.');
+    let channel = parts.next()?;
+    let topic = match (parts.next()?, parts.next()) {
+        (symbol, None) => Subscription::new(channel, symbol),
+        (interval, Some(symbol)) if channel.starts_with("kline") => {
+            Subscription::new(channel, symbol).with_interval(interval)
+        }
+        (depth, Some(symbol)) => Subscription::new(channel, symbol).with_depth(depth.parse().ok()?),
+    };
+    Some(topic)
+}
+
+impl TryFrom<&BybitTradeData> for normalized::Trade {
+    type Error = ExStreamError;
+
+    fn try_from(trade: &BybitTradeData) -> Result<Self, Self::Error> {
+        Ok(normalized::Trade {
+            exchange: normalized::Exchange::Bybit,
+            symbol: normalized::canonical_symbol(normalized::Exchange::Bybit, &trade.symbol),
+            price: normalized::parse_number("price", &trade.price)?,
+            size: normalized::parse_number("size", &trade.size)?,
+            side: trade.side.parse()?,
+            trade_id: trade.trade_id.clone(),
+            exchange_time: trade.timestamp,
+            local_time: normalized::now_ms(),
+        })
+    }
+}
+
+impl TryFrom<&BybitOrderBook> for BookUpdate {
+    type Error = ExStreamError;
+
+    fn try_from(book: &BybitOrderBook) -> Result<Self, Self::Error> {
+        let levels = |entries: &[BybitOrderEntry]| {
+            entries
+                .iter()
+                .map(|entry| normalized::parse_level(entry))
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        Ok(BookUpdate {
+            exchange: normalized::Exchange::Bybit,
+            symbol: normalized::canonical_symbol(normalized::Exchange::Bybit, &book.data.symbol),
+            is_snapshot: matches!(book.data_type, BybitDataType::Snapshot),
+            bids: levels(&book.data.bids)?,
+            asks: levels(&book.data.asks)?,
+            sequence: Some(book.data.update_id),
+            exchange_time: book.timestamp,
+            local_time: normalized::now_ms(),
+        })
+    }
+}
+
+impl TryFrom<&BybitTicker> for normalized::Ticker {
+    type Error = ExStreamError;
+
+    fn try_from(ticker: &BybitTicker) -> Result<Self, Self::Error> {
+        let data = &ticker.data;
+        let optional = |field: &str, value: &Option<StrDecimal>| {
+            value
+                .as_ref()
+                .map(|value| normalized::parse_number(field, value))
+                .transpose()
+        };
+
+        Ok(normalized::Ticker {
+            exchange: normalized::Exchange::Bybit,
+            symbol: normalized::canonical_symbol(normalized::Exchange::Bybit, &data.symbol),
+            last_price: optional("price", &data.last_price)?.ok_or_else(|| {
+                ExStreamError::NormalizeError(format!("missing last price of {}", data.symbol))
+            })?,
+            open_24h: optional("price", &data.prev_price_24h)?,
+            high_24h: optional("price", &data.high_price_24h)?,
+            low_24h: optional("price", &data.low_price_24h)?,
+            volume_24h: optional("volume", &data.volume_24h)?,
+            exchange_time: ticker.timestamp,
+            local_time: normalized::now_ms(),
+        })
+    }
+}
+
+impl Normalize for BybitMessage {
+    fn normalize(&self) -> Result<Vec<MarketData>, ExStreamError> {
+        match self {
+            BybitMessage::Trade(trade) => trade
+                .data
+                .iter()
+                .map(|data| Ok(MarketData::Trade(data.try_into()?)))
+                .collect(),
+            BybitMessage::OrderBook(book) => Ok(vec![MarketData::BookUpdate(book.try_into()?)]),
+            // Deltas leave out the 24h statistics which did not change, only snapshots are
+            // complete. `connect_normalized` merges the deltas into snapshots beforehand.
+            BybitMessage::Ticker(ticker) | BybitMessage::LtTicker(ticker)
+                if ticker.data_type == BybitDataType::Snapshot =>
+            {
+                Ok(vec![MarketData::Ticker(ticker.try_into()?)])
+            }
+            BybitMessage::Ticker(_)
+            | BybitMessage::LtTicker(_)
+            | BybitMessage::Kline(_)
+            | BybitMessage::Liquidation(_)
+            | BybitMessage::LtKline(_)
+            | BybitMessage::LtNav(_)
+            | BybitMessage::SubscriptionAck { .. } => Ok(Vec::new()),
+        }
     }
 }
@@ -1,6 +1,20 @@
 use serde::{Deserialize, Serialize};
 
-use crate::models::{RequestKind, to_lower};
+use crate::error::ExStreamError;
+use crate::models::normalized::{self, BestBidOffer, MarketData, Normalize};
+use crate::models::{
+    Exchange, ExchangeMessage, Instrument, InstrumentKind, IntoSymbol, RequestKind, StrDecimal,
+    Subscription, SubscriptionRequest, to_lower,
+};
+
+/// Marker type for Coinbase connections
+#[derive(Debug, Clone, Copy)]
+pub struct Coinbase;
+
+impl Exchange for Coinbase {
+    type Request = CoinbaseRequest;
+    type Message = CoinbaseMessage;
+}
 
 #[derive(Serialize, Debug, Clone)]
 pub struct CoinbaseRequest {
@@ -29,30 +43,66 @@ pub struct CoinbaseChannel {
     pub product_ids: Vec<String>,
 }
 
+impl CoinbaseChannel {
+    fn topics(&self) -> Vec<Subscription> {
+        self.product_ids
+            .iter()
+            .map(|product_id| Subscription::new(&self.name, product_id))
+            .collect()
+    }
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct CoinbaseTicker {
     #[serde(rename = "type")]
     pub kind: String,
     pub sequence: u64,
     pub product_id: String,
-    pub price: String,
-    pub open_24h: String,
-    pub volume_24h: String,
-    pub low_24h: String,
-    pub high_24h: String,
-    pub volume_30d: String,
-    pub best_bid: String,
-    pub best_bid_size: String,
-    pub best_ask: String,
-    pub best_ask_size: String,
+    pub price: StrDecimal,
+    pub open_24h: StrDecimal,
+    pub volume_24h: StrDecimal,
+    pub low_24h: StrDecimal,
+    pub high_24h: StrDecimal,
+    pub volume_30d: StrDecimal,
+    pub best_bid: StrDecimal,
+    pub best_bid_size: StrDecimal,
+    pub best_ask: StrDecimal,
+    pub best_ask_size: StrDecimal,
     pub side: String,
     pub time: String,
     pub trade_id: u64,
-    pub last_size: String,
+    pub last_size: StrDecimal,
+}
+
+impl ExchangeMessage for CoinbaseMessage {
+    fn symbol(&self) -> Option<&str> {
+        match self {
+            CoinbaseMessage::SubscriptionAck { .. } => None,
+            CoinbaseMessage::Ticker(ticker) => Some(&ticker.product_id),
+        }
+    }
+
+    fn instrument(&self) -> Option<Instrument> {
+        Instrument::parse(
+            normalized::Exchange::Coinbase,
+            self.symbol()?,
+            InstrumentKind::Spot,
+        )
+    }
+
+    /// Coinbase answers every request with the full list of active channels
+    fn active_topics(&self) -> Option<Vec<Subscription>> {
+        match self {
+            CoinbaseMessage::SubscriptionAck { channels, .. } => {
+                Some(channels.iter().flat_map(CoinbaseChannel::topics).collect())
+            }
+            CoinbaseMessage::Ticker(_) => None,
+        }
+    }
 }
 
 impl CoinbaseRequest {
-    pub fn trade_request(kind: RequestKind, symbol: impl Into<String>) -> Self {
+    pub fn trade_request(kind: RequestKind, symbol: impl IntoSymbol) -> Self {
         let channels = vec![Self::trade_param(symbol)];
         CoinbaseRequest {
             kind,
@@ -61,10 +111,14 @@ impl CoinbaseRequest {
         }
     }
 
-    pub fn trade_param(symbol: impl Into<String>) -> CoinbaseChannel {
+    pub fn trade_param(symbol: impl IntoSymbol) -> CoinbaseChannel {
         CoinbaseChannel {
             name: "ticker".to_string(),
-            product_ids: vec![symbol.into().to_uppercase()],
+            product_ids: vec![
+                symbol
+                    .into_symbol(normalized::Exchange::Coinbase)
+                    .to_uppercase(),
+            ],
         }
     }
 
@@ -96,28 +150,165 @@ impl CoinbaseRequest {
         self.params.is_empty()
     }
 
-    pub fn with_trade(mut self, symbol: impl Into<String>) -> Self {
+    pub fn with_trade(mut self, symbol: impl IntoSymbol) -> Self {
         self.add_trade(symbol);
         self
     }
 
-    pub fn with_trades(mut self, symbols: Vec<impl Into<String>>) -> Self {
+    pub fn with_trades(mut self, symbols: Vec<impl IntoSymbol>) -> Self {
         self.add_trades(symbols);
         self
     }
 
-    pub fn add_trade(&mut self, symbol: impl Into<String>) {
+    pub fn add_trade(&mut self, symbol: impl IntoSymbol) {
         let channel = CoinbaseChannel {
             name: "ticker".to_string(),
-            product_ids: vec![symbol.into().to_uppercase()],
+            product_ids: vec![
+                symbol
+                    .into_symbol(normalized::Exchange::Coinbase)
+                    .to_uppercase(),
+            ],
         };
 
         self.params.push(channel);
     }
 
-    pub fn add_trades(&mut self, symbols: Vec<impl Into<String>>) {
+    pub fn add_trades(&mut self, symbols: Vec<impl IntoSymbol>) {
         for symbol in symbols {
             self.add_trade(symbol);
         }
     }
 }
+
+impl SubscriptionRequest for CoinbaseRequest {
+    fn kind(&self) -> RequestKind {
+        self.kind
+    }
+
+    fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+
+    fn remove_topics(&mut self, other: &Self) {
+        for channel in self.params.iter_mut() {
+            for removed in other.params.iter().filter(|c| c.name == channel.name) {
+                channel
+                    .product_ids
+                    .retain(|id| !removed.product_ids.contains(id));
+            }
+        }
+        self.params
+            .retain(|channel| !channel.product_ids.is_empty());
+        self.product_ids
+            .retain(|id| !other.product_ids.contains(id));
+    }
+
+    /// Coinbase requests have no id, acks cannot be correlated
+    fn set_request_id(&mut self, _id: u64) -> bool {
+        false
+    }
+
+    fn request_id(&self) -> Option<String> {
+        None
+    }
+
+    fn topics(&self) -> Vec<Subscription> {
+        self.params
+            .iter()
+            .flat_map(CoinbaseChannel::topics)
+            .collect()
+    }
+
+    fn from_topics(kind: RequestKind, topics: &[Subscription], _active: &[Self]) -> Vec<Self> {
+        if topics.is_empty() {
+            return Vec::new();
+        }
+
+        let channels = topics
+            .iter()
+            .map(|topic| CoinbaseChannel {
+                name: topic.channel.clone(),
+                product_ids: vec![topic.symbol.to_uppercase()],
+            })
+            .collect();
+        vec![Self::new(kind, channels)]
+    }
+}
+
+/// The ticker carries the last trade
+impl TryFrom<&CoinbaseTicker> for normalized::Trade {
+    type Error = ExStreamError;
+
+    fn try_from(ticker: &CoinbaseTicker) -> Result<Self, Self::Error> {
+        Ok(normalized::Trade {
+            exchange: normalized::Exchange::Coinbase,
+            symbol: normalized::canonical_symbol(
+                normalized::Exchange::Coinbase,
+                &ticker.product_id,
+            ),
+            price: normalized::parse_number("price", &ticker.price)?,
+            size: normalized::parse_number("size", &ticker.last_size)?,
+            side: ticker.side.parse()?,
+            trade_id: ticker.trade_id.to_string(),
+            exchange_time: normalized::parse_timestamp(&ticker.time)?,
+            local_time: normalized::now_ms(),
+        })
+    }
+}
+
+impl TryFrom<&CoinbaseTicker> for BestBidOffer {
+    type Error = ExStreamError;
+
+    fn try_from(ticker: &CoinbaseTicker) -> Result<Self, Self::Error> {
+        Ok(BestBidOffer {
+            exchange: normalized::Exchange::Coinbase,
+            symbol: normalized::canonical_symbol(
+                normalized::Exchange::Coinbase,
+                &ticker.product_id,
+            ),
+            bid_price: normalized::parse_number("price", &ticker.best_bid)?,
+            bid_size: normalized::parse_number("size", &ticker.best_bid_size)?,
+            ask_price: normalized::parse_number("price", &ticker.best_ask)?,
+            ask_size: normalized::parse_number("size", &ticker.best_ask_size)?,
+            exchange_time: normalized::parse_timestamp(&ticker.time)?,
+            local_time: normalized::now_ms(),
+        })
+    }
+}
+
+impl TryFrom<&CoinbaseTicker> for normalized::Ticker {
+    type Error = ExStreamError;
+
+    fn try_from(ticker: &CoinbaseTicker) -> Result<Self, Self::Error> {
+        Ok(normalized::Ticker {
+            exchange: normalized::Exchange::Coinbase,
+            symbol: normalized::canonical_symbol(
+                normalized::Exchange::Coinbase,
+                &ticker.product_id,
+            ),
+            last_price: normalized::parse_number("price", &ticker.price)?,
+            open_24h: Some(normalized::parse_number("price", &ticker.open_24h)?),
+            high_24h: Some(normalized::parse_number("price", &ticker.high_24h)?),
+            low_24h: Some(normalized::parse_number("price", &ticker.low_24h)?),
+            volume_24h: Some(normalized::parse_number("volume", &ticker.volume_24h)?),
+            exchange_time: normalized::parse_timestamp(&ticker.time)?,
+            local_time: normalized::now_ms(),
+        })
+    }
+}
+
+impl Normalize for CoinbaseMessage {
+    fn normalize(&self) -> Result<Vec<MarketData>, ExStreamError> {
+        match self {
+            CoinbaseMessage::Ticker(ticker) => {
+                let ticker = ticker.as_ref();
+                Ok(vec![
+                    MarketData::Trade(ticker.try_into()?),
+                    MarketData::BestBidOffer(ticker.try_into()?),
+                    MarketData::Ticker(ticker.try_into()?),
+                ])
+            }
+            CoinbaseMessage::SubscriptionAck { .. } => Ok(Vec::new()),
+        }
+    }
+}
@@ -1,11 +1,148 @@
-use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::models::Instrument;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RequestKind {
     Subscribe,
     Unsubscribe,
 }
 
+/// Price or size sent by the exchange as a JSON string.
+/// Kept as the exchange string, or parsed into an exact decimal with the `decimal` feature.
+#[cfg(not(feature = "decimal"))]
+pub type StrDecimal = String;
+#[cfg(feature = "decimal")]
+pub type StrDecimal = rust_decimal::Decimal;
+
+/// Price or size sent by the exchange as a JSON number.
+/// Parsed as `f64`, or from the raw JSON text into an exact decimal with the `decimal` feature.
+#[cfg(not(feature = "decimal"))]
+pub type NumDecimal = f64;
+#[cfg(feature = "decimal")]
+pub type NumDecimal = rust_decimal::Decimal;
+
+/// Ties together the request and message types of an exchange, so a connection only
+/// accepts requests meant for the exchange it is connected to
+pub trait Exchange: Debug + Send + Sync + 'static {
+    type Request: SubscriptionRequest;
+    type Message: DeserializeOwned + ExchangeMessage + Debug + Send + 'static;
+}
+
+/// A single subscribed topic, with the channel and symbol as the exchange names them
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Subscription {
+    pub channel: String,
+    pub symbol: String,
+    pub depth: Option<u64>,
+    /// Candle interval as the exchange names it, e.g. `5` or `D`
+    pub interval: Option<String>,
+}
+
+impl Subscription {
+    pub fn new(channel: impl Into<String>, symbol: impl Into<String>) -> Self {
+        Self {
+            channel: channel.into(),
+            symbol: symbol.into(),
+            depth: None,
+            interval: None,
+        }
+    }
+
+    pub fn with_depth(mut self, depth: u64) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    pub fn with_interval(mut self, interval: impl Into<String>) -> Self {
+        self.interval = Some(interval.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscriptionStatus {
+    /// Sent to the exchange, not acknowledged yet
+    Pending,
+    /// Acknowledged by the exchange
+    Confirmed,
+}
+
+/// Entry of the subscription registry kept by the connection handler
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubscriptionEntry {
+    pub subscription: Subscription,
+    pub status: SubscriptionStatus,
+}
+
+/// Exchange answer to a subscribe or unsubscribe request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubscriptionAck {
+    /// Request id echoed back by the exchange
+    pub request_id: String,
+    /// `Err` with the exchange reason when the request was rejected
+    pub result: Result<(), String>,
+    /// Symbol the ack is about, for exchanges acking every symbol separately
+    pub symbol: Option<String>,
+    /// Topics the exchange rejected when it accepted the rest of the request,
+    /// empty when the whole request succeeded or failed
+    pub rejected: Vec<Subscription>,
+}
+
+/// Behaviour shared by the exchange messages
+pub trait ExchangeMessage {
+    /// Symbol of a market data message, `None` for acks, heartbeats and other control messages
+    fn symbol(&self) -> Option<&str>;
+
+    /// The ack carried by this message, if it answers a request with an id
+    fn ack(&self) -> Option<SubscriptionAck> {
+        None
+    }
+
+    /// Instrument of a market data message, parsed from the exchange symbol
+    fn instrument(&self) -> Option<Instrument> {
+        None
+    }
+
+    /// Full list of active topics, for exchanges answering requests with their whole state
+    fn active_topics(&self) -> Option<Vec<Subscription>> {
+        None
+    }
+}
+
+/// Behaviour shared by the exchange subscription requests, used by the transport
+/// to keep track of the active subscriptions so they can be replayed on reconnect
+pub trait SubscriptionRequest: Serialize + Debug + Clone + Send + Sync + 'static {
+    /// Whether this is a subscribe or an unsubscribe request
+    fn kind(&self) -> RequestKind;
+
+    /// Check if the request has no topics left
+    fn is_empty(&self) -> bool;
+
+    /// Remove the topics contained in `other` from this request
+    fn remove_topics(&mut self, other: &Self);
+
+    /// Set the id echoed back in the exchange ack, returns false if the exchange has no request ids
+    fn set_request_id(&mut self, id: u64) -> bool;
+
+    /// Id echoed back in the exchange ack, if set
+    fn request_id(&self) -> Option<String>;
+
+    /// Topics contained in this request
+    fn topics(&self) -> Vec<Subscription>;
+
+    /// Build the requests for the given topics. `active` holds the current subscribe
+    /// requests, for exchanges that need to carry over settings such as auth tokens.
+    fn from_topics(kind: RequestKind, topics: &[Subscription], active: &[Self]) -> Vec<Self>;
+
+    /// Number of acks the exchange sends back for this request
+    fn expected_acks(&self) -> usize {
+        1
+    }
+}
+
 pub mod to_upper {
     use super::*;
     use serde::Serializer;
@@ -63,3 +200,66 @@ pub mod to_lower {
         }
     }
 }
+
+/// Deserialize a JSON number into a [`NumDecimal`], use with `deserialize_with`.
+/// The raw JSON text is only available when deserializing straight from `serde_json`,
+/// not from the buffered content of untagged or flattened types.
+pub mod raw_number {
+    use super::*;
+
+    #[cfg(not(feature = "decimal"))]
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<NumDecimal, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        f64::deserialize(deserializer)
+    }
+
+    #[cfg(feature = "decimal")]
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<NumDecimal, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let raw = Box::<serde_json::value::RawValue>::deserialize(deserializer)?;
+        let text = raw.get();
+        rust_decimal::Decimal::from_str_exact(text)
+            .or_else(|_| rust_decimal::Decimal::from_scientific(text))
+            .map_err(|e| serde::de::Error::custom(format!("invalid decimal {text}: {e}")))
+    }
+
+    /// Same as [`deserialize`] for optional fields, use together with `#[serde(default)]`
+    pub fn deserialize_option<'de, D>(deserializer: D) -> Result<Option<NumDecimal>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Number(#[serde(deserialize_with = "deserialize")] NumDecimal);
+
+        Ok(Option::<Number>::deserialize(deserializer)?.map(|Number(value)| value))
+    }
+}
+
+/// Deserialize a [`StrDecimal`] sent as an empty string when it does not apply into `None`,
+/// use with `deserialize_with` and `#[serde(default)]`
+pub mod empty_string {
+    use super::*;
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<StrDecimal>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let Some(text) = Option::<String>::deserialize(deserializer)? else {
+            return Ok(None);
+        };
+        if text.is_empty() {
+            return Ok(None);
+        }
+        #[cfg(not(feature = "decimal"))]
+        return Ok(Some(text));
+        #[cfg(feature = "decimal")]
+        return text
+            .parse()
+            .map(Some)
+            .map_err(|e| serde::de::Error::custom(format!("invalid decimal {text}: {e}")));
+    }
+}
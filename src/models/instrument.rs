@@ -0,0 +1,138 @@
+use std::fmt;
+
+use crate::models::normalized::Exchange;
+
+/// Quote assets recognised when splitting symbols without separator, e.g. `ETHUSDT`.
+/// Longer assets come first so `FDUSD` is not mistaken for `USD`.
+const QUOTE_ASSETS: &[&str] = &[
+    "FDUSD", "USDT", "USDC", "BUSD", "TUSD", "USDE", "EURI", "DAI", "USD", "EUR", "GBP", "JPY",
+    "TRY", "BRL", "AUD", "BTC", "ETH", "BNB", "SOL",
+];
+
+/// Suffix of the Binance COIN-M perpetual symbols, e.g. `BTCUSD_PERP`
+const BINANCE_PERP_SUFFIX: &str = "_PERP";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum InstrumentKind {
+    #[default]
+    Spot,
+    Perpetual,
+}
+
+/// Exchange independent instrument, rendered as the symbol each exchange expects
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Instrument {
+    pub base: String,
+    pub quote: String,
+    pub kind: InstrumentKind,
+}
+
+impl Instrument {
+    pub fn new(base: impl Into<String>, quote: impl Into<String>, kind: InstrumentKind) -> Self {
+        Self {
+            base: base.into().to_uppercase(),
+            quote: quote.into().to_uppercase(),
+            kind,
+        }
+    }
+
+    pub fn spot(base: impl Into<String>, quote: impl Into<String>) -> Self {
+        Self::new(base, quote, InstrumentKind::Spot)
+    }
+
+    pub fn perpetual(base: impl Into<String>, quote: impl Into<String>) -> Self {
+        Self::new(base, quote, InstrumentKind::Perpetual)
+    }
+
+    /// Symbol as sent on the wire, e.g. `BTCUSDT`, `BTC-USD` or `BTC/USD`.
+    /// Binance COIN-M perpetuals, the only ones quoted in USD, carry a `_PERP` suffix.
+    pub fn symbol(&self, exchange: Exchange) -> String {
+        match exchange {
+            Exchange::Binance if self.is_coin_margined() => {
+                format!("{}{}{BINANCE_PERP_SUFFIX}", self.base, self.quote)
+            }
+            Exchange::Binance | Exchange::Bybit => format!("{}{}", self.base, self.quote),
+            Exchange::Coinbase => format!("{}-{}", self.base, self.quote),
+            Exchange::Kraken => format!("{}/{}", self.base, self.quote),
+        }
+    }
+
+    /// Parse a symbol received from the exchange. Symbols without separator are split on a
+    /// known quote asset, see [`QUOTE_ASSETS`]. Kraken's `XBT` is reported as `BTC`, and
+    /// Binance symbols with a `_PERP` suffix are perpetuals whatever the given kind.
+    pub fn parse(exchange: Exchange, symbol: &str, kind: InstrumentKind) -> Option<Self> {
+        let mut symbol = symbol.to_uppercase();
+        let mut kind = kind;
+        if exchange == Exchange::Binance
+            && let Some(pair) = symbol.strip_suffix(BINANCE_PERP_SUFFIX)
+        {
+            symbol = pair.to_string();
+            kind = InstrumentKind::Perpetual;
+        }
+        let (base, quote) = match exchange {
+            Exchange::Coinbase => symbol.split_once('-')?,
+            Exchange::Kraken => symbol.split_once('/')?,
+            Exchange::Binance | Exchange::Bybit => QUOTE_ASSETS.iter().find_map(|quote| {
+                let base = symbol.strip_suffix(quote)?;
+                (!base.is_empty()).then_some((base, *quote))
+            })?,
+        };
+
+        let base = match (exchange, base) {
+            (Exchange::Kraken, "XBT") => "BTC",
+            _ => base,
+        };
+        Some(Self::new(base, quote, kind))
+    }
+
+    /// Parse the symbol at the end of a Bybit topic, e.g. `orderbook.50.ETHUSDT`
+    pub fn from_bybit_topic(topic: &str, kind: InstrumentKind) -> Option<Self> {
+        let symbol = topic.rsplit('.').next()?;
+        Self::parse(Exchange::Bybit, symbol, kind)
+    }
+
+    fn is_coin_margined(&self) -> bool {
+        self.kind == InstrumentKind::Perpetual && self.quote == "USD"
+    }
+}
+
+impl fmt::Display for Instrument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.base, self.quote)
+    }
+}
+
+/// Symbol accepted by the requests and builders: an [`Instrument`], or a string sent as given
+pub trait IntoSymbol {
+    fn into_symbol(self, exchange: Exchange) -> String;
+}
+
+impl IntoSymbol for Instrument {
+    fn into_symbol(self, exchange: Exchange) -> String {
+        self.symbol(exchange)
+    }
+}
+
+impl IntoSymbol for &Instrument {
+    fn into_symbol(self, exchange: Exchange) -> String {
+        self.symbol(exchange)
+    }
+}
+
+impl IntoSymbol for String {
+    fn into_symbol(self, _exchange: Exchange) -> String {
+        self
+    }
+}
+
+impl IntoSymbol for &String {
+    fn into_symbol(self, _exchange: Exchange) -> String {
+        self.clone()
+    }
+}
+
+impl IntoSymbol for &str {
+    fn into_symbol(self, _exchange: Exchange) -> String {
+        self.to_string()
+    }
+}
@@ -1,6 +1,21 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::value::RawValue;
 
-use crate::models::{RequestKind, to_lower};
+use crate::error::ExStreamError;
+use crate::models::normalized::{self, MarketData, Normalize};
+use crate::models::{
+    Exchange, ExchangeMessage, Instrument, InstrumentKind, IntoSymbol, NumDecimal, RequestKind,
+    Subscription, SubscriptionAck, SubscriptionRequest, raw_number, to_lower,
+};
+
+/// Marker type for Kraken connections
+#[derive(Debug, Clone, Copy)]
+pub struct Kraken;
+
+impl Exchange for Kraken {
+    type Request = KrakenRequest;
+    type Message = KrakenMessage;
+}
 
 #[derive(Serialize, Debug, Clone)]
 pub struct KrakenRequest {
@@ -16,6 +31,10 @@ pub struct KrakenRequest {
 pub enum KrakenParams {
     Trade(KrakenTradeParams),
     L3(KrakenL3Params),
+    Book(KrakenBookParams),
+    Ticker(KrakenTickerParams),
+    Ohlc(KrakenOhlcParams),
+    Instrument(KrakenInstrumentParams),
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -42,62 +61,286 @@ pub struct KrakenL3Params {
     pub token: String, // Authentication token
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct KrakenBookParams {
+    /// This must be "book" for L2 book requests
+    channel: KrakenChannel,
+    pub symbol: Vec<String>,
+    /// Possible values: [10, 25, 100, 500, 1000]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub depth: Option<u64>,
+    /// Request a snapshot after subscribing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct KrakenTickerParams {
+    /// This must be "ticker" for ticker requests
+    channel: KrakenChannel,
+    pub symbol: Vec<String>,
+    /// Send an update on every best bid or offer change, or on trades only (default)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event_trigger: Option<KrakenEventTrigger>,
+    /// Request a snapshot after subscribing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct KrakenOhlcParams {
+    /// This must be "ohlc" for candle requests
+    channel: KrakenChannel,
+    pub symbol: Vec<String>,
+    /// Candle interval in minutes, possible values: [1, 5, 15, 30, 60, 240, 1440, 10080, 21600]
+    pub interval: u64,
+    /// Request a snapshot after subscribing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct KrakenInstrumentParams {
+    /// This must be "instrument" for reference data requests
+    channel: KrakenChannel,
+    /// Request a snapshot after subscribing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<bool>,
+    /// The channel covers every pair, so there are no symbols to tell whether
+    /// the request still subscribes to anything
+    #[serde(skip)]
+    active: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum KrakenEventTrigger {
+    Bbo,
+    Trades,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum KrakenChannel {
     Trade,
     #[serde(rename = "level3")]
     L3,
+    /// Public L2 book, aggregated by price level
+    Book,
+    Ticker,
+    /// Candles of the subscribed interval
+    Ohlc,
+    /// Reference data of every asset and pair, e.g. the precisions used by book checksums
+    Instrument,
 }
 
-#[derive(Deserialize, Debug, Clone)]
-#[serde(untagged)]
+impl KrakenChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KrakenChannel::Trade => "trade",
+            KrakenChannel::L3 => "level3",
+            KrakenChannel::Book => "book",
+            KrakenChannel::Ticker => "ticker",
+            KrakenChannel::Ohlc => "ohlc",
+            KrakenChannel::Instrument => "instrument",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "trade" => Some(KrakenChannel::Trade),
+            "level3" => Some(KrakenChannel::L3),
+            "book" => Some(KrakenChannel::Book),
+            "ticker" => Some(KrakenChannel::Ticker),
+            "ohlc" => Some(KrakenChannel::Ohlc),
+            "instrument" => Some(KrakenChannel::Instrument),
+            _ => None,
+        }
+    }
+}
+
+/// Messages are told apart by their `method` or `channel` field rather than by trying
+/// every variant, so the data is parsed straight from the JSON text, see [`raw_number`]
+#[derive(Debug, Clone)]
 pub enum KrakenMessage {
     SubscriptionAck {
-        #[serde(rename = "method", with = "to_lower")]
         kind: RequestKind,
-        result: KrakenParams,
+        /// Only present on success
+        result: Option<KrakenAckResult>,
         success: bool,
-        error: String,
+        /// Only present on failure
+        error: Option<String>,
+        /// Only present on failure
+        symbol: Option<String>,
+        time_in: String,
+        time_out: String,
+        req_id: Option<u64>,
+    },
+    /// Answer to a `ping`, only forwarded when the connection has no heartbeat
+    Pong {
+        method: KrakenMethod,
         time_in: String,
         time_out: String,
         req_id: Option<u64>,
     },
     Event(KrakenEvent),
+    /// Heartbeats and channels without a dedicated model, e.g. `status`
     Heartbeat {
         channel: String,
     },
 }
 
+/// Methods answered by Kraken outside of subscriptions
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum KrakenMethod {
+    Pong,
+}
+
+/// Every field a Kraken message can have at the top level
+#[derive(Deserialize)]
+struct KrakenFrame {
+    method: Option<String>,
+    channel: Option<String>,
+    #[serde(rename = "type")]
+    kind: Option<KrakenEventKind>,
+    data: Option<Box<RawValue>>,
+    result: Option<KrakenAckResult>,
+    success: Option<bool>,
+    error: Option<String>,
+    symbol: Option<String>,
+    time_in: Option<String>,
+    time_out: Option<String>,
+    req_id: Option<u64>,
+}
+
+impl<'de> Deserialize<'de> for KrakenMessage {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        let frame = KrakenFrame::deserialize(deserializer)?;
+        if let Some(method) = frame.method {
+            let time_in = frame
+                .time_in
+                .ok_or_else(|| D::Error::missing_field("time_in"))?;
+            let time_out = frame
+                .time_out
+                .ok_or_else(|| D::Error::missing_field("time_out"))?;
+            if method == "pong" {
+                return Ok(KrakenMessage::Pong {
+                    method: KrakenMethod::Pong,
+                    time_in,
+                    time_out,
+                    req_id: frame.req_id,
+                });
+            }
+
+            let kind =
+                to_lower::deserialize(serde::de::value::StrDeserializer::<D::Error>::new(&method))?;
+            return Ok(KrakenMessage::SubscriptionAck {
+                kind,
+                result: frame.result,
+                success: frame
+                    .success
+                    .ok_or_else(|| D::Error::missing_field("success"))?,
+                error: frame.error,
+                symbol: frame.symbol,
+                time_in,
+                time_out,
+                req_id: frame.req_id,
+            });
+        }
+
+        let channel = frame
+            .channel
+            .ok_or_else(|| D::Error::missing_field("channel"))?;
+        let (Some(event_channel), Some(kind), Some(data)) =
+            (KrakenChannel::from_name(&channel), frame.kind, frame.data)
+        else {
+            return Ok(KrakenMessage::Heartbeat { channel });
+        };
+
+        let data = match event_channel {
+            KrakenChannel::Trade => serde_json::from_str::<Vec<KrakenTradeData>>(data.get())
+                .map(|data| data.into_iter().map(KrakenData::Trade).collect()),
+            KrakenChannel::L3 => match kind {
+                KrakenEventKind::Snapshot => serde_json::from_str::<Vec<KrakenBook>>(data.get())
+                    .map(|data| data.into_iter().map(KrakenData::Book).collect()),
+                KrakenEventKind::Update => {
+                    serde_json::from_str::<Vec<KrakenBookUpdate>>(data.get())
+                        .map(|data| data.into_iter().map(KrakenData::BookUpdate).collect())
+                }
+            },
+            KrakenChannel::Book => serde_json::from_str::<Vec<KrakenL2Book>>(data.get())
+                .map(|data| data.into_iter().map(KrakenData::L2Book).collect()),
+            KrakenChannel::Ticker => serde_json::from_str::<Vec<KrakenTicker>>(data.get())
+                .map(|data| data.into_iter().map(KrakenData::Ticker).collect()),
+            KrakenChannel::Ohlc => serde_json::from_str::<Vec<KrakenOhlc>>(data.get())
+                .map(|data| data.into_iter().map(KrakenData::Ohlc).collect()),
+            // Reference data is a single object rather than a list
+            KrakenChannel::Instrument => serde_json::from_str::<KrakenInstruments>(data.get())
+                .map(|data| vec![KrakenData::Instrument(data)]),
+        }
+        .map_err(D::Error::custom)?;
+
+        Ok(KrakenMessage::Event(KrakenEvent {
+            channel: event_channel,
+            kind,
+            data,
+        }))
+    }
+}
+
+/// Subscription echoed back in a successful ack, one ack is sent per symbol
 #[derive(Deserialize, Debug, Clone)]
+pub struct KrakenAckResult {
+    pub channel: KrakenChannel,
+    pub symbol: Option<String>,
+    pub snapshot: Option<bool>,
+    pub depth: Option<u64>,
+    pub interval: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
 pub struct KrakenEvent {
     pub channel: KrakenChannel,
-    #[serde(rename = "type")]
     pub kind: KrakenEventKind,
     pub data: Vec<KrakenData>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum KrakenEventKind {
     Snapshot,
     Update,
 }
 
-#[derive(Deserialize, Debug, Clone)]
-#[serde(untagged)]
+/// Data of an event, level3 snapshots and updates are told apart by the message `type`
+#[derive(Debug, Clone)]
 pub enum KrakenData {
     Trade(KrakenTradeData),
+    /// Level3 snapshot, every resting order of the subscribed depth
     Book(KrakenBook),
+    /// Level3 update, only the orders that were added, modified or deleted
+    BookUpdate(KrakenBookUpdate),
+    /// L2 book snapshot or the levels that changed, depending on the message `type`
+    L2Book(KrakenL2Book),
+    Ticker(KrakenTicker),
+    Ohlc(KrakenOhlc),
+    Instrument(KrakenInstruments),
 }
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct KrakenTradeData {
     pub symbol: String,
     pub side: String,
-    #[serde(rename = "qty")]
-    pub size: f64,
-    pub price: f64,
+    #[serde(rename = "qty", deserialize_with = "raw_number::deserialize")]
+    pub size: NumDecimal,
+    #[serde(deserialize_with = "raw_number::deserialize")]
+    pub price: NumDecimal,
     #[serde(rename = "ord_type")]
     pub order_type: String,
     pub trade_id: u64,
@@ -110,29 +353,230 @@ pub struct KrakenBook {
     pub checksum: u64,
     pub bids: Vec<KrakenOrderEntry>,
     pub asks: Vec<KrakenOrderEntry>,
+    pub timestamp: Option<String>, // Format: RFC3339
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct KrakenBookUpdate {
+    pub symbol: String,
+    /// Checksum of the book after applying the update
+    pub checksum: u64,
+    pub bids: Vec<KrakenOrderUpdate>,
+    pub asks: Vec<KrakenOrderUpdate>,
+    pub timestamp: Option<String>, // Format: RFC3339
 }
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct KrakenOrderEntry {
     pub order_id: String,
-    #[serde(rename = "limit_price")]
-    pub price: f64,
-    #[serde(rename = "order_qty")]
-    pub size: f64,
+    #[serde(rename = "limit_price", deserialize_with = "raw_number::deserialize")]
+    pub price: NumDecimal,
+    #[serde(rename = "order_qty", deserialize_with = "raw_number::deserialize")]
+    pub size: NumDecimal,
     pub timestamp: String, // Format: RFC3339
 }
 
-impl KrakenRequest {
-    pub fn new(kind: RequestKind, params: KrakenParams) -> Self {
-        KrakenRequest {
-            kind,
-            params,
-            id: None,
+#[derive(Deserialize, Debug, Clone)]
+pub struct KrakenOrderUpdate {
+    pub event: KrakenOrderEvent,
+    pub order_id: String,
+    #[serde(rename = "limit_price", deserialize_with = "raw_number::deserialize")]
+    pub price: NumDecimal,
+    /// Remaining quantity after a modify, the last known quantity of a deleted order
+    #[serde(rename = "order_qty", deserialize_with = "raw_number::deserialize")]
+    pub size: NumDecimal,
+    pub timestamp: String, // Format: RFC3339
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct KrakenL2Book {
+    pub symbol: String,
+    /// Checksum of the top 10 levels of the book after applying the message
+    pub checksum: u64,
+    pub bids: Vec<KrakenLevel>,
+    pub asks: Vec<KrakenLevel>,
+    /// Only present in updates
+    pub timestamp: Option<String>, // Format: RFC3339
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct KrakenLevel {
+    #[serde(deserialize_with = "raw_number::deserialize")]
+    pub price: NumDecimal,
+    /// Zero when the level is removed from the book
+    #[serde(rename = "qty", deserialize_with = "raw_number::deserialize")]
+    pub size: NumDecimal,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct KrakenTicker {
+    pub symbol: String,
+    #[serde(deserialize_with = "raw_number::deserialize")]
+    pub bid: NumDecimal,
+    #[serde(deserialize_with = "raw_number::deserialize")]
+    pub bid_qty: NumDecimal,
+    #[serde(deserialize_with = "raw_number::deserialize")]
+    pub ask: NumDecimal,
+    #[serde(deserialize_with = "raw_number::deserialize")]
+    pub ask_qty: NumDecimal,
+    #[serde(deserialize_with = "raw_number::deserialize")]
+    pub last: NumDecimal,
+    /// Volume of the last 24h, in the base currency
+    #[serde(deserialize_with = "raw_number::deserialize")]
+    pub volume: NumDecimal,
+    #[serde(deserialize_with = "raw_number::deserialize")]
+    pub vwap: NumDecimal,
+    #[serde(deserialize_with = "raw_number::deserialize")]
+    pub low: NumDecimal,
+    #[serde(deserialize_with = "raw_number::deserialize")]
+    pub high: NumDecimal,
+    /// Price change of the last 24h
+    #[serde(deserialize_with = "raw_number::deserialize")]
+    pub change: NumDecimal,
+    #[serde(deserialize_with = "raw_number::deserialize")]
+    pub change_pct: NumDecimal,
+    pub timestamp: Option<String>, // Format: RFC3339
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct KrakenOhlc {
+    pub symbol: String,
+    #[serde(deserialize_with = "raw_number::deserialize")]
+    pub open: NumDecimal,
+    #[serde(deserialize_with = "raw_number::deserialize")]
+    pub high: NumDecimal,
+    #[serde(deserialize_with = "raw_number::deserialize")]
+    pub low: NumDecimal,
+    #[serde(deserialize_with = "raw_number::deserialize")]
+    pub close: NumDecimal,
+    #[serde(deserialize_with = "raw_number::deserialize")]
+    pub volume: NumDecimal,
+    #[serde(deserialize_with = "raw_number::deserialize")]
+    pub vwap: NumDecimal,
+    /// Number of trades in the candle
+    pub trades: u64,
+    pub interval_begin: String, // Format: RFC3339
+    /// Candle interval in minutes
+    pub interval: u64,
+    pub timestamp: Option<String>, // Format: RFC3339
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct KrakenInstruments {
+    pub assets: Vec<KrakenAsset>,
+    pub pairs: Vec<KrakenPair>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct KrakenAsset {
+    pub id: String,
+    pub status: String,
+    /// Decimals used for amounts of the asset
+    pub precision: u32,
+    /// Decimals used when displaying amounts of the asset
+    pub precision_display: u32,
+    pub borrowable: bool,
+    #[serde(deserialize_with = "raw_number::deserialize")]
+    pub collateral_value: NumDecimal,
+    #[serde(default, deserialize_with = "raw_number::deserialize_option")]
+    pub margin_rate: Option<NumDecimal>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct KrakenPair {
+    pub symbol: String,
+    pub base: String,
+    pub quote: String,
+    pub status: String,
+    /// Decimals of the prices, used by the book checksums
+    pub price_precision: u32,
+    #[serde(deserialize_with = "raw_number::deserialize")]
+    pub price_increment: NumDecimal,
+    /// Decimals of the quantities, used by the book checksums
+    pub qty_precision: u32,
+    #[serde(deserialize_with = "raw_number::deserialize")]
+    pub qty_increment: NumDecimal,
+    #[serde(deserialize_with = "raw_number::deserialize")]
+    pub qty_min: NumDecimal,
+    pub cost_precision: u32,
+    pub marginable: bool,
+    pub has_index: bool,
+    #[serde(default, deserialize_with = "raw_number::deserialize_option")]
+    pub margin_initial: Option<NumDecimal>,
+    pub position_limit_long: Option<u64>,
+    pub position_limit_short: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum KrakenOrderEvent {
+    /// New order, queued behind the orders already resting at its price
+    Add,
+    /// Quantity or price change of a resting order
+    Modify,
+    /// Order filled or cancelled
+    Delete,
+}
+
+impl ExchangeMessage for KrakenMessage {
+    fn symbol(&self) -> Option<&str> {
+        match self {
+            KrakenMessage::Event(event) => event.data.first().and_then(|data| match data {
+                KrakenData::Trade(trade) => Some(trade.symbol.as_str()),
+                KrakenData::Book(book) => Some(book.symbol.as_str()),
+                KrakenData::BookUpdate(update) => Some(update.symbol.as_str()),
+                KrakenData::L2Book(book) => Some(book.symbol.as_str()),
+                KrakenData::Ticker(ticker) => Some(ticker.symbol.as_str()),
+                KrakenData::Ohlc(candle) => Some(candle.symbol.as_str()),
+                KrakenData::Instrument(_) => None,
+            }),
+            KrakenMessage::SubscriptionAck { .. }
+            | KrakenMessage::Pong { .. }
+            | KrakenMessage::Heartbeat { .. } => None,
         }
     }
 
-    pub fn new_subscribe(channel: KrakenChannel) -> Self {
-        let params = match channel {
+    fn instrument(&self) -> Option<Instrument> {
+        Instrument::parse(
+            normalized::Exchange::Kraken,
+            self.symbol()?,
+            InstrumentKind::Spot,
+        )
+    }
+
+    fn ack(&self) -> Option<SubscriptionAck> {
+        let KrakenMessage::SubscriptionAck {
+            success,
+            result: ack_result,
+            error,
+            symbol,
+            req_id: Some(req_id),
+            ..
+        } = self
+        else {
+            return None;
+        };
+
+        let result = match success {
+            true => Ok(()),
+            false => Err(error.clone().unwrap_or_default()),
+        };
+        let symbol = symbol
+            .clone()
+            .or_else(|| ack_result.as_ref().and_then(|r| r.symbol.clone()));
+        Some(SubscriptionAck {
+            request_id: req_id.to_string(),
+            result,
+            symbol,
+            rejected: Vec::new(),
+        })
+    }
+}
+
+impl KrakenParams {
+    /// Empty params of the channel, L3 params still need a token
+    pub fn new(channel: KrakenChannel) -> Self {
+        match channel {
             KrakenChannel::Trade => KrakenParams::Trade(KrakenTradeParams {
                 channel,
                 symbol: Vec::new(),
@@ -145,42 +589,103 @@ impl KrakenRequest {
                 snapshot: Some(true),
                 token: String::new(), // Token should be set later
             }),
-        };
-
-        KrakenRequest {
-            kind: RequestKind::Subscribe,
-            params,
-            id: None,
-        }
-    }
-
-    pub fn new_unsubscribe(channel: KrakenChannel) -> Self {
-        let params = match channel {
-            KrakenChannel::Trade => KrakenParams::Trade(KrakenTradeParams {
+            KrakenChannel::Book => KrakenParams::Book(KrakenBookParams {
                 channel,
                 symbol: Vec::new(),
+                depth: None,
+                snapshot: Some(true),
+            }),
+            KrakenChannel::Ticker => KrakenParams::Ticker(KrakenTickerParams {
+                channel,
+                symbol: Vec::new(),
+                event_trigger: None,
                 snapshot: None,
             }),
-            KrakenChannel::L3 => KrakenParams::L3(KrakenL3Params {
+            KrakenChannel::Ohlc => KrakenParams::Ohlc(KrakenOhlcParams {
                 channel,
                 symbol: Vec::new(),
-                depth: None,
-                snapshot: Some(true),
-                token: String::new(), // Token should be set later
+                interval: 1,
+                snapshot: None,
             }),
-        };
+            KrakenChannel::Instrument => KrakenParams::Instrument(KrakenInstrumentParams {
+                channel,
+                snapshot: None,
+                active: true,
+            }),
+        }
+    }
+
+    pub fn channel(&self) -> KrakenChannel {
+        match self {
+            KrakenParams::Trade(params) => params.channel,
+            KrakenParams::L3(params) => params.channel,
+            KrakenParams::Book(params) => params.channel,
+            KrakenParams::Ticker(params) => params.channel,
+            KrakenParams::Ohlc(params) => params.channel,
+            KrakenParams::Instrument(params) => params.channel,
+        }
+    }
 
+    /// Symbols of the request, the instrument channel has none
+    pub fn symbols(&self) -> &[String] {
+        match self {
+            KrakenParams::Trade(params) => &params.symbol,
+            KrakenParams::L3(params) => &params.symbol,
+            KrakenParams::Book(params) => &params.symbol,
+            KrakenParams::Ticker(params) => &params.symbol,
+            KrakenParams::Ohlc(params) => &params.symbol,
+            KrakenParams::Instrument(_) => &[],
+        }
+    }
+
+    fn symbols_mut(&mut self) -> Option<&mut Vec<String>> {
+        match self {
+            KrakenParams::Trade(params) => Some(&mut params.symbol),
+            KrakenParams::L3(params) => Some(&mut params.symbol),
+            KrakenParams::Book(params) => Some(&mut params.symbol),
+            KrakenParams::Ticker(params) => Some(&mut params.symbol),
+            KrakenParams::Ohlc(params) => Some(&mut params.symbol),
+            KrakenParams::Instrument(_) => None,
+        }
+    }
+
+    fn depth(&self) -> Option<u64> {
+        match self {
+            KrakenParams::L3(params) => params.depth,
+            KrakenParams::Book(params) => params.depth,
+            _ => None,
+        }
+    }
+
+    fn interval(&self) -> Option<u64> {
+        match self {
+            KrakenParams::Ohlc(params) => Some(params.interval),
+            _ => None,
+        }
+    }
+}
+
+impl KrakenRequest {
+    pub fn new(kind: RequestKind, params: KrakenParams) -> Self {
         KrakenRequest {
-            kind: RequestKind::Unsubscribe,
+            kind,
             params,
             id: None,
         }
     }
 
+    pub fn new_subscribe(channel: KrakenChannel) -> Self {
+        Self::new(RequestKind::Subscribe, KrakenParams::new(channel))
+    }
+
+    pub fn new_unsubscribe(channel: KrakenChannel) -> Self {
+        Self::new(RequestKind::Unsubscribe, KrakenParams::new(channel))
+    }
+
     pub fn is_empty(&self) -> bool {
         match self.params {
-            KrakenParams::Trade(ref params) => params.symbol.is_empty(),
-            KrakenParams::L3(ref params) => params.symbol.is_empty(),
+            KrakenParams::Instrument(ref params) => !params.active,
+            ref params => params.symbols().is_empty(),
         }
     }
 
@@ -207,30 +712,261 @@ impl KrakenRequest {
     }
 
     pub fn set_depth(&mut self, depth: u64) {
-        if let KrakenParams::L3(ref mut params) = self.params {
-            params.depth = Some(depth);
+        match self.params {
+            KrakenParams::L3(ref mut params) => params.depth = Some(depth),
+            KrakenParams::Book(ref mut params) => params.depth = Some(depth),
+            _ => {}
         }
     }
 
-    pub fn add_symbol(&mut self, symbol: impl Into<String>) {
-        match &mut self.params {
-            KrakenParams::Trade(params) => params.symbol.push(symbol.into().to_uppercase()),
-            KrakenParams::L3(params) => params.symbol.push(symbol.into().to_uppercase()),
+    /// Set the candle interval in minutes of ohlc requests
+    pub fn set_interval(&mut self, interval: u64) {
+        if let KrakenParams::Ohlc(ref mut params) = self.params {
+            params.interval = interval;
+        }
+    }
+
+    /// Set when ticker updates are sent
+    pub fn set_event_trigger(&mut self, trigger: KrakenEventTrigger) {
+        if let KrakenParams::Ticker(ref mut params) = self.params {
+            params.event_trigger = Some(trigger);
+        }
+    }
+
+    /// Add a symbol, ignored by the instrument channel which covers every pair
+    pub fn add_symbol(&mut self, symbol: impl IntoSymbol) {
+        if let Some(symbols) = self.params.symbols_mut() {
+            symbols.push(
+                symbol
+                    .into_symbol(normalized::Exchange::Kraken)
+                    .to_uppercase(),
+            );
         }
     }
 
-    pub fn add_symbols(&mut self, symbols: Vec<impl Into<String>>) {
+    pub fn add_symbols(&mut self, symbols: Vec<impl IntoSymbol>) {
+        for symbol in symbols {
+            self.add_symbol(symbol);
+        }
+    }
+}
+
+impl SubscriptionRequest for KrakenRequest {
+    fn kind(&self) -> RequestKind {
+        self.kind
+    }
+
+    fn is_empty(&self) -> bool {
+        KrakenRequest::is_empty(self)
+    }
+
+    fn remove_topics(&mut self, other: &Self) {
+        // Books of another depth or candles of another interval are other topics
+        if self.params.channel() != other.params.channel()
+            || self.params.depth() != other.params.depth()
+            || self.params.interval() != other.params.interval()
+        {
+            return;
+        }
+
         match &mut self.params {
-            KrakenParams::Trade(params) => {
-                for symbol in symbols {
-                    params.symbol.push(symbol.into().to_uppercase());
+            KrakenParams::Instrument(params) => params.active = false,
+            params => {
+                let removed = other.params.symbols();
+                if let Some(symbols) = params.symbols_mut() {
+                    symbols.retain(|s| !removed.contains(s));
                 }
             }
-            KrakenParams::L3(params) => {
-                for symbol in symbols {
-                    params.symbol.push(symbol.into().to_uppercase());
+        }
+    }
+
+    fn set_request_id(&mut self, id: u64) -> bool {
+        self.set_id(id);
+        true
+    }
+
+    fn request_id(&self) -> Option<String> {
+        self.id.map(|id| id.to_string())
+    }
+
+    /// The instrument channel is reported as a single topic with an empty symbol
+    fn topics(&self) -> Vec<Subscription> {
+        let channel = self.params.channel().as_str();
+        let topic = |symbol: &str| Subscription {
+            channel: channel.to_string(),
+            symbol: symbol.to_string(),
+            depth: self.params.depth(),
+            interval: self.params.interval().map(|interval| interval.to_string()),
+        };
+
+        match &self.params {
+            KrakenParams::Instrument(params) if params.active => vec![topic("")],
+            params => params
+                .symbols()
+                .iter()
+                .map(|symbol| topic(symbol))
+                .collect(),
+        }
+    }
+
+    /// Level3 requests reuse the token of an active level3 subscription
+    fn from_topics(kind: RequestKind, topics: &[Subscription], active: &[Self]) -> Vec<Self> {
+        let token = active.iter().find_map(|request| match &request.params {
+            KrakenParams::L3(params) if !params.token.is_empty() => Some(params.token.clone()),
+            _ => None,
+        });
+
+        // One request per channel, depth and interval, as Kraken params hold a single channel
+        let mut requests: Vec<Self> = Vec::new();
+        for topic in topics {
+            let Some(channel) = KrakenChannel::from_name(&topic.channel) else {
+                tracing::warn!("Unknown Kraken channel {}", topic.channel);
+                continue;
+            };
+            let interval = match &topic.interval {
+                Some(interval) => match interval.parse::<u64>() {
+                    Ok(interval) => Some(interval),
+                    Err(_) => {
+                        tracing::warn!("Invalid Kraken ohlc interval {}", interval);
+                        continue;
+                    }
+                },
+                None => None,
+            };
+
+            let existing = requests.iter_mut().find(|request| {
+                request.params.channel() == channel
+                    && request.params.depth() == topic.depth
+                    && interval.is_none_or(|interval| request.params.interval() == Some(interval))
+            });
+            let request = match existing {
+                Some(request) => request,
+                None => {
+                    let mut request = match kind {
+                        RequestKind::Subscribe => Self::new_subscribe(channel),
+                        RequestKind::Unsubscribe => Self::new_unsubscribe(channel),
+                    };
+                    if let Some(depth) = topic.depth {
+                        request.set_depth(depth);
+                    }
+                    if let Some(interval) = interval {
+                        request.set_interval(interval);
+                    }
+                    if let Some(token) = &token {
+                        request.set_token(token.clone());
+                    }
+                    requests.push(request);
+                    requests.last_mut().expect("request was just pushed")
                 }
-            }
+            };
+            request.add_symbol(&topic.symbol);
         }
+        requests
+    }
+
+    /// Kraken acks every symbol separately
+    fn expected_acks(&self) -> usize {
+        self.params.symbols().len().max(1)
+    }
+}
+
+impl TryFrom<&KrakenTradeData> for normalized::Trade {
+    type Error = ExStreamError;
+
+    fn try_from(trade: &KrakenTradeData) -> Result<Self, Self::Error> {
+        Ok(normalized::Trade {
+            exchange: normalized::Exchange::Kraken,
+            symbol: normalized::canonical_symbol(normalized::Exchange::Kraken, &trade.symbol),
+            price: trade.price,
+            size: trade.size,
+            side: trade.side.parse()?,
+            trade_id: trade.trade_id.to_string(),
+            exchange_time: normalized::parse_timestamp(&trade.timestamp)?,
+            local_time: normalized::now_ms(),
+        })
+    }
+}
+
+impl TryFrom<&KrakenTicker> for normalized::Ticker {
+    type Error = ExStreamError;
+
+    fn try_from(ticker: &KrakenTicker) -> Result<Self, Self::Error> {
+        let local_time = normalized::now_ms();
+        let exchange_time = match &ticker.timestamp {
+            Some(timestamp) => normalized::parse_timestamp(timestamp)?,
+            None => local_time,
+        };
+
+        Ok(normalized::Ticker {
+            exchange: normalized::Exchange::Kraken,
+            symbol: normalized::canonical_symbol(normalized::Exchange::Kraken, &ticker.symbol),
+            last_price: ticker.last,
+            open_24h: Some(ticker.last - ticker.change),
+            high_24h: Some(ticker.high),
+            low_24h: Some(ticker.low),
+            volume_24h: Some(ticker.volume),
+            exchange_time,
+            local_time,
+        })
+    }
+}
+
+impl KrakenL2Book {
+    /// Normalize the book, which is a snapshot or an update depending on the message `type`
+    pub fn to_book_update(
+        &self,
+        kind: KrakenEventKind,
+    ) -> Result<normalized::BookUpdate, ExStreamError> {
+        let local_time = normalized::now_ms();
+        let exchange_time = match &self.timestamp {
+            Some(timestamp) => normalized::parse_timestamp(timestamp)?,
+            None => local_time,
+        };
+        let levels = |levels: &[KrakenLevel]| {
+            levels
+                .iter()
+                .map(|level| normalized::PriceLevel {
+                    price: level.price,
+                    size: level.size,
+                })
+                .collect()
+        };
+
+        Ok(normalized::BookUpdate {
+            exchange: normalized::Exchange::Kraken,
+            symbol: normalized::canonical_symbol(normalized::Exchange::Kraken, &self.symbol),
+            is_snapshot: kind == KrakenEventKind::Snapshot,
+            bids: levels(&self.bids),
+            asks: levels(&self.asks),
+            sequence: None,
+            exchange_time,
+            local_time,
+        })
+    }
+}
+
+impl Normalize for KrakenMessage {
+    /// Level3 books carry individual orders, which have no aggregated equivalent without
+    /// maintaining the book, so they normalize to nothing
+    fn normalize(&self) -> Result<Vec<MarketData>, ExStreamError> {
+        let KrakenMessage::Event(event) = self else {
+            return Ok(Vec::new());
+        };
+
+        event
+            .data
+            .iter()
+            .filter_map(|data| match data {
+                KrakenData::Trade(trade) => Some(trade.try_into().map(MarketData::Trade)),
+                KrakenData::L2Book(book) => {
+                    Some(book.to_book_update(event.kind).map(MarketData::BookUpdate))
+                }
+                KrakenData::Ticker(ticker) => Some(ticker.try_into().map(MarketData::Ticker)),
+                KrakenData::Book(_)
+                | KrakenData::BookUpdate(_)
+                | KrakenData::Ohlc(_)
+                | KrakenData::Instrument(_) => None,
+            })
+            .collect()
     }
 }
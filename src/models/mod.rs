@@ -1,11 +1,16 @@
 mod binance;
+mod binance_user_data;
 mod bybit;
 mod coinbase;
 mod common;
+mod instrument;
 mod kraken;
+pub mod normalized;
 
 pub use binance::*;
+pub use binance_user_data::*;
 pub use bybit::*;
 pub use coinbase::*;
 pub use common::*;
+pub use instrument::*;
 pub use kraken::*;
@@ -0,0 +1,223 @@
+//! Exchange agnostic market data, converted from the exchange specific messages
+
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use futures_util::{StreamExt as _, stream};
+
+use crate::error::ExStreamError;
+use crate::models::{Instrument, InstrumentKind, NumDecimal, StrDecimal};
+use crate::transport::WsMsgStream;
+
+/// Exchange a normalized message comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Exchange {
+    Binance,
+    Bybit,
+    Coinbase,
+    Kraken,
+}
+
+/// Side of the taker of a trade
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl FromStr for Side {
+    type Err = ExStreamError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("buy") {
+            Ok(Side::Buy)
+        } else if s.eq_ignore_ascii_case("sell") {
+            Ok(Side::Sell)
+        } else {
+            Err(ExStreamError::NormalizeError(format!("unknown side {s}")))
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trade {
+    pub exchange: Exchange,
+    /// Canonical symbol, see [`canonical_symbol`]
+    pub symbol: String,
+    pub price: NumDecimal,
+    pub size: NumDecimal,
+    pub side: Side,
+    pub trade_id: String,
+    /// Exchange timestamp in milliseconds since the epoch
+    pub exchange_time: u64,
+    /// Local timestamp in milliseconds since the epoch, taken when the message is normalized
+    pub local_time: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PriceLevel {
+    pub price: NumDecimal,
+    /// Zero when the level is removed from the book
+    pub size: NumDecimal,
+}
+
+/// Aggregated order book levels, either a full snapshot or the levels that changed
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookUpdate {
+    pub exchange: Exchange,
+    pub symbol: String,
+    pub is_snapshot: bool,
+    pub bids: Vec<PriceLevel>,
+    pub asks: Vec<PriceLevel>,
+    /// Exchange sequence number, when the exchange provides one
+    pub sequence: Option<u64>,
+    pub exchange_time: u64,
+    pub local_time: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BestBidOffer {
+    pub exchange: Exchange,
+    pub symbol: String,
+    pub bid_price: NumDecimal,
+    pub bid_size: NumDecimal,
+    pub ask_price: NumDecimal,
+    pub ask_size: NumDecimal,
+    pub exchange_time: u64,
+    pub local_time: u64,
+}
+
+/// Rolling 24h statistics
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ticker {
+    pub exchange: Exchange,
+    pub symbol: String,
+    pub last_price: NumDecimal,
+    pub open_24h: Option<NumDecimal>,
+    pub high_24h: Option<NumDecimal>,
+    pub low_24h: Option<NumDecimal>,
+    pub volume_24h: Option<NumDecimal>,
+    pub exchange_time: u64,
+    pub local_time: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MarketData {
+    Trade(Trade),
+    BookUpdate(BookUpdate),
+    BestBidOffer(BestBidOffer),
+    Ticker(Ticker),
+}
+
+/// Conversion of a whole exchange message, which can carry any number of market data updates.
+/// Acks, heartbeats and other control messages normalize to nothing.
+pub trait Normalize {
+    fn normalize(&self) -> Result<Vec<MarketData>, ExStreamError>;
+}
+
+/// Symbol shared by every exchange: upper case base and quote without separator, e.g. `BTCUSDT`.
+/// Symbols the [`Instrument`] parser does not recognise are upper cased with the pair separator removed.
+pub fn canonical_symbol(exchange: Exchange, symbol: &str) -> String {
+    if let Some(instrument) = Instrument::parse(exchange, symbol, InstrumentKind::Spot) {
+        return format!("{}{}", instrument.base, instrument.quote);
+    }
+
+    let symbol = symbol.to_uppercase().replace(['-', '/'], "");
+    // Kraken still reports bitcoin as XBT in some places
+    match symbol.strip_prefix("XBT") {
+        Some(quote) => format!("BTC{quote}"),
+        None => symbol,
+    }
+}
+
+pub(crate) fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(not(feature = "decimal"))]
+pub(crate) fn parse_number(field: &str, value: &StrDecimal) -> Result<NumDecimal, ExStreamError> {
+    value
+        .parse()
+        .map_err(|_| ExStreamError::NormalizeError(format!("invalid {field} {value}")))
+}
+
+/// Already parsed when deserializing the message
+#[cfg(feature = "decimal")]
+pub(crate) fn parse_number(_field: &str, value: &StrDecimal) -> Result<NumDecimal, ExStreamError> {
+    Ok(*value)
+}
+
+pub(crate) fn parse_level(entry: &[StrDecimal]) -> Result<PriceLevel, ExStreamError> {
+    match entry {
+        [price, size, ..] => Ok(PriceLevel {
+            price: parse_number("price", price)?,
+            size: parse_number("size", size)?,
+        }),
+        _ => Err(ExStreamError::NormalizeError(format!(
+            "invalid book level {entry:?}"
+        ))),
+    }
+}
+
+/// Parse an RFC3339 timestamp, e.g. `2023-09-25T07:49:37.708706Z`, into milliseconds since the epoch
+pub(crate) fn parse_timestamp(text: &str) -> Result<u64, ExStreamError> {
+    parse_rfc3339(text)
+        .ok_or_else(|| ExStreamError::NormalizeError(format!("invalid timestamp {text}")))
+}
+
+fn parse_rfc3339(text: &str) -> Option<u64> {
+    let (date, time) = text.split_once(['T', 't', ' '])?;
+    let mut date = date.splitn(3, '-');
+    let year: i64 = date.next()?.parse().ok()?;
+    let month: i64 = date.next()?.parse().ok()?;
+    let day: i64 = date.next()?.parse().ok()?;
+
+    let (clock, offset) = match time.strip_suffix(['Z', 'z']) {
+        Some(clock) => (clock, 0),
+        None => {
+            let at = time.rfind(['+', '-'])?;
+            let (hours, minutes) = time[at + 1..].split_once(':')?;
+            let offset = hours.parse::<i64>().ok()? * 3600 + minutes.parse::<i64>().ok()? * 60;
+            let sign = if time[at..].starts_with('-') { -1 } else { 1 };
+            (&time[..at], sign * offset)
+        }
+    };
+    let (clock, fraction) = clock.split_once('.').unwrap_or((clock, ""));
+    let mut clock = clock.splitn(3, ':');
+    let hours: i64 = clock.next()?.parse().ok()?;
+    let minutes: i64 = clock.next()?.parse().ok()?;
+    let seconds: i64 = clock.next()?.parse().ok()?;
+    let millis = format!("{:0<3}", &fraction[..fraction.len().min(3)])
+        .parse::<i64>()
+        .ok()?;
+
+    let seconds =
+        days_from_civil(year, month, day) * 86_400 + hours * 3600 + minutes * 60 + seconds - offset;
+    u64::try_from(seconds * 1000 + millis).ok()
+}
+
+/// Days since the epoch of a proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Turn a stream of exchange messages into a stream of normalized market data
+pub(crate) fn normalize_stream<M: Normalize + Send + 'static>(
+    stream: WsMsgStream<M>,
+) -> WsMsgStream<MarketData> {
+    Box::pin(stream.flat_map(|message| {
+        let normalized = match message.and_then(|message| message.normalize()) {
+            Ok(data) => data.into_iter().map(Ok).collect(),
+            Err(e) => vec![Err(e)],
+        };
+        stream::iter(normalized)
+    }))
+}
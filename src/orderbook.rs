@@ -0,0 +1,140 @@
+//! Local order books maintained from the exchange streams
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use crate::models::NumDecimal;
+use crate::models::normalized::PriceLevel;
+
+mod binance;
+mod bybit;
+mod kraken;
+
+pub use binance::*;
+pub use bybit::*;
+pub use kraken::*;
+
+/// Price usable as a book key, ordered numerically
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Price(pub NumDecimal);
+
+impl Eq for Price {}
+
+impl PartialOrd for Price {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Price {
+    #[cfg(not(feature = "decimal"))]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+
+    #[cfg(feature = "decimal")]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookSide {
+    Bid,
+    Ask,
+}
+
+/// Price levels of a single symbol, sorted by price
+#[derive(Debug, Clone, Default)]
+pub struct L2Book {
+    bids: BTreeMap<Price, NumDecimal>,
+    asks: BTreeMap<Price, NumDecimal>,
+}
+
+impl L2Book {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the size of a level, a size of zero removes the level
+    pub fn apply(&mut self, side: BookSide, level: PriceLevel) {
+        let levels = match side {
+            BookSide::Bid => &mut self.bids,
+            BookSide::Ask => &mut self.asks,
+        };
+        if level.size == NumDecimal::default() {
+            levels.remove(&Price(level.price));
+        } else {
+            levels.insert(Price(level.price), level.size);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bids.is_empty() && self.asks.is_empty()
+    }
+
+    pub fn best_bid(&self) -> Option<PriceLevel> {
+        self.bids.iter().next_back().map(to_level)
+    }
+
+    pub fn best_ask(&self) -> Option<PriceLevel> {
+        self.asks.iter().next().map(to_level)
+    }
+
+    /// Best `depth` bids, highest price first
+    pub fn bids(&self, depth: usize) -> Vec<PriceLevel> {
+        self.bids.iter().rev().take(depth).map(to_level).collect()
+    }
+
+    /// Best `depth` asks, lowest price first
+    pub fn asks(&self, depth: usize) -> Vec<PriceLevel> {
+        self.asks.iter().take(depth).map(to_level).collect()
+    }
+
+    /// Keep only the best `depth` levels on each side
+    pub fn truncate(&mut self, depth: usize) {
+        while self.bids.len() > depth {
+            self.bids.pop_first();
+        }
+        while self.asks.len() > depth {
+            self.asks.pop_last();
+        }
+    }
+}
+
+fn to_level((price, size): (&Price, &NumDecimal)) -> PriceLevel {
+    PriceLevel {
+        price: price.0,
+        size: *size,
+    }
+}
+
+/// Change of a local book
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BookEvent {
+    /// The book was rebuilt from a snapshot
+    Snapshot { symbol: String },
+    /// An update was applied to the book
+    Updated { symbol: String },
+    /// An update was missed or arrived out of order. The book is stale and updates
+    /// are ignored until the next snapshot, which is sent again when resubscribing.
+    OutOfSync {
+        symbol: String,
+        expected: u64,
+        received: u64,
+    },
+    /// An update arrived while the book waits for a snapshot and was ignored
+    Unsynced { symbol: String },
+    /// The book does not match the exchange checksum. The book is stale and updates
+    /// are ignored until the next snapshot, which is sent again when resubscribing.
+    ChecksumMismatch {
+        symbol: String,
+        expected: u32,
+        computed: u32,
+    },
+}
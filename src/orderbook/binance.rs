@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+#[cfg(feature = "binance-rest")]
+use std::time::Duration;
+
+#[cfg(feature = "binance-rest")]
+use tokio::time::Instant;
+
+use crate::error::ExStreamError;
+use crate::models::normalized::{self, PriceLevel};
+use crate::models::{BinanceDepthUpdate, BinanceLevel, BinancePartialDepth};
+use crate::orderbook::{BookEvent, BookSide, L2Book};
+#[cfg(feature = "binance-rest")]
+use crate::transport::ReconnectPolicy;
+
+#[cfg(feature = "binance-rest")]
+mod rest;
+
+#[cfg(feature = "binance-rest")]
+pub use rest::*;
+
+/// Books shared between the event stream and the consumer
+pub type SharedBinanceBooks = Arc<RwLock<BinanceOrderBooks>>;
+
+#[derive(Debug, Clone)]
+enum SyncState {
+    /// Waiting for a snapshot, diffs are kept to be applied on top of it
+    Buffering {
+        updates: Vec<BinanceDepthUpdate>,
+        fetching: bool,
+        /// Consecutive snapshots that failed or were older than the diffs
+        #[cfg(feature = "binance-rest")]
+        failures: u32,
+    },
+    Synced,
+    /// The snapshot retries are exhausted, diffs are ignored until the cooldown is over
+    #[cfg(feature = "binance-rest")]
+    Failed {
+        until: Instant,
+    },
+}
+
+impl SyncState {
+    fn buffering(updates: Vec<BinanceDepthUpdate>) -> Self {
+        SyncState::Buffering {
+            updates,
+            fetching: false,
+            #[cfg(feature = "binance-rest")]
+            failures: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SymbolBook {
+    book: L2Book,
+    update_id: u64,
+    state: SyncState,
+}
+
+impl Default for SymbolBook {
+    fn default() -> Self {
+        Self {
+            book: L2Book::default(),
+            update_id: 0,
+            state: SyncState::buffering(Vec::new()),
+        }
+    }
+}
+
+/// Per symbol L2 books built from the Binance `depth@100ms` diff streams and REST snapshots.
+///
+/// Diffs are buffered until a snapshot is fetched, those already contained in the snapshot
+/// (`u <= lastUpdateId`) are dropped and the rest applied on top of it. Every diff must then
+/// start right after the previous one (`U == u + 1`, or `pu == u` for futures), a gap drops
+/// the book and fetches a new snapshot.
+#[derive(Debug, Clone)]
+pub struct BinanceOrderBooks {
+    books: HashMap<String, SymbolBook>,
+    #[cfg(feature = "binance-rest")]
+    limit: u64,
+    #[cfg(feature = "binance-rest")]
+    retry: ReconnectPolicy,
+    #[cfg(feature = "binance-rest")]
+    cooldown: Duration,
+}
+
+impl Default for BinanceOrderBooks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BinanceOrderBooks {
+    pub fn new() -> Self {
+        Self {
+            books: HashMap::new(),
+            #[cfg(feature = "binance-rest")]
+            limit: Self::DEFAULT_LIMIT,
+            #[cfg(feature = "binance-rest")]
+            retry: ReconnectPolicy::default()
+                .with_max_attempts(Some(Self::DEFAULT_SNAPSHOT_ATTEMPTS)),
+            #[cfg(feature = "binance-rest")]
+            cooldown: Self::DEFAULT_FAILURE_COOLDOWN,
+        }
+    }
+
+    /// Apply a diff, buffering it while the book waits for a snapshot.
+    /// Returns no event while buffering.
+    pub fn apply_update(
+        &mut self,
+        update: &BinanceDepthUpdate,
+    ) -> Result<Option<BookEvent>, ExStreamError> {
+        let symbol = update.symbol.clone();
+        let entry = self.books.entry(symbol.clone()).or_default();
+
+        match &mut entry.state {
+            SyncState::Buffering { updates, .. } => {
+                // Only a continuous run of diffs can be applied to the snapshot
+                if updates
+                    .last()
+                    .is_some_and(|last| !continues(update, last.final_update_id))
+                {
+                    updates.clear();
+                }
+                updates.push(update.clone());
+                Ok(None)
+            }
+            #[cfg(feature = "binance-rest")]
+            SyncState::Failed { until } if Instant::now() < *until => Ok(None),
+            // The cooldown is over, buffer again so a new snapshot is fetched
+            #[cfg(feature = "binance-rest")]
+            SyncState::Failed { .. } => {
+                tracing::info!("Fetching the given up Binance {} book again", symbol);
+                entry.state = SyncState::buffering(vec![update.clone()]);
+                Ok(None)
+            }
+            SyncState::Synced if update.final_update_id <= entry.update_id => Ok(None),
+            SyncState::Synced if !continues(update, entry.update_id) => {
+                let expected = entry.update_id + 1;
+                let received = match update.previous_final_update_id {
+                    Some(previous) => previous + 1,
+                    None => update.first_update_id,
+                };
+                tracing::warn!(
+                    "Binance book {} out of sync, expected update {} but received {}",
+                    symbol,
+                    expected,
+                    received
+                );
+                entry.book.clear();
+                entry.state = SyncState::buffering(vec![update.clone()]);
+                Ok(Some(BookEvent::OutOfSync {
+                    symbol,
+                    expected,
+                    received,
+                }))
+            }
+            SyncState::Synced => {
+                apply_levels(&mut entry.book, update)?;
+                entry.update_id = update.final_update_id;
+                Ok(Some(BookEvent::Updated { symbol }))
+            }
+        }
+    }
+
+    /// Rebuild the book of the symbol from a snapshot and the buffered diffs.
+    /// Returns no event when the snapshot is older than the buffered diffs, a newer one is needed.
+    pub fn apply_snapshot(
+        &mut self,
+        symbol: &str,
+        snapshot: &BinancePartialDepth,
+    ) -> Result<Option<BookEvent>, ExStreamError> {
+        let entry = self.books.entry(symbol.to_string()).or_default();
+        let SyncState::Buffering {
+            updates, fetching, ..
+        } = &mut entry.state
+        else {
+            return Ok(None);
+        };
+        *fetching = false;
+
+        let last_update_id = snapshot.last_update_id;
+        if updates
+            .first()
+            .is_none_or(|first| !continues(first, last_update_id))
+        {
+            return Ok(None);
+        }
+        // The buffered diffs are continuous, so the first one left continues the snapshot
+        updates.retain(|update| update.final_update_id > last_update_id);
+
+        let updates = std::mem::take(updates);
+        entry.book.clear();
+        for (side, levels) in [
+            (BookSide::Bid, &snapshot.bids),
+            (BookSide::Ask, &snapshot.asks),
+        ] {
+            apply_side(&mut entry.book, side, levels)?;
+        }
+        entry.update_id = last_update_id;
+        for update in &updates {
+            apply_levels(&mut entry.book, update)?;
+            entry.update_id = update.final_update_id;
+        }
+        entry.state = SyncState::Synced;
+
+        Ok(Some(BookEvent::Snapshot {
+            symbol: symbol.to_string(),
+        }))
+    }
+
+    pub fn book(&self, symbol: &str) -> Option<&L2Book> {
+        self.books.get(symbol).map(|entry| &entry.book)
+    }
+
+    /// Last update id applied to the book of the symbol
+    pub fn update_id(&self, symbol: &str) -> Option<u64> {
+        self.books.get(symbol).map(|entry| entry.update_id)
+    }
+
+    /// Check if the book of the symbol is up to date
+    pub fn is_synced(&self, symbol: &str) -> bool {
+        self.books
+            .get(symbol)
+            .is_some_and(|entry| matches!(entry.state, SyncState::Synced))
+    }
+
+    pub fn best_bid(&self, symbol: &str) -> Option<PriceLevel> {
+        self.book(symbol)?.best_bid()
+    }
+
+    pub fn best_ask(&self, symbol: &str) -> Option<PriceLevel> {
+        self.book(symbol)?.best_ask()
+    }
+
+    /// Best `depth` bids and asks of the symbol
+    pub fn top(&self, symbol: &str, depth: usize) -> Option<(Vec<PriceLevel>, Vec<PriceLevel>)> {
+        let book = self.book(symbol)?;
+        Some((book.bids(depth), book.asks(depth)))
+    }
+}
+
+/// Check if the diff follows the update id without a gap. Spot update ids are consecutive, futures
+/// ids are not and each diff carries the final id of the previous one instead. The first diff
+/// after a snapshot may straddle its last update id, levels carry absolute quantities so
+/// applying it again is harmless.
+fn continues(update: &BinanceDepthUpdate, update_id: u64) -> bool {
+    match update.previous_final_update_id {
+        Some(previous) => previous <= update_id,
+        None => update.first_update_id <= update_id + 1,
+    }
+}
+
+fn apply_levels(book: &mut L2Book, update: &BinanceDepthUpdate) -> Result<(), ExStreamError> {
+    apply_side(book, BookSide::Bid, &update.bids)?;
+    apply_side(book, BookSide::Ask, &update.asks)
+}
+
+fn apply_side(
+    book: &mut L2Book,
+    side: BookSide,
+    levels: &[BinanceLevel],
+) -> Result<(), ExStreamError> {
+    for level in levels {
+        book.apply(side, normalized::parse_level(level)?);
+    }
+    Ok(())
+}
@@ -0,0 +1,292 @@
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_util::StreamExt as _;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+
+use super::{BinanceOrderBooks, SharedBinanceBooks, SyncState};
+use crate::error::ExStreamError;
+use crate::models::{BinanceMarket, BinanceMessage, BinancePartialDepth};
+use crate::orderbook::BookEvent;
+use crate::transport::{ReconnectPolicy, WsMsgStream};
+
+/// Source of the depth snapshots the diff streams are applied on
+#[async_trait]
+pub trait BinanceSnapshotFetcher: Send + Sync + 'static {
+    /// Fetch the book of the symbol, e.g. `BTCUSDT`, with `limit` levels per side
+    async fn fetch(&self, symbol: &str, limit: u64) -> Result<BinancePartialDepth, ExStreamError>;
+}
+
+/// Fetch snapshots from the Binance REST depth endpoint of the market,
+/// `/api/v3/depth`, `/fapi/v1/depth` or `/dapi/v1/depth`
+#[derive(Debug, Clone)]
+pub struct BinanceRestSnapshot {
+    endpoint: String,
+    market: BinanceMarket,
+    client: reqwest::Client,
+}
+
+impl BinanceRestSnapshot {
+    pub const ENDPOINT: &str = "https://api.binance.com";
+    pub const TESTNET_ENDPOINT: &str = "https://testnet.binance.vision";
+    pub const USD_M_ENDPOINT: &str = "https://fapi.binance.com";
+    pub const COIN_M_ENDPOINT: &str = "https://dapi.binance.com";
+    /// Shared by the USDⓈ-M and COIN-M testnets
+    pub const FUTURES_TESTNET_ENDPOINT: &str = "https://testnet.binancefuture.com";
+
+    /// Fetch spot snapshots from a custom base URL, e.g. a local mock server
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        Self::for_market(BinanceMarket::Spot).with_endpoint(endpoint)
+    }
+
+    pub fn testnet() -> Self {
+        Self::new(Self::TESTNET_ENDPOINT)
+    }
+
+    /// Fetch snapshots of the market from its live endpoint
+    pub fn for_market(market: BinanceMarket) -> Self {
+        let endpoint = match market {
+            BinanceMarket::Spot => Self::ENDPOINT,
+            BinanceMarket::UsdM => Self::USD_M_ENDPOINT,
+            BinanceMarket::CoinM => Self::COIN_M_ENDPOINT,
+        };
+        Self {
+            endpoint: endpoint.to_string(),
+            market,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Fetch from a custom base URL, keeping the path of the market
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into().trim_end_matches('/').to_string();
+        self
+    }
+}
+
+impl Default for BinanceRestSnapshot {
+    fn default() -> Self {
+        Self::new(Self::ENDPOINT)
+    }
+}
+
+#[async_trait]
+impl BinanceSnapshotFetcher for BinanceRestSnapshot {
+    async fn fetch(&self, symbol: &str, limit: u64) -> Result<BinancePartialDepth, ExStreamError> {
+        let path = match self.market {
+            BinanceMarket::Spot => "/api/v3/depth",
+            BinanceMarket::UsdM => "/fapi/v1/depth",
+            BinanceMarket::CoinM => "/dapi/v1/depth",
+        };
+        let url = format!("{}{}", self.endpoint, path);
+        let limit = limit.to_string();
+        let response = self
+            .client
+            .get(url)
+            .query(&[("symbol", symbol), ("limit", limit.as_str())])
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| ExStreamError::RestError(e.to_string()))?;
+        let text = response
+            .text()
+            .await
+            .map_err(|e| ExStreamError::RestError(e.to_string()))?;
+
+        let mut snapshot: BinancePartialDepth =
+            serde_json::from_str(&text).map_err(|error| ExStreamError::ParseError {
+                error,
+                raw_content: text,
+            })?;
+        snapshot.symbol = Some(symbol.to_string());
+        Ok(snapshot)
+    }
+}
+
+impl BinanceOrderBooks {
+    /// Snapshot levels fetched per side when not set
+    pub const DEFAULT_LIMIT: u64 = 1000;
+
+    /// Snapshot attempts per book when no retry policy is set
+    pub const DEFAULT_SNAPSHOT_ATTEMPTS: u32 = 10;
+
+    /// Time a given up book ignores its diffs when not set
+    pub const DEFAULT_FAILURE_COOLDOWN: Duration = Duration::from_secs(60);
+
+    /// Levels per side of the snapshots, up to 5000
+    pub fn with_limit(mut self, limit: u64) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Backoff between the snapshots of a book when one fails or is older than the diffs,
+    /// and the number of retries before the book is given up
+    pub fn with_snapshot_retry(mut self, policy: ReconnectPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Time a book ignores its diffs once given up. The first diff after it fetches
+    /// a new snapshot, with a fresh retry policy.
+    pub fn with_failure_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Symbols waiting for a snapshot which is not being fetched yet, marked as fetching,
+    /// with the backoff to wait before fetching
+    fn take_snapshot_requests(&mut self) -> Vec<(String, Duration)> {
+        let retry = &self.retry;
+        self.books
+            .iter_mut()
+            .filter_map(|(symbol, entry)| match &mut entry.state {
+                SyncState::Buffering {
+                    updates,
+                    fetching,
+                    failures,
+                } if !*fetching && !updates.is_empty() => {
+                    *fetching = true;
+                    let delay = match *failures {
+                        0 => Duration::ZERO,
+                        failures => retry.backoff(failures),
+                    };
+                    Some((symbol.clone(), delay))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Count a snapshot that failed or was older than the diffs. The snapshot is fetched again
+    /// after the retry backoff, or the book is given up with an error for the cooldown once
+    /// the policy stops allowing attempts.
+    fn snapshot_failed(&mut self, symbol: &str) -> Option<ExStreamError> {
+        let entry = self.books.get_mut(symbol)?;
+        let SyncState::Buffering {
+            fetching, failures, ..
+        } = &mut entry.state
+        else {
+            return None;
+        };
+        *fetching = false;
+        *failures += 1;
+        if self.retry.allows(*failures) {
+            return None;
+        }
+
+        let attempts = *failures;
+        tracing::error!(
+            "Giving up the Binance {} book after {} snapshot attempts",
+            symbol,
+            attempts
+        );
+        entry.state = SyncState::Failed {
+            until: Instant::now() + self.cooldown,
+        };
+        Some(ExStreamError::SnapshotFailed {
+            symbol: symbol.to_string(),
+            attempts,
+        })
+    }
+
+    /// Maintain the books from a Binance stream subscribed to `BinanceStream::DiffDepth`,
+    /// returning the books and a stream of the changes. Snapshots are fetched in the background
+    /// whenever a book needs one, messages other than diffs are skipped. A book whose snapshots
+    /// keep failing is given up with [`ExStreamError::SnapshotFailed`] until the cooldown is over,
+    /// see [`BinanceOrderBooks::with_snapshot_retry`] and [`BinanceOrderBooks::with_failure_cooldown`].
+    pub fn track(
+        self,
+        stream: WsMsgStream<BinanceMessage>,
+        fetcher: impl BinanceSnapshotFetcher,
+    ) -> (SharedBinanceBooks, WsMsgStream<BookEvent>) {
+        let books = Arc::new(RwLock::new(self));
+        let (events_tx, events_rx) = mpsc::unbounded_channel();
+        tokio::spawn(sync_books(
+            stream,
+            books.clone(),
+            Arc::new(fetcher),
+            events_tx,
+        ));
+        (books, Box::pin(UnboundedReceiverStream::new(events_rx)))
+    }
+}
+
+type SnapshotResult = (String, Result<BinancePartialDepth, ExStreamError>);
+
+/// Apply the diffs and the snapshots fetched along, until the stream or the consumer is gone
+async fn sync_books(
+    mut stream: WsMsgStream<BinanceMessage>,
+    books: SharedBinanceBooks,
+    fetcher: Arc<dyn BinanceSnapshotFetcher>,
+    events_tx: mpsc::UnboundedSender<Result<BookEvent, ExStreamError>>,
+) {
+    let mut fetches = JoinSet::<SnapshotResult>::new();
+    loop {
+        let events = tokio::select! {
+            message = stream.next() => match message {
+                Some(Ok(message)) => match message.data() {
+                    BinanceMessage::DepthUpdate(update) => books
+                        .write()
+                        .expect("order books lock poisoned")
+                        .apply_update(update)
+                        .transpose()
+                        .into_iter()
+                        .collect(),
+                    _ => Vec::new(),
+                },
+                Some(Err(e)) => vec![Err(e)],
+                None => break,
+            },
+            Some(fetched) = fetches.join_next() => {
+                let (symbol, snapshot) = match fetched {
+                    Ok(fetched) => fetched,
+                    Err(e) => {
+                        if events_tx.send(Err(e.into())).is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+                };
+                let mut books = books.write().expect("order books lock poisoned");
+                match snapshot {
+                    Ok(snapshot) => match books.apply_snapshot(&symbol, &snapshot) {
+                        Ok(Some(event)) => vec![Ok(event)],
+                        Ok(None) => {
+                            tracing::debug!("Binance {} snapshot is older than the diffs", symbol);
+                            books.snapshot_failed(&symbol).map(Err).into_iter().collect()
+                        }
+                        Err(e) => vec![Err(e)],
+                    },
+                    Err(e) => {
+                        tracing::warn!("Failed to fetch Binance {} snapshot: {}", symbol, e);
+                        let given_up = books.snapshot_failed(&symbol);
+                        std::iter::once(e).chain(given_up).map(Err).collect()
+                    }
+                }
+            }
+        };
+
+        let (requests, limit) = {
+            let mut books = books.write().expect("order books lock poisoned");
+            (books.take_snapshot_requests(), books.limit)
+        };
+        for (symbol, delay) in requests {
+            let fetcher = fetcher.clone();
+            fetches.spawn(async move {
+                tokio::time::sleep(delay).await;
+                let snapshot = fetcher.fetch(&symbol, limit).await;
+                (symbol, snapshot)
+            });
+        }
+
+        for event in events {
+            if events_tx.send(event).is_err() {
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use futures_util::{StreamExt as _, future};
+
+use crate::error::ExStreamError;
+use crate::models::normalized::{self, PriceLevel};
+use crate::models::{Bybit, BybitDataType, BybitMessage, BybitOrderBook};
+use crate::orderbook::{BookEvent, BookSide, L2Book};
+use crate::transport::{ConnectionHandle, WsMsgStream};
+
+/// Books shared between the event stream and the consumer
+pub type SharedBybitBooks = Arc<RwLock<BybitOrderBooks>>;
+
+#[derive(Debug, Clone, Default)]
+struct SymbolBook {
+    book: L2Book,
+    update_id: u64,
+    synced: bool,
+}
+
+/// Per symbol L2 books built from the Bybit `orderbook` snapshots and deltas.
+/// Every depth subscribed for a symbol is a separate book with its own update ids,
+/// the accessors taking only the symbol use the deepest book.
+#[derive(Debug, Clone, Default)]
+pub struct BybitOrderBooks {
+    books: HashMap<(String, u64), SymbolBook>,
+}
+
+impl BybitOrderBooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply an order book message. Update ids must follow each other,
+    /// an update id of 1 is a snapshot sent after a service restart.
+    pub fn apply(&mut self, message: &BybitOrderBook) -> Result<BookEvent, ExStreamError> {
+        let data = &message.data;
+        let symbol = data.symbol.clone();
+        let depth = topic_depth(&message.topic)?;
+        let entry = self.books.entry((symbol.clone(), depth)).or_default();
+
+        let is_snapshot =
+            matches!(message.data_type, BybitDataType::Snapshot) || data.update_id == 1;
+        if is_snapshot {
+            entry.book.clear();
+        } else if !entry.synced {
+            // Waiting for a snapshot, the update cannot be applied
+            return Ok(BookEvent::Unsynced { symbol });
+        } else if data.update_id != entry.update_id + 1 {
+            tracing::warn!(
+                "Bybit book {} at depth {} out of sync, expected update {} but received {}",
+                symbol,
+                depth,
+                entry.update_id + 1,
+                data.update_id
+            );
+            entry.synced = false;
+            return Ok(BookEvent::OutOfSync {
+                symbol,
+                expected: entry.update_id + 1,
+                received: data.update_id,
+            });
+        }
+
+        for (side, levels) in [(BookSide::Bid, &data.bids), (BookSide::Ask, &data.asks)] {
+            for level in levels {
+                let level = normalized::parse_level(level)?;
+                entry.book.apply(side, level);
+            }
+        }
+        entry.update_id = data.update_id;
+        entry.synced = true;
+
+        Ok(match is_snapshot {
+            true => BookEvent::Snapshot { symbol },
+            false => BookEvent::Updated { symbol },
+        })
+    }
+
+    pub fn book(&self, symbol: &str) -> Option<&L2Book> {
+        self.deepest(symbol).map(|entry| &entry.book)
+    }
+
+    /// Book of the symbol subscribed at the given depth
+    pub fn book_at_depth(&self, symbol: &str, depth: u64) -> Option<&L2Book> {
+        self.books
+            .get(&(symbol.to_string(), depth))
+            .map(|entry| &entry.book)
+    }
+
+    /// Last update id applied to the book of the symbol
+    pub fn update_id(&self, symbol: &str) -> Option<u64> {
+        self.deepest(symbol).map(|entry| entry.update_id)
+    }
+
+    /// Check if the book of the symbol is up to date
+    pub fn is_synced(&self, symbol: &str) -> bool {
+        self.deepest(symbol).is_some_and(|entry| entry.synced)
+    }
+
+    fn deepest(&self, symbol: &str) -> Option<&SymbolBook> {
+        self.books
+            .iter()
+            .filter(|((book_symbol, _), _)| book_symbol == symbol)
+            .max_by_key(|((_, depth), _)| *depth)
+            .map(|(_, entry)| entry)
+    }
+
+    pub fn best_bid(&self, symbol: &str) -> Option<PriceLevel> {
+        self.book(symbol)?.best_bid()
+    }
+
+    pub fn best_ask(&self, symbol: &str) -> Option<PriceLevel> {
+        self.book(symbol)?.best_ask()
+    }
+
+    /// Best `depth` bids and asks of the symbol
+    pub fn top(&self, symbol: &str, depth: usize) -> Option<(Vec<PriceLevel>, Vec<PriceLevel>)> {
+        let book = self.book(symbol)?;
+        Some((book.bids(depth), book.asks(depth)))
+    }
+
+    /// Maintain the books from a Bybit stream, returning the books and a stream of the
+    /// changes. Messages other than order books are skipped. The order book of a symbol
+    /// and depth is resubscribed through the handle when an update is missed, to get a
+    /// new snapshot.
+    pub fn track(
+        stream: WsMsgStream<BybitMessage>,
+        handle: ConnectionHandle<Bybit>,
+    ) -> (SharedBybitBooks, WsMsgStream<BookEvent>) {
+        let books = SharedBybitBooks::default();
+        let shared = books.clone();
+        let events = stream.filter_map(move |message| {
+            let event = match message {
+                Ok(BybitMessage::OrderBook(book)) => {
+                    let event = shared
+                        .write()
+                        .expect("order books lock poisoned")
+                        .apply(&book);
+                    // Updates are `Unsynced` until the snapshot arrives, so a gap resubscribes once
+                    if let Ok(BookEvent::OutOfSync { symbol, .. }) = &event
+                        && let Ok(depth) = topic_depth(&book.topic)
+                    {
+                        resync(&handle, symbol, depth);
+                    }
+                    Some(event)
+                }
+                Ok(_) => None,
+                Err(e) => Some(Err(e)),
+            };
+            future::ready(event)
+        });
+        (books, Box::pin(events))
+    }
+}
+
+/// Depth of an order book topic, e.g. 50 for `orderbook.50.BTCUSDT`
+fn topic_depth(topic: &str) -> Result<u64, ExStreamError> {
+    topic
+        .split('.')
+        .nth(1)
+        .and_then(|depth| depth.parse().ok())
+        .ok_or_else(|| ExStreamError::UnsupportedMessage(format!("order book topic {topic}")))
+}
+
+/// Resubscribe to the order book of the symbol at the depth to get a new snapshot,
+/// the other depths are kept
+fn resync(handle: &ConnectionHandle<Bybit>, symbol: &str, depth: u64) {
+    let topics = handle
+        .subscriptions()
+        .into_iter()
+        .map(|entry| entry.subscription)
+        .filter(|topic| {
+            topic.channel == "orderbook" && topic.symbol == symbol && topic.depth == Some(depth)
+        })
+        .collect();
+    if let Err(e) = handle.resubscribe(topics) {
+        tracing::warn!(
+            "Failed to resubscribe Bybit book {} at depth {}: {}",
+            symbol,
+            depth,
+            e
+        );
+    }
+}
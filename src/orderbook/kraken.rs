@@ -0,0 +1,715 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+use futures_util::{Stream, StreamExt as _};
+
+use crate::error::ExStreamError;
+use crate::models::normalized::PriceLevel;
+use crate::models::{
+    Kraken, KrakenBook, KrakenBookUpdate, KrakenChannel, KrakenData, KrakenEventKind,
+    KrakenInstruments, KrakenL2Book, KrakenMessage, KrakenOrderEntry, KrakenOrderEvent,
+    KrakenOrderUpdate, KrakenPair, NumDecimal,
+};
+use crate::orderbook::{BookEvent, BookSide, L2Book, Price};
+use crate::transport::{ConnectionHandle, WsMsgStream};
+
+/// Number of price levels covered by the Kraken checksum
+const CHECKSUM_LEVELS: usize = 10;
+
+/// Books shared between the event stream and the consumer
+pub type SharedKrakenBooks = Arc<RwLock<KrakenOrderBooks>>;
+pub type SharedKrakenL3Books = Arc<RwLock<KrakenL3Books>>;
+
+/// Resting order of an L3 book
+#[derive(Debug, Clone, PartialEq)]
+pub struct L3Order {
+    pub order_id: String,
+    pub price: NumDecimal,
+    pub size: NumDecimal,
+    pub timestamp: String,
+}
+
+impl From<&KrakenOrderEntry> for L3Order {
+    fn from(entry: &KrakenOrderEntry) -> Self {
+        Self {
+            order_id: entry.order_id.clone(),
+            price: entry.price,
+            size: entry.size,
+            timestamp: entry.timestamp.clone(),
+        }
+    }
+}
+impl From<&KrakenOrderUpdate> for L3Order {
+    fn from(order: &KrakenOrderUpdate) -> Self {
+        Self {
+            order_id: order.order_id.clone(),
+            price: order.price,
+            size: order.size,
+            timestamp: order.timestamp.clone(),
+        }
+    }
+}
+
+/// Order level book of a single symbol, orders of a level are kept in priority order
+#[derive(Debug, Clone, Default)]
+pub struct KrakenL3Book {
+    bids: BTreeMap<Price, Vec<L3Order>>,
+    asks: BTreeMap<Price, Vec<L3Order>>,
+    /// Side and price of every order, to find the order of a modify or delete event
+    orders: HashMap<String, (BookSide, Price)>,
+}
+
+impl KrakenL3Book {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+        self.orders.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.orders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orders.is_empty()
+    }
+
+    pub fn order(&self, order_id: &str) -> Option<&L3Order> {
+        let (side, price) = self.orders.get(order_id)?;
+        self.levels(*side)
+            .get(price)?
+            .iter()
+            .find(|order| order.order_id == order_id)
+    }
+
+    /// Add an order at the back of its price level
+    pub fn add(&mut self, side: BookSide, order: L3Order) {
+        // A repeated add replaces the order
+        self.delete(&order.order_id);
+
+        let price = Price(order.price);
+        self.orders.insert(order.order_id.clone(), (side, price));
+        self.levels_mut(side).entry(price).or_default().push(order);
+    }
+
+    /// Change the size of an order, keeping its priority unless the price changes
+    pub fn modify(&mut self, side: BookSide, order: L3Order) {
+        match self.orders.get(&order.order_id) {
+            Some((_, price)) if *price == Price(order.price) => {
+                let price = *price;
+                if let Some(resting) = self
+                    .levels_mut(side)
+                    .get_mut(&price)
+                    .and_then(|level| level.iter_mut().find(|o| o.order_id == order.order_id))
+                {
+                    *resting = order;
+                }
+            }
+            _ => self.add(side, order),
+        }
+    }
+
+    pub fn delete(&mut self, order_id: &str) -> Option<L3Order> {
+        let (side, price) = self.orders.remove(order_id)?;
+        let levels = self.levels_mut(side);
+        let level = levels.get_mut(&price)?;
+        let index = level.iter().position(|order| order.order_id == order_id)?;
+        let order = level.remove(index);
+        if level.is_empty() {
+            levels.remove(&price);
+        }
+        Some(order)
+    }
+
+    /// Orders of the best `depth` bid levels, highest price first
+    pub fn bids(&self, depth: usize) -> Vec<&L3Order> {
+        self.bids.values().rev().take(depth).flatten().collect()
+    }
+
+    /// Orders of the best `depth` ask levels, lowest price first
+    pub fn asks(&self, depth: usize) -> Vec<&L3Order> {
+        self.asks.values().take(depth).flatten().collect()
+    }
+
+    /// Aggregate the orders into price levels
+    pub fn to_l2(&self) -> L2Book {
+        let mut book = L2Book::new();
+        for (side, levels) in [(BookSide::Bid, &self.bids), (BookSide::Ask, &self.asks)] {
+            for (price, orders) in levels {
+                let size = orders.iter().map(|order| order.size).sum();
+                book.apply(
+                    side,
+                    PriceLevel {
+                        price: price.0,
+                        size,
+                    },
+                );
+            }
+        }
+        book
+    }
+
+    /// Kraken CRC32 over the orders of the top 10 levels, asks first
+    pub fn checksum(&self, price_precision: u32, qty_precision: u32) -> u32 {
+        let orders = self
+            .asks
+            .values()
+            .take(CHECKSUM_LEVELS)
+            .chain(self.bids.values().rev().take(CHECKSUM_LEVELS))
+            .flatten()
+            .map(|order| (order.price, order.size));
+        checksum(orders, price_precision, qty_precision)
+    }
+
+    fn levels(&self, side: BookSide) -> &BTreeMap<Price, Vec<L3Order>> {
+        match side {
+            BookSide::Bid => &self.bids,
+            BookSide::Ask => &self.asks,
+        }
+    }
+
+    fn levels_mut(&mut self, side: BookSide) -> &mut BTreeMap<Price, Vec<L3Order>> {
+        match side {
+            BookSide::Bid => &mut self.bids,
+            BookSide::Ask => &mut self.asks,
+        }
+    }
+}
+
+/// Kraken checksum of price and quantity pairs, already in checksum order. Each value is
+/// formatted with the instrument precision, without decimal point and leading zeros.
+pub fn checksum(
+    entries: impl IntoIterator<Item = (NumDecimal, NumDecimal)>,
+    price_precision: u32,
+    qty_precision: u32,
+) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    for (price, qty) in entries {
+        hasher.update(checksum_digits(price, price_precision).as_bytes());
+        hasher.update(checksum_digits(qty, qty_precision).as_bytes());
+    }
+    hasher.finalize()
+}
+
+fn checksum_digits(value: NumDecimal, precision: u32) -> String {
+    let text = format!("{:.*}", precision as usize, value).replace('.', "");
+    text.trim_start_matches('0').to_string()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Precision {
+    price: u32,
+    qty: u32,
+}
+
+/// Precisions of the symbols, required to verify the checksums unless verification is disabled
+#[derive(Debug, Clone)]
+struct Precisions {
+    verify: bool,
+    symbols: HashMap<String, Precision>,
+}
+
+impl Default for Precisions {
+    fn default() -> Self {
+        Self {
+            verify: true,
+            symbols: HashMap::new(),
+        }
+    }
+}
+
+impl Precisions {
+    fn insert(&mut self, symbol: String, price: u32, qty: u32) {
+        self.symbols.insert(symbol, Precision { price, qty });
+    }
+
+    fn insert_pairs(&mut self, instruments: &KrakenInstruments) {
+        for pair in &instruments.pairs {
+            self.insert(
+                pair.symbol.clone(),
+                pair.price_precision,
+                pair.qty_precision,
+            );
+        }
+    }
+
+    /// Precision to verify the book of the symbol with, `None` when verification is disabled
+    fn get(&self, symbol: &str) -> Result<Option<Precision>, ExStreamError> {
+        match (self.verify, self.symbols.get(symbol)) {
+            (false, _) => Ok(None),
+            (true, Some(precision)) => Ok(Some(*precision)),
+            (true, None) => Err(ExStreamError::MissingPrecision(symbol.to_string())),
+        }
+    }
+
+    fn contains(&self, symbol: &str) -> bool {
+        self.symbols.contains_key(symbol)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct SymbolL2Book {
+    book: L2Book,
+    synced: bool,
+}
+
+/// Per symbol L2 books built from the Kraken `book` channel
+#[derive(Debug, Clone)]
+pub struct KrakenOrderBooks {
+    depth: usize,
+    books: HashMap<String, SymbolL2Book>,
+    precisions: Precisions,
+}
+
+impl KrakenOrderBooks {
+    /// Books are kept to the subscribed depth, Kraken does not remove the levels pushed out of it
+    pub fn new(depth: u64) -> Self {
+        Self {
+            depth: depth as usize,
+            books: HashMap::new(),
+            precisions: Precisions::default(),
+        }
+    }
+
+    /// Price and quantity decimals of the symbol, as listed by the Kraken `instrument` channel.
+    /// Snapshots of symbols without a known precision are rejected with
+    /// [`ExStreamError::MissingPrecision`] unless checksums are disabled.
+    pub fn with_precision(
+        mut self,
+        symbol: impl Into<String>,
+        price_precision: u32,
+        qty_precision: u32,
+    ) -> Self {
+        self.precisions
+            .insert(symbol.into(), price_precision, qty_precision);
+        self
+    }
+
+    /// Take the precision of a pair received on the Kraken `instrument` channel
+    pub fn with_pair(self, pair: &KrakenPair) -> Self {
+        self.with_precision(
+            pair.symbol.clone(),
+            pair.price_precision,
+            pair.qty_precision,
+        )
+    }
+
+    /// Take the precisions of every pair of an `instrument` snapshot
+    pub fn with_instruments(mut self, instruments: &KrakenInstruments) -> Self {
+        self.precisions.insert_pairs(instruments);
+        self
+    }
+
+    /// Maintain the books without verifying the exchange checksums
+    pub fn without_checksum(mut self) -> Self {
+        self.precisions.verify = false;
+        self
+    }
+
+    /// Apply a `book` snapshot or update and verify its checksum
+    pub fn apply(
+        &mut self,
+        kind: KrakenEventKind,
+        message: &KrakenL2Book,
+    ) -> Result<BookEvent, ExStreamError> {
+        let symbol = message.symbol.clone();
+        let entry = self.books.entry(symbol.clone()).or_default();
+        if kind == KrakenEventKind::Update && !entry.synced {
+            // Waiting for a snapshot, the update cannot be applied
+            return Ok(BookEvent::Unsynced { symbol });
+        }
+
+        let precision = match self.precisions.get(&symbol) {
+            Ok(precision) => precision,
+            Err(e) => {
+                entry.synced = false;
+                return Err(e);
+            }
+        };
+        if kind == KrakenEventKind::Snapshot {
+            entry.book.clear();
+            entry.synced = true;
+        }
+
+        for (side, levels) in [
+            (BookSide::Bid, &message.bids),
+            (BookSide::Ask, &message.asks),
+        ] {
+            for level in levels {
+                entry.book.apply(
+                    side,
+                    PriceLevel {
+                        price: level.price,
+                        size: level.size,
+                    },
+                );
+            }
+        }
+        entry.book.truncate(self.depth);
+
+        if let Some(precision) = precision {
+            let computed = l2_checksum(&entry.book, precision);
+            let expected = message.checksum as u32;
+            if computed != expected {
+                tracing::warn!(
+                    "Kraken book {} checksum mismatch, expected {} but computed {}",
+                    symbol,
+                    expected,
+                    computed
+                );
+                entry.synced = false;
+                return Ok(BookEvent::ChecksumMismatch {
+                    symbol,
+                    expected,
+                    computed,
+                });
+            }
+        }
+
+        Ok(match kind {
+            KrakenEventKind::Snapshot => BookEvent::Snapshot { symbol },
+            KrakenEventKind::Update => BookEvent::Updated { symbol },
+        })
+    }
+
+    pub fn book(&self, symbol: &str) -> Option<&L2Book> {
+        self.books.get(symbol).map(|entry| &entry.book)
+    }
+
+    /// Check if the book of the symbol is up to date
+    pub fn is_synced(&self, symbol: &str) -> bool {
+        self.books.get(symbol).is_some_and(|entry| entry.synced)
+    }
+
+    pub fn best_bid(&self, symbol: &str) -> Option<PriceLevel> {
+        self.book(symbol)?.best_bid()
+    }
+
+    pub fn best_ask(&self, symbol: &str) -> Option<PriceLevel> {
+        self.book(symbol)?.best_ask()
+    }
+
+    /// Maintain the books from a Kraken stream, returning the books and a stream of the changes.
+    /// On a checksum mismatch the symbol is resubscribed through the handle to get a new snapshot.
+    /// Precisions are also taken from the `instrument` channel when it is subscribed on the same
+    /// connection, symbols whose snapshot came first are resubscribed once their precision is known.
+    pub fn track(
+        self,
+        stream: WsMsgStream<KrakenMessage>,
+        handle: ConnectionHandle<Kraken>,
+    ) -> (SharedKrakenBooks, WsMsgStream<BookEvent>) {
+        let books = Arc::new(RwLock::new(self));
+        let shared = books.clone();
+        let mut awaiting = AwaitingPrecision::new(handle.clone(), KrakenChannel::Book);
+        let events = stream.flat_map(move |message| {
+            let events = match message {
+                Ok(KrakenMessage::Event(event)) => {
+                    let mut books = shared.write().expect("order books lock poisoned");
+                    event
+                        .data
+                        .iter()
+                        .filter_map(|data| match data {
+                            KrakenData::L2Book(book) => {
+                                Some(awaiting.record(books.apply(event.kind, book)))
+                            }
+                            KrakenData::Instrument(instruments) => {
+                                books.precisions.insert_pairs(instruments);
+                                awaiting.release(&books.precisions);
+                                None
+                            }
+                            _ => None,
+                        })
+                        .collect()
+                }
+                Ok(_) => Vec::new(),
+                Err(e) => vec![Err(e)],
+            };
+            futures_util::stream::iter(events)
+        });
+        (
+            books,
+            resync_on_mismatch(events, handle, KrakenChannel::Book),
+        )
+    }
+}
+
+/// Kraken checksum of the top 10 levels of an L2 book
+fn l2_checksum(book: &L2Book, precision: Precision) -> u32 {
+    let levels = book
+        .asks(CHECKSUM_LEVELS)
+        .into_iter()
+        .chain(book.bids(CHECKSUM_LEVELS))
+        .map(|level| (level.price, level.size));
+    checksum(levels, precision.price, precision.qty)
+}
+
+#[derive(Debug, Clone, Default)]
+struct SymbolBook {
+    book: KrakenL3Book,
+    synced: bool,
+}
+
+/// Per symbol L3 books built from the Kraken `level3` channel
+#[derive(Debug, Clone, Default)]
+pub struct KrakenL3Books {
+    books: HashMap<String, SymbolBook>,
+    precisions: Precisions,
+}
+
+impl KrakenL3Books {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Price and quantity decimals of the symbol, as listed by the Kraken `instrument` channel.
+    /// Snapshots of symbols without a known precision are rejected with
+    /// [`ExStreamError::MissingPrecision`] unless checksums are disabled.
+    pub fn with_precision(
+        mut self,
+        symbol: impl Into<String>,
+        price_precision: u32,
+        qty_precision: u32,
+    ) -> Self {
+        self.precisions
+            .insert(symbol.into(), price_precision, qty_precision);
+        self
+    }
+
+    /// Take the precision of a pair received on the Kraken `instrument` channel
+    pub fn with_pair(self, pair: &KrakenPair) -> Self {
+        self.with_precision(
+            pair.symbol.clone(),
+            pair.price_precision,
+            pair.qty_precision,
+        )
+    }
+
+    /// Take the precisions of every pair of an `instrument` snapshot
+    pub fn with_instruments(mut self, instruments: &KrakenInstruments) -> Self {
+        self.precisions.insert_pairs(instruments);
+        self
+    }
+
+    /// Maintain the books without verifying the exchange checksums
+    pub fn without_checksum(mut self) -> Self {
+        self.precisions.verify = false;
+        self
+    }
+
+    /// Replace the book of the symbol with a `level3` snapshot and verify its checksum
+    pub fn apply_snapshot(&mut self, snapshot: &KrakenBook) -> Result<BookEvent, ExStreamError> {
+        let entry = self.books.entry(snapshot.symbol.clone()).or_default();
+        let precision = match self.precisions.get(&snapshot.symbol) {
+            Ok(precision) => precision,
+            Err(e) => {
+                entry.synced = false;
+                return Err(e);
+            }
+        };
+        entry.book.clear();
+        entry.synced = true;
+        for (side, orders) in [
+            (BookSide::Bid, &snapshot.bids),
+            (BookSide::Ask, &snapshot.asks),
+        ] {
+            for order in orders {
+                entry.book.add(side, order.into());
+            }
+        }
+
+        Ok(self
+            .verify(&snapshot.symbol, snapshot.checksum, precision)
+            .unwrap_or_else(|| BookEvent::Snapshot {
+                symbol: snapshot.symbol.clone(),
+            }))
+    }
+
+    /// Apply the order events of a `level3` update and verify its checksum
+    pub fn apply_update(&mut self, update: &KrakenBookUpdate) -> Result<BookEvent, ExStreamError> {
+        let entry = self.books.entry(update.symbol.clone()).or_default();
+        if !entry.synced {
+            // Waiting for a snapshot, the update cannot be applied
+            return Ok(BookEvent::Unsynced {
+                symbol: update.symbol.clone(),
+            });
+        }
+        let precision = self.precisions.get(&update.symbol)?;
+
+        for (side, orders) in [(BookSide::Bid, &update.bids), (BookSide::Ask, &update.asks)] {
+            for order in orders {
+                match order.event {
+                    KrakenOrderEvent::Add => entry.book.add(side, order.into()),
+                    KrakenOrderEvent::Modify => entry.book.modify(side, order.into()),
+                    KrakenOrderEvent::Delete => {
+                        entry.book.delete(&order.order_id);
+                    }
+                }
+            }
+        }
+
+        Ok(self
+            .verify(&update.symbol, update.checksum, precision)
+            .unwrap_or_else(|| BookEvent::Updated {
+                symbol: update.symbol.clone(),
+            }))
+    }
+
+    /// Compare the checksum of the book with the exchange checksum, marking the book
+    /// out of sync on a mismatch
+    fn verify(
+        &mut self,
+        symbol: &str,
+        checksum: u64,
+        precision: Option<Precision>,
+    ) -> Option<BookEvent> {
+        let precision = precision?;
+        let entry = self.books.get_mut(symbol)?;
+        let computed = entry.book.checksum(precision.price, precision.qty);
+        let expected = checksum as u32;
+        if computed == expected {
+            return None;
+        }
+
+        tracing::warn!(
+            "Kraken L3 book {} checksum mismatch, expected {} but computed {}",
+            symbol,
+            expected,
+            computed
+        );
+        entry.synced = false;
+        Some(BookEvent::ChecksumMismatch {
+            symbol: symbol.to_string(),
+            expected,
+            computed,
+        })
+    }
+
+    pub fn book(&self, symbol: &str) -> Option<&KrakenL3Book> {
+        self.books.get(symbol).map(|entry| &entry.book)
+    }
+
+    /// Check if the book of the symbol is up to date
+    pub fn is_synced(&self, symbol: &str) -> bool {
+        self.books.get(symbol).is_some_and(|entry| entry.synced)
+    }
+
+    /// Maintain the books from a Kraken stream, returning the books and a stream of the changes.
+    /// On a checksum mismatch the symbol is resubscribed through the handle to get a new snapshot.
+    /// Precisions are also taken from the `instrument` channel when it is subscribed on the same
+    /// connection, symbols whose snapshot came first are resubscribed once their precision is known.
+    pub fn track(
+        self,
+        stream: WsMsgStream<KrakenMessage>,
+        handle: ConnectionHandle<Kraken>,
+    ) -> (SharedKrakenL3Books, WsMsgStream<BookEvent>) {
+        let books = Arc::new(RwLock::new(self));
+        let shared = books.clone();
+        let mut awaiting = AwaitingPrecision::new(handle.clone(), KrakenChannel::L3);
+        let events = stream.flat_map(move |message| {
+            let events = match message {
+                Ok(KrakenMessage::Event(event)) => {
+                    let mut books = shared.write().expect("order books lock poisoned");
+                    event
+                        .data
+                        .iter()
+                        .filter_map(|data| match data {
+                            KrakenData::Book(snapshot) => {
+                                Some(awaiting.record(books.apply_snapshot(snapshot)))
+                            }
+                            KrakenData::BookUpdate(update) => Some(books.apply_update(update)),
+                            KrakenData::Instrument(instruments) => {
+                                books.precisions.insert_pairs(instruments);
+                                awaiting.release(&books.precisions);
+                                None
+                            }
+                            _ => None,
+                        })
+                        .collect()
+                }
+                Ok(_) => Vec::new(),
+                Err(e) => vec![Err(e)],
+            };
+            futures_util::stream::iter(events)
+        });
+        (books, resync_on_mismatch(events, handle, KrakenChannel::L3))
+    }
+}
+
+/// Resubscribe to the channel of a symbol whose book does not match the checksum. Updates keep
+/// failing until the new snapshot arrives, so the symbol is only resubscribed once per snapshot.
+fn resync_on_mismatch(
+    events: impl Stream<Item = Result<BookEvent, ExStreamError>> + Send + 'static,
+    handle: ConnectionHandle<Kraken>,
+    channel: KrakenChannel,
+) -> WsMsgStream<BookEvent> {
+    let mut resyncing = HashSet::new();
+    Box::pin(events.inspect(move |event| match event {
+        Ok(BookEvent::Snapshot { symbol }) => {
+            resyncing.remove(symbol);
+        }
+        Ok(BookEvent::ChecksumMismatch { symbol, .. }) if resyncing.insert(symbol.clone()) => {
+            resubscribe(&handle, channel, symbol);
+        }
+        _ => {}
+    }))
+}
+
+/// Resubscribe to the channel of a symbol to get a new snapshot
+fn resubscribe(handle: &ConnectionHandle<Kraken>, channel: KrakenChannel, symbol: &str) {
+    let topics = handle
+        .subscriptions()
+        .into_iter()
+        .map(|entry| entry.subscription)
+        .filter(|topic| topic.channel == channel.as_str() && topic.symbol == symbol)
+        .collect();
+    if let Err(e) = handle.resubscribe(topics) {
+        tracing::warn!(
+            "Failed to resubscribe Kraken {} {}: {}",
+            channel.as_str(),
+            symbol,
+            e
+        );
+    }
+}
+
+/// Symbols whose snapshot was rejected for an unknown precision, resubscribed once
+/// the `instrument` channel lists them
+struct AwaitingPrecision {
+    handle: ConnectionHandle<Kraken>,
+    channel: KrakenChannel,
+    symbols: HashSet<String>,
+}
+
+impl AwaitingPrecision {
+    fn new(handle: ConnectionHandle<Kraken>, channel: KrakenChannel) -> Self {
+        Self {
+            handle,
+            channel,
+            symbols: HashSet::new(),
+        }
+    }
+
+    fn record(
+        &mut self,
+        event: Result<BookEvent, ExStreamError>,
+    ) -> Result<BookEvent, ExStreamError> {
+        if let Err(ExStreamError::MissingPrecision(symbol)) = &event {
+            self.symbols.insert(symbol.clone());
+        }
+        event
+    }
+
+    fn release(&mut self, precisions: &Precisions) {
+        self.symbols.retain(|symbol| {
+            if !precisions.contains(symbol) {
+                return true;
+            }
+            resubscribe(&self.handle, self.channel, symbol);
+            false
+        });
+    }
+}
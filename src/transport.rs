@@ -1,214 +1,1139 @@
+use std::collections::hash_map::RandomState;
 use std::fmt::Debug;
+use std::hash::{BuildHasher as _, Hasher as _};
 use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 
+use futures_util::stream::{SplitSink, SplitStream};
 use futures_util::{SinkExt as _, Stream, StreamExt as _};
-use serde::{Serialize, de::DeserializeOwned};
-use tokio::sync::mpsc;
-use tokio_stream::wrappers::UnboundedReceiverStream;
-use tokio_tungstenite::{connect_async, tungstenite::Message as TungsteniteMessage};
+use serde::Serialize;
+use tokio::net::TcpStream;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio_tungstenite::{
+    MaybeTlsStream, WebSocketStream, connect_async, tungstenite::Message as TungsteniteMessage,
+};
 use tokio_util::sync::CancellationToken;
 
 use crate::error::ExStreamError;
+use crate::models::normalized::MarketData;
+use crate::models::{
+    Exchange, ExchangeMessage, RequestKind, Subscription, SubscriptionEntry, SubscriptionRequest,
+};
+
+mod ack;
+mod heartbeat;
+pub(crate) mod inbound;
+mod registry;
+mod watchdog;
+
+pub use heartbeat::Heartbeat;
+pub use inbound::{Backpressure, BufferPolicy};
+pub use watchdog::Watchdog;
 
 pub type WsMsgStream<M> = Pin<Box<dyn Stream<Item = Result<M, ExStreamError>> + Send + 'static>>;
-pub type ConnectionResult<M> = Result<(WsMsgStream<M>, ConnectionHandler), ExStreamError>;
+pub type ConnectionResult<E> =
+    Result<(WsMsgStream<<E as Exchange>::Message>, ConnectionHandler<E>), ExStreamError>;
+pub type NormalizedResult<E> =
+    Result<(WsMsgStream<MarketData>, ConnectionHandler<E>), ExStreamError>;
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+type WsSink = SplitSink<WsStream, TungsteniteMessage>;
+type WsSource = SplitStream<WsStream>;
+
+/// Number of lifecycle events buffered for each event receiver
+const EVENT_CAPACITY: usize = 64;
+
+/// Lifecycle events emitted by the connection
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectionEvent {
+    /// Opening the WebSocket connection to the endpoint
+    Connecting { endpoint: String },
+    /// The WebSocket handshake completed
+    Connected,
+    /// A subscribe or unsubscribe request was written to the socket
+    SubscriptionSent { kind: RequestKind, request: String },
+    /// The connection was lost, data may be missing until the next `Connected`
+    Disconnected { reason: String, code: Option<u16> },
+    /// No frame (or no data for `symbol`) arrived for `idle`, the connection is re-established
+    Stale {
+        symbol: Option<String>,
+        idle: Duration,
+    },
+    /// Waiting before the given reconnection attempt (starting at 1)
+    Reconnecting { attempt: u32, delay: Duration },
+    /// The connection task stopped, no more messages will be received
+    ShutDown,
+}
+
+/// Policy used to re-establish a WebSocket connection after it was lost
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    /// Delay before the first reconnection attempt
+    pub initial_backoff: Duration,
+    /// Upper bound for the delay between two attempts
+    pub max_backoff: Duration,
+    /// Factor applied to the delay after each failed attempt
+    pub multiplier: f64,
+    /// Random fraction of the delay added or removed, between 0.0 and 1.0
+    pub jitter: f64,
+    /// Maximum number of consecutive attempts, `None` to retry forever
+    pub max_attempts: Option<u32>,
+    /// A connection that stayed up for this long resets the attempt counter
+    pub reset_after: Duration,
+}
+
+impl ReconnectPolicy {
+    /// Never reconnect, the stream ends when the connection is lost
+    pub fn disabled() -> Self {
+        Self {
+            max_attempts: Some(0),
+            ..Self::default()
+        }
+    }
 
+    pub fn with_initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    pub fn with_max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: Option<u32>) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    pub fn with_reset_after(mut self, reset_after: Duration) -> Self {
+        self.reset_after = reset_after;
+        self
+    }
+
+    /// Check if the given attempt (starting at 1) is allowed
+    pub fn allows(&self, attempt: u32) -> bool {
+        self.max_attempts.is_none_or(|max| attempt <= max)
+    }
+
+    /// Delay to wait before the given attempt (starting at 1)
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let base = self.initial_backoff.as_secs_f64() * self.multiplier.max(1.0).powi(exponent);
+        let base = base.min(self.max_backoff.as_secs_f64());
+
+        // Random factor in [-jitter, +jitter]
+        let random = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64;
+        let factor = 1.0 + self.jitter * (2.0 * random - 1.0);
+
+        Duration::from_secs_f64((base * factor).max(0.0))
+    }
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.2,
+            max_attempts: None,
+            reset_after: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Connection settings shared by all the exchange builders
+#[derive(Debug, Clone)]
+pub struct ConnectionConfig {
+    pub reconnect: ReconnectPolicy,
+    /// Inbound buffer policy, the buffer is unbounded when `None`
+    pub buffer: Option<BufferPolicy>,
+    /// Application level keepalive, only needed by exchanges expecting client pings
+    pub heartbeat: Option<Heartbeat>,
+    /// Stale feed detection, disabled when `None`
+    pub watchdog: Option<Watchdog>,
+    /// Time to wait for the exchange to acknowledge a request sent with an ack
+    pub ack_timeout: Duration,
+}
+
+impl Default for ConnectionConfig {
+    fn default() -> Self {
+        Self {
+            reconnect: ReconnectPolicy::default(),
+            buffer: None,
+            heartbeat: None,
+            watchdog: None,
+            ack_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Owns the connection tasks, the connection is shut down when the handler is dropped.
+/// Dereferences to a [`ConnectionHandle`], use [`ConnectionHandler::handle`] to share it between tasks.
 #[derive(Debug)]
-/// Connection handlers that handles WebSocket connection lifecycle
-pub struct ConnectionHandler {
-    ws_tx: mpsc::UnboundedSender<TungsteniteMessage>,
+pub struct ConnectionHandler<E: Exchange> {
+    handle: ConnectionHandle<E>,
     writer_task: tokio::task::JoinHandle<()>,
     connection_task: tokio::task::JoinHandle<()>,
+}
+
+impl<E: Exchange> ConnectionHandler<E> {
+    /// Cloneable handle controlling this connection
+    pub fn handle(&self) -> ConnectionHandle<E> {
+        self.handle.clone()
+    }
+
+    /// Gracefully shutdown the connection, aborting the tasks still running after the timeout
+    pub async fn shutdown(mut self, timeout: Duration) -> Result<(), ExStreamError> {
+        tracing::info!("Shutting down connection handler");
+        self.handle.shutdown.cancel();
+
+        let tasks = async { tokio::try_join!(&mut self.writer_task, &mut self.connection_task) };
+        match tokio::time::timeout(timeout, tasks).await {
+            Ok(result) => {
+                result.map_err(ExStreamError::TaskError)?;
+                Ok(())
+            }
+            Err(_) => {
+                tracing::warn!(
+                    "Connection tasks still running after {:?}, aborting",
+                    timeout
+                );
+                self.writer_task.abort();
+                self.connection_task.abort();
+                Err(ExStreamError::ShutdownTimeout(timeout))
+            }
+        }
+    }
+
+    /// Check every subscription added through the handles before sending it
+    pub(crate) fn with_request_check(
+        mut self,
+        check: impl Fn(&E::Request) -> Result<(), ExStreamError> + Send + Sync + 'static,
+    ) -> Self {
+        self.handle.check = Some(RequestCheck(Arc::new(check)));
+        self
+    }
+
+    /// Check if the connection tasks are still running, they finish shortly after
+    /// [`ConnectionHandle::is_alive`] turns false
+    pub fn tasks_running(&self) -> bool {
+        !self.writer_task.is_finished() && !self.connection_task.is_finished()
+    }
+}
+
+impl<E: Exchange> std::ops::Deref for ConnectionHandler<E> {
+    type Target = ConnectionHandle<E>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.handle
+    }
+}
+
+impl<E: Exchange> Drop for ConnectionHandler<E> {
+    fn drop(&mut self) {
+        self.handle.shutdown.cancel();
+    }
+}
+
+/// Cheap cloneable handle to manage the subscriptions and observe the lifecycle of a connection.
+/// Handles do not keep the connection open, it ends when its [`ConnectionHandler`] is dropped.
+#[derive(Debug)]
+pub struct ConnectionHandle<E: Exchange> {
+    ws_tx: mpsc::UnboundedSender<Outbound>,
+    events: broadcast::Sender<ConnectionEvent>,
+    registry: registry::Registry<E::Request>,
+    symbol_activity: watchdog::SymbolActivity,
+    acks: ack::PendingAcks,
+    next_request_id: Arc<AtomicU64>,
+    ack_timeout: Duration,
+    check: Option<RequestCheck<E::Request>>,
     shutdown: CancellationToken,
 }
 
-impl ConnectionHandler {
-    /// Add a subscription
-    pub fn subscribe(&self, message: impl Serialize + Debug) -> Result<(), ExStreamError> {
-        let sub = serde_json::to_string(&message).map_err(|e| ExStreamError::ParseError {
-            error: e,
-            raw_content: format!("{:?}", message),
-        })?;
+type CheckFn<R> = dyn Fn(&R) -> Result<(), ExStreamError> + Send + Sync;
+
+/// Exchange specific check of the subscriptions added on a running connection
+struct RequestCheck<R>(Arc<CheckFn<R>>);
+
+impl<R> Clone for RequestCheck<R> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<R> Debug for RequestCheck<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("RequestCheck")
+    }
+}
+
+impl<E: Exchange> Clone for ConnectionHandle<E> {
+    fn clone(&self) -> Self {
+        Self {
+            ws_tx: self.ws_tx.clone(),
+            events: self.events.clone(),
+            registry: self.registry.clone(),
+            symbol_activity: self.symbol_activity.clone(),
+            acks: self.acks.clone(),
+            next_request_id: self.next_request_id.clone(),
+            ack_timeout: self.ack_timeout,
+            check: self.check.clone(),
+            shutdown: self.shutdown.clone(),
+        }
+    }
+}
+
+impl<E: Exchange> ConnectionHandle<E> {
+    /// Add a subscription, a request id is assigned when none is set
+    pub fn subscribe(&self, mut message: E::Request) -> Result<(), ExStreamError> {
+        if let Some(RequestCheck(check)) = &self.check {
+            check(&message)?;
+        }
+        self.assign_request_id(&mut message);
+        let sub = to_text(&message)?;
 
         tracing::info!("Adding subscription: {:?}", sub);
-        self.ws_tx
-            .send(TungsteniteMessage::Text(sub.into()))
-            .map_err(|_| ExStreamError::StreamClosed)?;
-        Ok(())
+        self.registry.send_and_track(message, |message| {
+            self.ws_tx
+                .send(Outbound::Request(message.kind(), sub))
+                .map_err(|_| ExStreamError::StreamClosed)
+        })
     }
 
-    /// Remove a subscription
-    pub fn unsubscribe(&self, message: impl Serialize + Debug) -> Result<(), ExStreamError> {
-        let unsub = serde_json::to_string(&message).map_err(|e| ExStreamError::ParseError {
-            error: e,
-            raw_content: format!("{:?}", message),
-        })?;
+    /// Remove a subscription, a request id is assigned when none is set
+    pub fn unsubscribe(&self, mut message: E::Request) -> Result<(), ExStreamError> {
+        self.assign_request_id(&mut message);
+        let unsub = to_text(&message)?;
 
         tracing::info!("Removing subscription: {:?}", unsub);
-        self.ws_tx
-            .send(TungsteniteMessage::Text(unsub.into()))
-            .map_err(|_| ExStreamError::StreamClosed)?;
+        let topics = message.topics();
+        self.registry.send_and_track(message, |message| {
+            self.ws_tx
+                .send(Outbound::Request(message.kind(), unsub))
+                .map_err(|_| ExStreamError::StreamClosed)
+        })?;
+        self.forget_symbols(&topics);
         Ok(())
     }
 
-    /// Send a custom message to the WebSocket
+    /// Stop watching the symbols of the topics that no other subscription covers
+    fn forget_symbols(&self, topics: &[Subscription]) {
+        let remaining = self.registry.topics();
+        let gone = topics
+            .iter()
+            .map(|topic| topic.symbol.as_str())
+            .filter(|symbol| {
+                !remaining
+                    .iter()
+                    .any(|topic| topic.symbol.eq_ignore_ascii_case(symbol))
+            })
+            .collect::<Vec<_>>();
+        if gone.is_empty() {
+            return;
+        }
+
+        self.symbol_activity
+            .lock()
+            .expect("symbol activity lock poisoned")
+            .retain(|symbol, _| !gone.iter().any(|gone| gone.eq_ignore_ascii_case(symbol)));
+    }
+
+    /// Add a subscription and wait for the exchange ack, a request id is assigned when none is set.
+    /// When the exchange rejects only some topics, the others stay subscribed.
+    pub async fn subscribe_with_ack(&self, mut message: E::Request) -> Result<(), ExStreamError> {
+        let ack = self.register_ack(&mut message)?;
+        let request = message.clone();
+        if let Err(e) = self.subscribe(message) {
+            self.acks.remove(&ack.0);
+            return Err(e);
+        }
+
+        match self.receive_ack(ack).await {
+            // The registry already dropped the topics the exchange rejected
+            Ok(answer) => answer.map_err(|reason| ExStreamError::SubscriptionRejected { reason }),
+            Err(e) => {
+                // The exchange is not known to stream these topics, do not replay them
+                self.registry.untrack(&request);
+                Err(e)
+            }
+        }
+    }
+
+    /// Remove a subscription and wait for the exchange ack, a request id is assigned when none is set
+    pub async fn unsubscribe_with_ack(&self, mut message: E::Request) -> Result<(), ExStreamError> {
+        let ack = self.register_ack(&mut message)?;
+        let active = self.registry.requests();
+        let current = self.registry.topics();
+        let removed = message
+            .topics()
+            .into_iter()
+            .filter(|topic| current.contains(topic))
+            .collect::<Vec<_>>();
+        if let Err(e) = self.unsubscribe(message) {
+            self.acks.remove(&ack.0);
+            return Err(e);
+        }
+
+        let result = self.wait_ack(ack).await;
+        if result.is_err() {
+            // The exchange may still be streaming these topics, keep replaying them
+            for request in E::Request::from_topics(RequestKind::Subscribe, &removed, &active) {
+                self.registry.track(request);
+            }
+        }
+        result
+    }
+
+    /// Active topics and whether the exchange acknowledged them.
+    /// Topics are pending again after a reconnect until the exchange acknowledges the replay.
+    pub fn subscriptions(&self) -> Vec<SubscriptionEntry> {
+        self.registry.entries()
+    }
+
+    /// Subscribe to exactly the given topics, sending the unsubscribe and subscribe
+    /// requests for the difference with the active topics
+    pub fn set_subscriptions(&self, desired: Vec<Subscription>) -> Result<(), ExStreamError> {
+        let active = self.registry.requests();
+        // Round trip through the requests to get the topics as the exchange names them
+        let desired = E::Request::from_topics(RequestKind::Subscribe, &desired, &active)
+            .iter()
+            .flat_map(SubscriptionRequest::topics)
+            .collect::<Vec<_>>();
+        let current = self.registry.topics();
+
+        let removed = current
+            .iter()
+            .filter(|topic| !desired.contains(topic))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut added = Vec::new();
+        for topic in desired {
+            if !current.contains(&topic) && !added.contains(&topic) {
+                added.push(topic);
+            }
+        }
+
+        for request in E::Request::from_topics(RequestKind::Unsubscribe, &removed, &active) {
+            self.unsubscribe(request)?;
+        }
+        for request in E::Request::from_topics(RequestKind::Subscribe, &added, &active) {
+            self.subscribe(request)?;
+        }
+        Ok(())
+    }
+
+    /// Unsubscribe and subscribe again to the topics, e.g. to receive a fresh book snapshot
+    pub fn resubscribe(&self, topics: Vec<Subscription>) -> Result<(), ExStreamError> {
+        if topics.is_empty() {
+            return Ok(());
+        }
+
+        let active = self.registry.requests();
+        for request in E::Request::from_topics(RequestKind::Unsubscribe, &topics, &active) {
+            self.unsubscribe(request)?;
+        }
+        for request in E::Request::from_topics(RequestKind::Subscribe, &topics, &active) {
+            self.subscribe(request)?;
+        }
+        Ok(())
+    }
+
+    fn assign_request_id(&self, message: &mut E::Request) {
+        if message.request_id().is_none() {
+            message.set_request_id(self.next_request_id.fetch_add(1, Ordering::Relaxed));
+        }
+    }
+
+    /// Register the request for its ack. The caller's request id is kept when set,
+    /// it must not be shared with another request still waiting for its ack.
+    fn register_ack(
+        &self,
+        message: &mut E::Request,
+    ) -> Result<(String, oneshot::Receiver<Result<(), String>>), ExStreamError> {
+        self.assign_request_id(message);
+        let Some(request_id) = message.request_id() else {
+            return Err(ExStreamError::UnsupportedRequest(
+                "the exchange does not support request ids".to_string(),
+            ));
+        };
+
+        let rx = self
+            .acks
+            .register(request_id.clone(), message.expected_acks());
+        Ok((request_id, rx))
+    }
+
+    async fn wait_ack(
+        &self,
+        ack: (String, oneshot::Receiver<Result<(), String>>),
+    ) -> Result<(), ExStreamError> {
+        self.receive_ack(ack)
+            .await?
+            .map_err(|reason| ExStreamError::SubscriptionRejected { reason })
+    }
+
+    /// Wait for the exchange answer to a request, `Err` when none arrived
+    async fn receive_ack(
+        &self,
+        (request_id, rx): (String, oneshot::Receiver<Result<(), String>>),
+    ) -> Result<Result<(), String>, ExStreamError> {
+        match tokio::time::timeout(self.ack_timeout, rx).await {
+            Ok(Ok(answer)) => Ok(answer),
+            Ok(Err(_)) => Err(ExStreamError::StreamClosed),
+            Err(_) => {
+                self.acks.remove(&request_id);
+                Err(ExStreamError::SubscriptionRejected {
+                    reason: format!("no acknowledgement within {:?}", self.ack_timeout),
+                })
+            }
+        }
+    }
+
+    /// Send a custom message to the WebSocket, custom messages are not replayed on reconnect
     pub fn send_message(&self, message: TungsteniteMessage) -> Result<(), ExStreamError> {
         tracing::info!("Sending custom message: {:?}", message);
         self.ws_tx
-            .send(message)
+            .send(Outbound::Message(message))
             .map_err(|_| ExStreamError::StreamClosed)?;
         Ok(())
     }
 
-    /// Gracefully shutdown the connection
-    pub async fn shutdown(self) -> Result<(), ExStreamError> {
-        tracing::info!("Shutting down connection handler");
-        self.shutdown.cancel();
-
-        tokio::try_join!(self.writer_task, self.connection_task)
-            .map_err(ExStreamError::TaskError)?;
-        Ok(())
+    /// Receive the lifecycle events emitted from now on.
+    /// The initial connection is already established when the handler is returned.
+    pub fn events(&self) -> broadcast::Receiver<ConnectionEvent> {
+        self.events.subscribe()
     }
 
-    /// Synchronously shutdown the connection
+    /// Request the connection to shut down without waiting for the tasks to finish
     pub fn shutdown_sync(&self) {
         self.shutdown.cancel();
     }
 
-    /// Check if the connection is still alive
+    /// Check if the connection is still alive, i.e. neither shut down nor given up reconnecting
     pub fn is_alive(&self) -> bool {
-        !self.writer_task.is_finished() && !self.connection_task.is_finished()
+        !self.shutdown.is_cancelled()
     }
 }
 
-/// Establish a WebSocket connection with the given source and subscription messages
-pub async fn connect_ws<M>(
+/// Establish a WebSocket connection with the given source and subscription messages,
+/// every initial message is sent separately once connected
+pub async fn connect_ws<E: Exchange>(
     endpoint: impl Into<String>,
-    initial_message: impl Serialize + Debug,
-) -> ConnectionResult<M>
-where
-    M: DeserializeOwned + Debug + Send + 'static,
-{
-    let sub = serde_json::to_string(&initial_message).map_err(|e| ExStreamError::ParseError {
-        error: e,
-        raw_content: format!("{:?}", initial_message),
-    })?;
-
-    let (ws_stream, _) = connect_async(endpoint.into()).await?;
-    let (mut write, mut read) = ws_stream.split();
-
+    initial_messages: Vec<E::Request>,
+    config: ConnectionConfig,
+) -> ConnectionResult<E> {
     // Message channels for forwarding messages to/from the WebSocket
-    let (outbound_tx, mut outbound_rx) = mpsc::unbounded_channel::<TungsteniteMessage>();
-    let (inbound_tx, inbound_rx) = mpsc::unbounded_channel::<Result<M, ExStreamError>>();
+    let (outbound_tx, outbound_rx) = mpsc::unbounded_channel::<Outbound>();
+    let (inbound_tx, inbound_rx) = inbound::channel::<E::Message>(config.buffer);
+    // Channel for handing the sink of a re-established connection to the writer task
+    let (sink_tx, sink_rx) = mpsc::unbounded_channel::<WsSink>();
+    // Heartbeat signals between the connection task and the writer task
+    let (pong_tx, pong_rx) = mpsc::unbounded_channel::<()>();
+    let (dead_tx, dead_rx) = mpsc::unbounded_channel::<()>();
+    let (events, _) = broadcast::channel(EVENT_CAPACITY);
 
     // Create a cancellation token for graceful shutdown
     let shutdown = CancellationToken::new();
 
-    // Send subscription message
-    write.send(TungsteniteMessage::Text(sub.into())).await?;
+    // Start far from the ids users typically pick for their own requests
+    let next_request_id = Arc::new(AtomicU64::new(1 << 32));
+    let initial_messages = initial_messages
+        .into_iter()
+        .map(|mut message| {
+            if message.request_id().is_none() {
+                message.set_request_id(next_request_id.fetch_add(1, Ordering::Relaxed));
+            }
+            message
+        })
+        .collect();
+    let registry = registry::Registry::new(initial_messages);
+    let symbol_activity = watchdog::SymbolActivity::default();
+    let acks = ack::PendingAcks::default();
+    let ack_timeout = config.ack_timeout;
+    let connection = Connection::<E> {
+        endpoint: endpoint.into(),
+        config,
+        registry: registry.clone(),
+        symbol_activity: symbol_activity.clone(),
+        acks: acks.clone(),
+        inbound_tx,
+        ping_pong_tx: outbound_tx.clone(),
+        sink_tx,
+        pong_tx,
+        events: events.clone(),
+        shutdown: shutdown.clone(),
+    };
+    let (write, read) = connection.open_session().await?;
 
     // Spawn writer task
-    let shutdown_signal = shutdown.clone();
-    let writer_task = tokio::spawn(async move {
+    let writer = Writer {
+        registry: registry.clone(),
+        heartbeat: connection.config.heartbeat.clone(),
+        outbound_rx,
+        sink_rx,
+        pong_rx,
+        dead_tx,
+        events: events.clone(),
+        shutdown: shutdown.clone(),
+    };
+    let writer_task = tokio::spawn(writer.run(write));
+
+    // Spawn connection task
+    let connection_task = tokio::spawn(connection.run(read, dead_rx));
+
+    let handle = ConnectionHandle {
+        ws_tx: outbound_tx,
+        events,
+        registry,
+        symbol_activity,
+        acks,
+        next_request_id,
+        ack_timeout,
+        check: None,
+        shutdown,
+    };
+    let handler = ConnectionHandler {
+        handle,
+        writer_task,
+        connection_task,
+    };
+
+    let stream = inbound_rx.into_stream();
+
+    Ok((stream, handler))
+}
+
+/// Serialize a request into a WebSocket text message
+fn to_text(message: &(impl Serialize + Debug)) -> Result<TungsteniteMessage, ExStreamError> {
+    let text = serde_json::to_string(message).map_err(|e| ExStreamError::ParseError {
+        error: e,
+        raw_content: format!("{:?}", message),
+    })?;
+    Ok(TungsteniteMessage::Text(text.into()))
+}
+
+/// Message queued for the writer task
+#[derive(Debug)]
+enum Outbound {
+    /// Subscribe or unsubscribe request, reported as a lifecycle event once sent
+    Request(RequestKind, TungsteniteMessage),
+    Message(TungsteniteMessage),
+}
+
+/// State owned by the writer task, which forwards outbound messages to the current connection
+struct Writer<R> {
+    registry: registry::Registry<R>,
+    heartbeat: Option<Heartbeat>,
+    outbound_rx: mpsc::UnboundedReceiver<Outbound>,
+    sink_rx: mpsc::UnboundedReceiver<WsSink>,
+    /// Pongs received by the connection task
+    pong_rx: mpsc::UnboundedReceiver<()>,
+    /// Tells the connection task that the heartbeat timed out
+    dead_tx: mpsc::UnboundedSender<()>,
+    events: broadcast::Sender<ConnectionEvent>,
+    shutdown: CancellationToken,
+}
+
+impl<R: SubscriptionRequest> Writer<R> {
+    /// Send every active subscription on a new session. Requests queued for the previous
+    /// session are dropped, the replay is taken after them and already covers them.
+    async fn start_session(&mut self, mut sink: WsSink) -> Option<WsSink> {
+        let outbound_rx = &mut self.outbound_rx;
+        let requests = self.registry.replay(|| {
+            while let Ok(outbound) = outbound_rx.try_recv() {
+                tracing::debug!(
+                    "Dropping message queued for the previous session: {:?}",
+                    outbound
+                );
+            }
+        });
+
+        for request in requests {
+            let message = match to_text(&request) {
+                Ok(message) => message,
+                Err(e) => {
+                    tracing::error!("Failed to serialize subscription: {:?}", e);
+                    continue;
+                }
+            };
+            let text = message.to_string();
+            if sink.send(message).await.is_err() {
+                tracing::info!("Failed to send subscription, WebSocket closed");
+                return None;
+            }
+            emit(
+                &self.events,
+                ConnectionEvent::SubscriptionSent {
+                    kind: request.kind(),
+                    request: text,
+                },
+            );
+        }
+        Some(sink)
+    }
+
+    async fn run(mut self, write: WsSink) {
+        let mut write = self.start_session(write).await;
+        let mut ping_timer = self.heartbeat.as_ref().map(|heartbeat| {
+            let mut timer = tokio::time::interval_at(
+                tokio::time::Instant::now() + heartbeat.interval,
+                heartbeat.interval,
+            );
+            timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            timer
+        });
+        let mut pong_deadline: Option<Pin<Box<tokio::time::Sleep>>> = None;
+
         loop {
             tokio::select! {
-                Some(message) = outbound_rx.recv() => {
-                    tracing::trace!("Sending message: {:?}", message);
-                    if write.send(message).await.is_err() {
+                Some(sink) = self.sink_rx.recv() => {
+                    tracing::debug!("Writer task switched to a new connection");
+                    write = self.start_session(sink).await;
+                    pong_deadline = None;
+                    if let Some(timer) = ping_timer.as_mut() {
+                        timer.reset();
+                    }
+                }
+                _ = next_tick(&mut ping_timer) => {
+                    let (Some(sink), Some(heartbeat)) = (write.as_mut(), self.heartbeat.as_ref()) else {
+                        continue;
+                    };
+
+                    tracing::trace!("Sending heartbeat ping");
+                    if sink.send(heartbeat.ping()).await.is_err() {
+                        tracing::info!("Failed to send heartbeat, WebSocket closed");
+                        write = None;
+                    } else if pong_deadline.is_none() {
+                        pong_deadline = Some(Box::pin(tokio::time::sleep(heartbeat.timeout)));
+                    }
+                }
+                Some(()) = self.pong_rx.recv() => {
+                    tracing::trace!("Received heartbeat pong");
+                    pong_deadline = None;
+                }
+                _ = expired(&mut pong_deadline) => {
+                    tracing::warn!("Heartbeat timed out, closing the connection");
+                    pong_deadline = None;
+                    if let Some(mut sink) = write.take()
+                        && sink.send(TungsteniteMessage::Close(None)).await.is_err()
+                    {
+                        tracing::info!("Failed to send close message, WebSocket closed");
+                    }
+                    let _ = self.dead_tx.send(());
+                }
+                Some(outbound) = self.outbound_rx.recv() => {
+                    let Some(sink) = write.as_mut() else {
+                        tracing::warn!("Dropping message while reconnecting: {:?}", outbound);
+                        continue;
+                    };
+
+                    tracing::trace!("Sending message: {:?}", outbound);
+                    let result = match outbound {
+                        Outbound::Request(kind, message) => {
+                            let request = message.to_string();
+                            sink.send(message)
+                                .await
+                                .map(|_| emit(&self.events, ConnectionEvent::SubscriptionSent { kind, request }))
+                        }
+                        Outbound::Message(message) => sink.send(message).await,
+                    };
+
+                    if result.is_err() {
                         tracing::info!("Failed to send message, WebSocket closed");
-                        break;
+                        write = None;
                     }
                 }
-                _ = shutdown_signal.cancelled() => {
+                _ = self.shutdown.cancelled() => {
                     tracing::info!("Shutdown signal received on writer task, terminating.");
-                    if write.send(TungsteniteMessage::Close(None)).await.is_err() {
+                    if let Some(mut sink) = write.take()
+                        && sink.send(TungsteniteMessage::Close(None)).await.is_err()
+                    {
                         tracing::info!("Failed to send close message, WebSocket closed");
                     }
                     break;
                 }
+                else => break,
             }
         }
         tracing::info!("Writer task finished, no more messages to send.");
-    });
+    }
+}
+
+/// Wait for the next tick of an optional timer, never completes without a timer
+async fn next_tick(timer: &mut Option<tokio::time::Interval>) {
+    match timer {
+        Some(timer) => {
+            timer.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Wait for an optional deadline, never completes without a deadline
+async fn expired(deadline: &mut Option<Pin<Box<tokio::time::Sleep>>>) {
+    match deadline {
+        Some(deadline) => deadline.as_mut().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Publish a lifecycle event, events are dropped when nobody is listening
+fn emit(events: &broadcast::Sender<ConnectionEvent>, event: ConnectionEvent) {
+    tracing::debug!("Connection event: {:?}", event);
+    let _ = events.send(event);
+}
+
+/// Why a WebSocket session stopped reading
+enum SessionEnd {
+    /// Shutdown was requested through the handler
+    Shutdown,
+    /// The consumer dropped the message stream
+    StreamDropped,
+    /// The connection was lost, with the error that caused it if any
+    Disconnected {
+        reason: String,
+        code: Option<u16>,
+        error: Option<ExStreamError>,
+    },
+}
+
+/// State owned by the connection task, which reads from the WebSocket and reconnects when needed
+struct Connection<E: Exchange> {
+    endpoint: String,
+    config: ConnectionConfig,
+    registry: registry::Registry<E::Request>,
+    symbol_activity: watchdog::SymbolActivity,
+    acks: ack::PendingAcks,
+    inbound_tx: inbound::InboundSender<E::Message>,
+    ping_pong_tx: mpsc::UnboundedSender<Outbound>,
+    sink_tx: mpsc::UnboundedSender<WsSink>,
+    pong_tx: mpsc::UnboundedSender<()>,
+    events: broadcast::Sender<ConnectionEvent>,
+    shutdown: CancellationToken,
+}
+
+impl<E: Exchange> Connection<E> {
+    async fn run(self, mut read: WsSource, mut dead_rx: mpsc::UnboundedReceiver<()>) {
+        let mut attempt = 0;
+        loop {
+            // Discard heartbeat timeouts raised for a previous session
+            while dead_rx.try_recv().is_ok() {}
+
+            let connected_at = Instant::now();
+            let error = match self.read_session(&mut read, &mut dead_rx).await {
+                SessionEnd::Shutdown | SessionEnd::StreamDropped => break,
+                SessionEnd::Disconnected {
+                    reason,
+                    code,
+                    error,
+                } => {
+                    emit(&self.events, ConnectionEvent::Disconnected { reason, code });
+                    error
+                }
+            };
+
+            if connected_at.elapsed() >= self.config.reconnect.reset_after {
+                attempt = 0;
+            }
+
+            match self.reconnect(&mut attempt).await {
+                Some(new_read) => read = new_read,
+                None => {
+                    // Not reconnecting, surface the error that ended the connection
+                    if let Some(error) = error
+                        && !self.forward(Err(error)).await
+                    {
+                        tracing::info!("Failed to forward error, inbound message channel closed");
+                    }
+                    break;
+                }
+            }
+        }
+
+        // Stop the writer task too, and let the handles know the connection is gone
+        self.shutdown.cancel();
+        emit(&self.events, ConnectionEvent::ShutDown);
+    }
+
+    /// Connect to the endpoint, the writer task sends the subscriptions once it owns the sink
+    async fn open_session(&self) -> Result<(WsSink, WsSource), ExStreamError> {
+        emit(
+            &self.events,
+            ConnectionEvent::Connecting {
+                endpoint: self.endpoint.clone(),
+            },
+        );
+        let (ws_stream, _) = connect_async(self.endpoint.as_str()).await?;
+        let (write, read) = ws_stream.split();
+        emit(&self.events, ConnectionEvent::Connected);
+
+        Ok((write, read))
+    }
+
+    /// Re-establish the connection following the reconnect policy, returns `None` when giving up
+    async fn reconnect(&self, attempt: &mut u32) -> Option<WsSource> {
+        let policy = &self.config.reconnect;
+        loop {
+            *attempt += 1;
+            if !policy.allows(*attempt) {
+                tracing::warn!("Giving up reconnecting to {}", self.endpoint);
+                return None;
+            }
+
+            let delay = policy.backoff(*attempt);
+            emit(
+                &self.events,
+                ConnectionEvent::Reconnecting {
+                    attempt: *attempt,
+                    delay,
+                },
+            );
+            tracing::info!(
+                "Reconnecting to {} in {:?} (attempt {})",
+                self.endpoint,
+                delay,
+                attempt
+            );
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = self.shutdown.cancelled() => return None,
+            }
+
+            match self.open_session().await {
+                Ok((write, read)) => {
+                    tracing::info!("Reconnected to {}", self.endpoint);
+                    if self.sink_tx.send(write).is_err() {
+                        tracing::info!("Writer task finished, cannot resume connection");
+                        return None;
+                    }
+                    return Some(read);
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to reconnect to {}: {:?}", self.endpoint, e);
+                    emit(
+                        &self.events,
+                        ConnectionEvent::Disconnected {
+                            reason: e.to_string(),
+                            code: None,
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    /// Forward a message to the consumer, returns false when the stream no longer accepts messages
+    async fn forward(&self, message: Result<E::Message, ExStreamError>) -> bool {
+        tokio::select! {
+            result = self.inbound_tx.send(message) => result.is_ok(),
+            _ = self.shutdown.cancelled() => false,
+        }
+    }
+
+    async fn read_session(
+        &self,
+        read: &mut WsSource,
+        dead_rx: &mut mpsc::UnboundedReceiver<()>,
+    ) -> SessionEnd {
+        let mut monitor = self
+            .config
+            .watchdog
+            .map(|config| watchdog::FeedMonitor::new(config, self.symbol_activity.clone()));
+        let mut watchdog_timer = self
+            .config
+            .watchdog
+            .map(|config| tokio::time::interval(config.check_period()));
 
-    // Spawn connection task
-    let shutdown_signal = shutdown.clone();
-    let ping_pong_tx = outbound_tx.clone();
-    let connection_task = tokio::spawn(async move {
         loop {
             tokio::select! {
                 message = read.next() => {
+                    if let Some(monitor) = monitor.as_mut() {
+                        monitor.frame();
+                    }
+
                     match message {
                         Some(Ok(TungsteniteMessage::Text(text))) => {
                             tracing::debug!("Received text message: {}", text);
 
-                            let msg = serde_json::from_str::<M>(&text)
+                            if let Some(heartbeat) = &self.config.heartbeat
+                                && heartbeat.is_pong(&text)
+                            {
+                                let _ = self.pong_tx.send(());
+                                continue;
+                            }
+
+                            let msg = serde_json::from_str::<E::Message>(&text)
                                 .map_err(|e| ExStreamError::ParseError {
                                     error: e,
                                     raw_content: text.to_string(),
                                 });
                             tracing::trace!("Parsed message: {:?}", msg);
 
-                            if inbound_tx.send(msg).is_err() {
+                            if let (Some(monitor), Ok(msg)) = (monitor.as_ref(), msg.as_ref())
+                                && let Some(symbol) = msg.symbol()
+                            {
+                                monitor.data(symbol);
+                            }
+
+                            if let Ok(msg) = msg.as_ref() {
+                                if let Some(ack) = msg.ack() {
+                                    self.registry.acknowledge(&ack);
+                                    self.acks.resolve(ack);
+                                }
+                                if let Some(topics) = msg.active_topics() {
+                                    self.registry.confirm_exact(topics);
+                                }
+                            }
+
+                            let forwarding = tokio::time::Instant::now();
+                            if !self.forward(msg).await {
                                 tracing::info!("Failed to send {text}, inbound message channel closed");
-                                break;
+                                return SessionEnd::StreamDropped;
+                            }
+                            // A full buffer with `Backpressure::Block` stops reading the socket
+                            if let Some(monitor) = monitor.as_mut() {
+                                monitor.pause(forwarding.elapsed());
                             }
                         }
                         Some(Ok(TungsteniteMessage::Ping(ping))) => {
                             tracing::trace!("Received ping: {:?}", ping);
 
-                            if ping_pong_tx.send(TungsteniteMessage::Pong(ping)).is_err() {
+                            if self.ping_pong_tx.send(Outbound::Message(TungsteniteMessage::Pong(ping))).is_err() {
                                 tracing::info!("Failed to send pong, outbound message channel closed");
-                                break;
+                                return SessionEnd::Shutdown;
                             };
                         }
                         Some(Ok(TungsteniteMessage::Pong(pong))) => {
                             tracing::trace!("Received pong: {:?}", pong);
                         }
-                        Some(Ok(TungsteniteMessage::Close(_))) => {
+                        Some(Ok(TungsteniteMessage::Close(frame))) => {
                             tracing::info!("WebSocket connection closed");
 
-                            break;
+                            return match frame {
+                                Some(frame) => SessionEnd::Disconnected {
+                                    reason: frame.reason.to_string(),
+                                    code: Some(frame.code.into()),
+                                    error: None,
+                                },
+                                None => SessionEnd::Disconnected {
+                                    reason: "closed without a close frame".to_string(),
+                                    code: None,
+                                    error: None,
+                                },
+                            };
                         }
                         Some(Ok(msg)) => {
                             tracing::warn!("Received unsupported message type");
 
-                            if inbound_tx.send(Err(ExStreamError::UnsupportedMessage(msg.to_string()))).is_err() {
+                            if !self.forward(Err(ExStreamError::UnsupportedMessage(msg.to_string()))).await {
                                 tracing::info!("Failed to forward unsupported message, inbound message channel closed");
-                                break;
+                                return SessionEnd::StreamDropped;
                             }
-                            continue;
                         }
                         Some(Err(e)) => {
                             tracing::error!("Error receiving message: {:?}", e);
 
-                            if inbound_tx.send(Err(ExStreamError::TungsteniteError(Box::new(e)))).is_err() {
-                                tracing::info!("Failed to forward error, inbound message channel closed");
-                            }
-                            break;
+                            return SessionEnd::Disconnected {
+                                reason: e.to_string(),
+                                code: None,
+                                error: Some(ExStreamError::TungsteniteError(Box::new(e))),
+                            };
                         }
                         None => {
                             tracing::info!("WebSocket client closed by server.");
-                            break;
+                            return SessionEnd::Disconnected {
+                                reason: "connection closed by server".to_string(),
+                                code: None,
+                                error: None,
+                            };
                         }
                     }
                 }
-                _ = shutdown_signal.cancelled() => {
+                _ = next_tick(&mut watchdog_timer) => {
+                    let Some(stale) = monitor.as_ref().and_then(|monitor| monitor.check()) else {
+                        continue;
+                    };
+
+                    tracing::warn!("Stale feed {:?}, idle for {:?}", stale.symbol, stale.idle);
+                    emit(&self.events, ConnectionEvent::Stale { symbol: stale.symbol, idle: stale.idle });
+                    return SessionEnd::Disconnected {
+                        reason: "stale feed".to_string(),
+                        code: None,
+                        error: None,
+                    };
+                }
+                Some(()) = dead_rx.recv() => {
+                    return SessionEnd::Disconnected {
+                        reason: "heartbeat timed out".to_string(),
+                        code: None,
+                        error: None,
+                    };
+                }
+                _ = self.shutdown.cancelled() => {
                     tracing::info!("Shutdown signal received on connection task, terminating.");
-                    break;
+                    return SessionEnd::Shutdown;
                 }
             }
         }
-    });
+    }
+}
 
-    let handler = ConnectionHandler {
-        ws_tx: outbound_tx,
-        writer_task,
-        connection_task,
-        shutdown,
-    };
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let stream = Box::pin(UnboundedReceiverStream::new(inbound_rx));
+    #[test]
+    fn backoff_grows_up_to_the_max() {
+        let policy = ReconnectPolicy::default()
+            .with_initial_backoff(Duration::from_millis(100))
+            .with_max_backoff(Duration::from_millis(1000))
+            .with_jitter(0.0);
 
-    Ok((stream, handler))
+        let delays = (1..=6)
+            .map(|attempt| policy.backoff(attempt))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            delays,
+            [100, 200, 400, 800, 1000, 1000].map(Duration::from_millis)
+        );
+    }
+
+    #[test]
+    fn jitter_stays_within_its_fraction() {
+        let policy = ReconnectPolicy::default()
+            .with_initial_backoff(Duration::from_millis(1000))
+            .with_jitter(0.2);
+
+        for _ in 0..100 {
+            let delay = policy.backoff(1);
+            assert!(
+                (Duration::from_millis(800)..=Duration::from_millis(1200)).contains(&delay),
+                "{delay:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn max_attempts_bound_the_retries() {
+        assert!(!ReconnectPolicy::disabled().allows(1));
+
+        let policy = ReconnectPolicy::default().with_max_attempts(Some(3));
+        assert!(policy.allows(3));
+        assert!(!policy.allows(4));
+        assert!(ReconnectPolicy::default().allows(u32::MAX));
+    }
 }
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::oneshot;
+
+use crate::models::SubscriptionAck;
+
+#[derive(Debug)]
+struct PendingAck {
+    remaining: usize,
+    tx: oneshot::Sender<Result<(), String>>,
+}
+
+/// Requests waiting for their exchange ack, keyed by request id
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PendingAcks {
+    pending: Arc<Mutex<HashMap<String, PendingAck>>>,
+}
+
+impl PendingAcks {
+    /// Wait for `expected` successful acks, or the first rejection, for the request id
+    pub(crate) fn register(
+        &self,
+        request_id: String,
+        expected: usize,
+    ) -> oneshot::Receiver<Result<(), String>> {
+        let (tx, rx) = oneshot::channel();
+        let ack = PendingAck {
+            remaining: expected,
+            tx,
+        };
+        self.lock().insert(request_id, ack);
+        rx
+    }
+
+    pub(crate) fn remove(&self, request_id: &str) {
+        self.lock().remove(request_id);
+    }
+
+    /// Resolve the pending request answered by the ack, if any
+    pub(crate) fn resolve(&self, ack: SubscriptionAck) {
+        let mut pending = self.lock();
+        let Some(entry) = pending.get_mut(&ack.request_id) else {
+            return;
+        };
+
+        entry.remaining = entry.remaining.saturating_sub(1);
+        if ack.result.is_ok() && entry.remaining > 0 {
+            return;
+        }
+
+        if let Some(entry) = pending.remove(&ack.request_id) {
+            let _ = entry.tx.send(ack.result);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, PendingAck>> {
+        self.pending.lock().expect("pending acks lock poisoned")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::sync::oneshot::error::TryRecvError;
+
+    use super::*;
+
+    fn ack(request_id: &str, result: Result<(), String>) -> SubscriptionAck {
+        SubscriptionAck {
+            request_id: request_id.to_string(),
+            result,
+            symbol: None,
+            rejected: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn acks_are_matched_by_request_id() {
+        let acks = PendingAcks::default();
+        let mut first = acks.register("1".to_string(), 1);
+        let mut second = acks.register("2".to_string(), 1);
+
+        acks.resolve(ack("2", Ok(())));
+        assert_eq!(first.try_recv(), Err(TryRecvError::Empty));
+        assert_eq!(second.try_recv(), Ok(Ok(())));
+
+        acks.resolve(ack("3", Ok(())));
+        assert_eq!(first.try_recv(), Err(TryRecvError::Empty));
+
+        acks.resolve(ack("1", Ok(())));
+        assert_eq!(first.try_recv(), Ok(Ok(())));
+    }
+
+    #[test]
+    fn every_expected_ack_is_awaited() {
+        let acks = PendingAcks::default();
+        let mut rx = acks.register("7".to_string(), 3);
+
+        acks.resolve(ack("7", Ok(())));
+        acks.resolve(ack("7", Ok(())));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+
+        acks.resolve(ack("7", Ok(())));
+        assert_eq!(rx.try_recv(), Ok(Ok(())));
+    }
+
+    #[test]
+    fn first_rejection_resolves_the_request() {
+        let acks = PendingAcks::default();
+        let mut rx = acks.register("7".to_string(), 3);
+
+        acks.resolve(ack("7", Ok(())));
+        acks.resolve(ack("7", Err("Currency pair not supported".to_string())));
+        assert_eq!(
+            rx.try_recv(),
+            Ok(Err("Currency pair not supported".to_string()))
+        );
+
+        // Later acks for the same id are ignored
+        acks.resolve(ack("7", Ok(())));
+        assert!(acks.lock().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn timed_out_request_ignores_a_late_ack() {
+        let acks = PendingAcks::default();
+        let mut rx = acks.register("7".to_string(), 1);
+
+        let waited = tokio::time::timeout(Duration::from_secs(5), &mut rx).await;
+        assert!(waited.is_err());
+        acks.remove("7");
+
+        acks.resolve(ack("7", Ok(())));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Closed));
+        assert!(acks.lock().is_empty());
+    }
+}
@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+use tokio_tungstenite::tungstenite::Message as TungsteniteMessage;
+
+/// Application level keepalive for exchanges that expect the client to ping.
+/// Pings are sent by the writer task, the connection is considered dead when no
+/// pong arrives within the timeout.
+#[derive(Debug, Clone)]
+pub struct Heartbeat {
+    /// Time between two pings
+    pub interval: Duration,
+    /// Time to wait for a pong before the connection is considered dead
+    pub timeout: Duration,
+    ping: &'static str,
+    is_pong: fn(&str) -> bool,
+}
+
+impl Heartbeat {
+    pub fn new(
+        interval: Duration,
+        timeout: Duration,
+        ping: &'static str,
+        is_pong: fn(&str) -> bool,
+    ) -> Self {
+        Self {
+            interval,
+            timeout,
+            ping,
+            is_pong,
+        }
+    }
+
+    /// Bybit drops connections that do not send `{"op":"ping"}` every 20 seconds
+    pub fn bybit() -> Self {
+        Self::new(
+            Duration::from_secs(20),
+            Duration::from_secs(10),
+            r#"{"op":"ping"}"#,
+            is_bybit_pong,
+        )
+    }
+
+    /// Kraken v2 `ping` method
+    pub fn kraken() -> Self {
+        Self::new(
+            Duration::from_secs(20),
+            Duration::from_secs(10),
+            r#"{"method":"ping"}"#,
+            is_kraken_pong,
+        )
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub(crate) fn ping(&self) -> TungsteniteMessage {
+        TungsteniteMessage::Text(self.ping.into())
+    }
+
+    /// Check if a text message is the pong answering our ping
+    pub(crate) fn is_pong(&self, text: &str) -> bool {
+        (self.is_pong)(text)
+    }
+}
+
+/// Fields used to recognise pong messages, every other field is ignored
+#[derive(Deserialize)]
+struct PongProbe {
+    op: Option<String>,
+    ret_msg: Option<String>,
+    method: Option<String>,
+}
+
+fn probe(text: &str) -> Option<PongProbe> {
+    // Avoid parsing every data message twice
+    if !text.contains("pong") {
+        return None;
+    }
+    serde_json::from_str(text).ok()
+}
+
+/// Linear and inverse reply `{"op":"ping","ret_msg":"pong",..}`, spot replies `{"op":"pong",..}`
+fn is_bybit_pong(text: &str) -> bool {
+    probe(text).is_some_and(|p| match p.op.as_deref() {
+        Some("pong") => true,
+        Some("ping") => p.ret_msg.as_deref() == Some("pong"),
+        _ => false,
+    })
+}
+
+/// Kraken replies `{"method":"pong","time_in":..,"time_out":..}`
+fn is_kraken_pong(text: &str) -> bool {
+    probe(text).is_some_and(|p| p.method.as_deref() == Some("pong"))
+}
@@ -0,0 +1,301 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use futures_util::stream;
+use tokio::sync::Notify;
+
+use crate::error::ExStreamError;
+use crate::transport::WsMsgStream;
+
+/// What to do with an inbound message when the buffer is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backpressure {
+    /// Stop reading from the socket until the consumer catches up, letting TCP apply backpressure
+    Block,
+    /// Discard the message that does not fit
+    DropNewest,
+    /// Discard the oldest buffered message to make room
+    DropOldest,
+    /// End the stream with `ExStreamError::Lagged`
+    Fail,
+}
+
+/// Bounded buffer between the connection task and the consumer stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferPolicy {
+    pub capacity: usize,
+    pub backpressure: Backpressure,
+}
+
+impl BufferPolicy {
+    pub fn new(capacity: usize, backpressure: Backpressure) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            backpressure,
+        }
+    }
+}
+
+/// Returned when the consumer can no longer receive messages
+#[derive(Debug)]
+pub(crate) struct InboundClosed;
+
+struct State<T> {
+    queue: VecDeque<Result<T, ExStreamError>>,
+    dropped: u64,
+    sender_closed: bool,
+    receiver_closed: bool,
+}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    /// Notified when a message is pushed or the sender goes away
+    readable: Notify,
+    /// Notified when a message is popped or the receiver goes away
+    writable: Notify,
+}
+
+impl<T> Shared<T> {
+    fn lock(&self) -> std::sync::MutexGuard<'_, State<T>> {
+        self.state.lock().expect("inbound buffer lock poisoned")
+    }
+}
+
+pub(crate) struct InboundSender<T> {
+    shared: Arc<Shared<T>>,
+    policy: Option<BufferPolicy>,
+}
+
+pub(crate) struct InboundReceiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+/// Create the inbound buffer, unbounded when no policy is given
+pub(crate) fn channel<T>(policy: Option<BufferPolicy>) -> (InboundSender<T>, InboundReceiver<T>) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            queue: VecDeque::new(),
+            dropped: 0,
+            sender_closed: false,
+            receiver_closed: false,
+        }),
+        readable: Notify::new(),
+        writable: Notify::new(),
+    });
+
+    let sender = InboundSender {
+        shared: shared.clone(),
+        policy,
+    };
+    (sender, InboundReceiver { shared })
+}
+
+/// Outcome of trying to push a message into the buffer
+enum Push<T> {
+    Queued,
+    Full(Result<T, ExStreamError>),
+    Closed,
+}
+
+impl<T> InboundSender<T> {
+    /// Queue a message for the consumer, applying the backpressure policy when the buffer is full
+    pub(crate) async fn send(
+        &self,
+        mut message: Result<T, ExStreamError>,
+    ) -> Result<(), InboundClosed> {
+        loop {
+            match self.try_push(message) {
+                Push::Queued => return Ok(()),
+                Push::Closed => return Err(InboundClosed),
+                Push::Full(returned) => message = returned,
+            }
+
+            // Blocking, wait for the consumer to make room
+            self.shared.writable.notified().await;
+        }
+    }
+
+    fn try_push(&self, message: Result<T, ExStreamError>) -> Push<T> {
+        let mut state = self.shared.lock();
+        if state.receiver_closed || state.sender_closed {
+            return Push::Closed;
+        }
+
+        let policy = match self.policy {
+            Some(policy) if state.queue.len() >= policy.capacity => policy,
+            _ => {
+                state.queue.push_back(message);
+                self.shared.readable.notify_one();
+                return Push::Queued;
+            }
+        };
+
+        match policy.backpressure {
+            Backpressure::Block => Push::Full(message),
+            Backpressure::DropNewest => {
+                state.dropped += 1;
+                tracing::warn!(
+                    "Inbound buffer full, dropped newest message ({} so far)",
+                    state.dropped
+                );
+                Push::Queued
+            }
+            Backpressure::DropOldest => {
+                state.queue.pop_front();
+                state.queue.push_back(message);
+                state.dropped += 1;
+                tracing::warn!(
+                    "Inbound buffer full, dropped oldest message ({} so far)",
+                    state.dropped
+                );
+                Push::Queued
+            }
+            Backpressure::Fail => {
+                state.dropped += 1;
+                tracing::error!("Inbound buffer full, failing the stream");
+                let dropped = state.dropped;
+                state
+                    .queue
+                    .push_back(Err(ExStreamError::Lagged { dropped }));
+                state.sender_closed = true;
+                self.shared.readable.notify_one();
+                Push::Closed
+            }
+        }
+    }
+}
+
+impl<T> Drop for InboundSender<T> {
+    fn drop(&mut self) {
+        self.shared.lock().sender_closed = true;
+        self.shared.readable.notify_one();
+    }
+}
+
+impl<T> InboundReceiver<T> {
+    /// Receive the next message, `None` once the sender is gone and the buffer is drained
+    pub(crate) async fn recv(&self) -> Option<Result<T, ExStreamError>> {
+        loop {
+            {
+                let mut state = self.shared.lock();
+                if let Some(message) = state.queue.pop_front() {
+                    self.shared.writable.notify_one();
+                    return Some(message);
+                }
+                if state.sender_closed {
+                    return None;
+                }
+            }
+
+            self.shared.readable.notified().await;
+        }
+    }
+}
+
+impl<T> Drop for InboundReceiver<T> {
+    fn drop(&mut self) {
+        self.shared.lock().receiver_closed = true;
+        self.shared.writable.notify_one();
+    }
+}
+
+impl<T: Send + 'static> InboundReceiver<T> {
+    pub(crate) fn into_stream(self) -> WsMsgStream<T> {
+        Box::pin(stream::unfold(self, |receiver| async move {
+            let message = receiver.recv().await?;
+            Some((message, receiver))
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    async fn drain(receiver: &InboundReceiver<u32>) -> Vec<u32> {
+        let mut received = Vec::new();
+        while let Ok(Some(message)) =
+            tokio::time::timeout(Duration::from_millis(10), receiver.recv()).await
+        {
+            received.push(message.unwrap());
+        }
+        received
+    }
+
+    #[tokio::test]
+    async fn unbounded_keeps_everything() {
+        let (sender, receiver) = channel::<u32>(None);
+        for n in 0..100 {
+            sender.send(Ok(n)).await.unwrap();
+        }
+        assert_eq!(drain(&receiver).await, (0..100).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn block_waits_for_the_consumer() {
+        let (sender, receiver) = channel::<u32>(Some(BufferPolicy::new(2, Backpressure::Block)));
+        sender.send(Ok(1)).await.unwrap();
+        sender.send(Ok(2)).await.unwrap();
+
+        let blocked = tokio::time::timeout(Duration::from_millis(50), sender.send(Ok(3))).await;
+        assert!(blocked.is_err(), "the third message must wait for room");
+
+        let sending = tokio::spawn(async move {
+            sender.send(Ok(3)).await.unwrap();
+            sender
+        });
+        assert_eq!(receiver.recv().await.unwrap().unwrap(), 1);
+        let _sender = sending.await.unwrap();
+        assert_eq!(drain(&receiver).await, vec![2, 3]);
+    }
+
+    #[tokio::test]
+    async fn block_stops_when_the_consumer_goes_away() {
+        let (sender, receiver) = channel::<u32>(Some(BufferPolicy::new(1, Backpressure::Block)));
+        sender.send(Ok(1)).await.unwrap();
+
+        let sending = tokio::spawn(async move { sender.send(Ok(2)).await });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        drop(receiver);
+        assert!(sending.await.unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn drop_newest_discards_the_incoming_message() {
+        let (sender, receiver) =
+            channel::<u32>(Some(BufferPolicy::new(2, Backpressure::DropNewest)));
+        for n in 1..=4 {
+            sender.send(Ok(n)).await.unwrap();
+        }
+        assert_eq!(drain(&receiver).await, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn drop_oldest_makes_room() {
+        let (sender, receiver) =
+            channel::<u32>(Some(BufferPolicy::new(2, Backpressure::DropOldest)));
+        for n in 1..=4 {
+            sender.send(Ok(n)).await.unwrap();
+        }
+        assert_eq!(drain(&receiver).await, vec![3, 4]);
+    }
+
+    #[tokio::test]
+    async fn fail_ends_the_stream_with_lagged() {
+        let (sender, receiver) = channel::<u32>(Some(BufferPolicy::new(2, Backpressure::Fail)));
+        sender.send(Ok(1)).await.unwrap();
+        sender.send(Ok(2)).await.unwrap();
+        assert!(sender.send(Ok(3)).await.is_err());
+        assert!(sender.send(Ok(4)).await.is_err());
+
+        assert_eq!(receiver.recv().await.unwrap().unwrap(), 1);
+        assert_eq!(receiver.recv().await.unwrap().unwrap(), 2);
+        assert!(matches!(
+            receiver.recv().await,
+            Some(Err(ExStreamError::Lagged { dropped: 1 }))
+        ));
+        assert!(receiver.recv().await.is_none());
+    }
+}
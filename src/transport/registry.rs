@@ -0,0 +1,331 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use crate::models::{
+    RequestKind, Subscription, SubscriptionAck, SubscriptionEntry, SubscriptionRequest,
+    SubscriptionStatus,
+};
+
+struct State<R> {
+    /// Active subscribe requests, replayed on reconnect
+    requests: Vec<R>,
+    /// Topics acknowledged by the exchange during the current session
+    confirmed: HashSet<Subscription>,
+}
+
+/// Active subscriptions of a connection, shared by the handler and the connection task
+#[derive(Clone)]
+pub(crate) struct Registry<R> {
+    state: Arc<Mutex<State<R>>>,
+}
+
+impl<R> std::fmt::Debug for Registry<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Registry").finish_non_exhaustive()
+    }
+}
+
+impl<R: SubscriptionRequest> Registry<R> {
+    pub(crate) fn new(initial: Vec<R>) -> Self {
+        let registry = Self {
+            state: Arc::new(Mutex::new(State {
+                requests: Vec::new(),
+                confirmed: HashSet::new(),
+            })),
+        };
+        for message in initial {
+            registry.track(message);
+        }
+        registry
+    }
+
+    /// Record a subscribe or unsubscribe request
+    pub(crate) fn track(&self, message: R) {
+        self.lock().track(message);
+    }
+
+    /// Send a request and record it once sent. Holding the lock meanwhile keeps the request
+    /// from slipping between a [`Registry::replay`] and the new session.
+    pub(crate) fn send_and_track<T, E>(
+        &self,
+        message: R,
+        send: impl FnOnce(&R) -> Result<T, E>,
+    ) -> Result<T, E> {
+        let mut state = self.lock();
+        let sent = send(&message)?;
+        state.track(message);
+        Ok(sent)
+    }
+
+    /// Stop tracking the topics of a request
+    pub(crate) fn untrack(&self, message: &R) {
+        self.lock().remove(message);
+    }
+
+    /// Apply an exchange ack, rejected topics are no longer tracked. When the exchange only
+    /// rejected some topics of the request, the others are confirmed.
+    pub(crate) fn acknowledge(&self, ack: &SubscriptionAck) {
+        let mut state = self.lock();
+        let Some(request) = state
+            .requests
+            .iter()
+            .find(|request| request.request_id().as_deref() == Some(ack.request_id.as_str()))
+        else {
+            return;
+        };
+
+        let topics = request
+            .topics()
+            .into_iter()
+            .filter(|topic| {
+                ack.symbol
+                    .as_ref()
+                    .is_none_or(|symbol| topic.symbol == *symbol)
+            })
+            .collect::<Vec<_>>();
+        match &ack.result {
+            Ok(()) => state.confirmed.extend(topics),
+            Err(reason) => {
+                let (rejected, accepted) = match ack.rejected.is_empty() {
+                    true => (topics, Vec::new()),
+                    false => topics
+                        .into_iter()
+                        .partition(|topic| ack.rejected.contains(topic)),
+                };
+                tracing::warn!("Subscription to {:?} rejected: {}", rejected, reason);
+                for removed in R::from_topics(RequestKind::Unsubscribe, &rejected, &[]) {
+                    state.remove(&removed);
+                }
+                state.confirmed.extend(accepted);
+            }
+        }
+    }
+
+    /// Replace the confirmed topics with the full list reported by the exchange
+    pub(crate) fn confirm_exact(&self, topics: Vec<Subscription>) {
+        self.lock().confirmed = topics.into_iter().collect();
+    }
+
+    /// Requests to send on a new session, every topic is pending until acknowledged again.
+    /// `discard` runs under the lock to drop the requests queued for the previous session,
+    /// which the replay already covers.
+    pub(crate) fn replay(&self, discard: impl FnOnce()) -> Vec<R> {
+        let mut state = self.lock();
+        state.confirmed.clear();
+        discard();
+        state.requests.clone()
+    }
+
+    /// Active subscribe requests
+    pub(crate) fn requests(&self) -> Vec<R> {
+        self.lock().requests.clone()
+    }
+
+    /// Active topics without duplicates, in subscription order
+    pub(crate) fn topics(&self) -> Vec<Subscription> {
+        let state = self.lock();
+        let mut seen = HashSet::new();
+        state
+            .requests
+            .iter()
+            .flat_map(SubscriptionRequest::topics)
+            .filter(|topic| seen.insert(topic.clone()))
+            .collect()
+    }
+
+    pub(crate) fn entries(&self) -> Vec<SubscriptionEntry> {
+        let topics = self.topics();
+        let state = self.lock();
+        topics
+            .into_iter()
+            .map(|subscription| {
+                let status = match state.confirmed.contains(&subscription) {
+                    true => SubscriptionStatus::Confirmed,
+                    false => SubscriptionStatus::Pending,
+                };
+                SubscriptionEntry {
+                    subscription,
+                    status,
+                }
+            })
+            .collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State<R>> {
+        self.state
+            .lock()
+            .expect("subscription registry lock poisoned")
+    }
+}
+
+impl<R: SubscriptionRequest> State<R> {
+    fn track(&mut self, message: R) {
+        match message.kind() {
+            RequestKind::Subscribe => {
+                if !message.is_empty() {
+                    self.requests.push(message);
+                }
+            }
+            RequestKind::Unsubscribe => self.remove(&message),
+        }
+    }
+
+    fn remove(&mut self, message: &R) {
+        for request in self.requests.iter_mut() {
+            request.remove_topics(message);
+        }
+        self.requests.retain(|request| !request.is_empty());
+        for topic in message.topics() {
+            self.confirmed.remove(&topic);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{BinanceRequest, KrakenChannel, KrakenParams, KrakenRequest, RequestKind};
+
+    fn topics(entries: Vec<SubscriptionEntry>) -> Vec<(String, SubscriptionStatus)> {
+        entries
+            .into_iter()
+            .map(|entry| (entry.subscription.symbol, entry.status))
+            .collect()
+    }
+
+    fn ack(request_id: &str) -> SubscriptionAck {
+        SubscriptionAck {
+            request_id: request_id.to_string(),
+            result: Ok(()),
+            symbol: None,
+            rejected: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn unsubscribe_removes_topics_from_the_replay() {
+        let registry = Registry::new(vec![
+            BinanceRequest::new_subscribe().with_trades(vec!["btcusdt", "ethusdt"]),
+        ]);
+        registry.track(BinanceRequest::new_subscribe().with_trade("solusdt"));
+
+        registry.track(BinanceRequest::new_unsubscribe().with_trades(vec!["btcusdt", "solusdt"]));
+        let replay = registry.replay(|| ());
+        assert_eq!(replay.len(), 1);
+        assert_eq!(replay[0].params, vec!["ethusdt@trade"]);
+
+        registry.track(BinanceRequest::new_unsubscribe().with_trade("ethusdt"));
+        assert!(registry.replay(|| ()).is_empty());
+    }
+
+    #[test]
+    fn unsubscribe_keeps_the_other_intervals_of_a_symbol() {
+        let ohlc = |kind, interval| {
+            let mut request = KrakenRequest::new(kind, KrakenParams::new(KrakenChannel::Ohlc));
+            request.set_interval(interval);
+            request.add_symbol("BTC/USD");
+            request
+        };
+        let registry = Registry::new(vec![ohlc(RequestKind::Subscribe, 1)]);
+        registry.track(ohlc(RequestKind::Subscribe, 5));
+
+        registry.track(ohlc(RequestKind::Unsubscribe, 5));
+        let topics = registry
+            .entries()
+            .into_iter()
+            .map(|entry| entry.subscription)
+            .collect::<Vec<_>>();
+        assert_eq!(topics.len(), 1);
+        assert_eq!(topics[0].symbol, "BTC/USD");
+        assert_eq!(topics[0].interval.as_deref(), Some("1"));
+    }
+
+    #[test]
+    fn confirmed_subscriptions_are_replayed_as_pending() {
+        let registry = Registry::new(vec![
+            BinanceRequest::new_subscribe()
+                .with_trade("btcusdt")
+                .with_id(1),
+        ]);
+        registry.acknowledge(&ack("1"));
+        assert_eq!(
+            topics(registry.entries()),
+            vec![("btcusdt".to_string(), SubscriptionStatus::Confirmed)]
+        );
+
+        let mut discarded = false;
+        let replay = registry.replay(|| discarded = true);
+        assert!(discarded);
+        assert_eq!(replay.len(), 1);
+        assert_eq!(replay[0].params, vec!["btcusdt@trade"]);
+        assert_eq!(
+            topics(registry.entries()),
+            vec![("btcusdt".to_string(), SubscriptionStatus::Pending)]
+        );
+
+        // The replayed request keeps its id, so the new ack confirms it again
+        registry.acknowledge(&ack("1"));
+        assert_eq!(
+            topics(registry.entries()),
+            vec![("btcusdt".to_string(), SubscriptionStatus::Confirmed)]
+        );
+    }
+
+    #[test]
+    fn rejected_topics_are_not_replayed() {
+        let registry = Registry::new(vec![
+            BinanceRequest::new_subscribe()
+                .with_trade("btcusdt")
+                .with_id(1),
+        ]);
+        registry.track(
+            BinanceRequest::new_subscribe()
+                .with_trade("nosuchpair")
+                .with_id(2),
+        );
+        registry.acknowledge(&SubscriptionAck {
+            request_id: "2".to_string(),
+            result: Err("Invalid symbol".to_string()),
+            symbol: None,
+            rejected: Vec::new(),
+        });
+
+        let replay = registry.replay(|| ());
+        assert_eq!(replay.len(), 1);
+        assert_eq!(replay[0].params, vec!["btcusdt@trade"]);
+    }
+
+    #[test]
+    fn partly_rejected_request_keeps_the_accepted_topics() {
+        let registry = Registry::new(Vec::new());
+        registry.track(
+            BinanceRequest::new_subscribe()
+                .with_trades(vec!["btcusdt", "nosuchpair"])
+                .with_id(2),
+        );
+        registry.acknowledge(&SubscriptionAck {
+            request_id: "2".to_string(),
+            result: Err("Invalid symbol".to_string()),
+            symbol: None,
+            rejected: BinanceRequest::new_subscribe()
+                .with_trade("nosuchpair")
+                .topics(),
+        });
+
+        assert_eq!(
+            topics(registry.entries()),
+            vec![("btcusdt".to_string(), SubscriptionStatus::Confirmed)]
+        );
+    }
+
+    #[test]
+    fn failed_send_is_not_tracked() {
+        let registry = Registry::<BinanceRequest>::new(Vec::new());
+        let sent = registry.send_and_track(
+            BinanceRequest::new_subscribe().with_trade("btcusdt"),
+            |_| Err::<(), _>("closed"),
+        );
+        assert_eq!(sent, Err("closed"));
+        assert!(registry.replay(|| ()).is_empty());
+    }
+}
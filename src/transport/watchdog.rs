@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// Detects feeds that went silent, e.g. half-open TCP connections that never deliver another frame.
+/// Any inbound frame counts as liveness, only market data messages count as symbol freshness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchdog {
+    /// Maximum time without any inbound frame
+    pub idle_timeout: Duration,
+    /// Maximum time without a data message for a symbol that already delivered data.
+    /// Quiet markets trigger reconnects too, so this should be generous for illiquid symbols.
+    pub symbol_timeout: Option<Duration>,
+}
+
+impl Watchdog {
+    pub fn new(idle_timeout: Duration) -> Self {
+        Self {
+            idle_timeout,
+            symbol_timeout: None,
+        }
+    }
+
+    pub fn with_symbol_timeout(mut self, timeout: Duration) -> Self {
+        self.symbol_timeout = Some(timeout);
+        self
+    }
+
+    /// How often the feed is checked
+    pub(crate) fn check_period(&self) -> Duration {
+        let shortest = match self.symbol_timeout {
+            Some(timeout) => timeout.min(self.idle_timeout),
+            None => self.idle_timeout,
+        };
+        (shortest / 4).max(Duration::from_millis(10))
+    }
+}
+
+/// A stale feed found by the watchdog
+#[derive(Debug)]
+pub(crate) struct Stale {
+    /// The silent symbol, `None` when the whole connection is silent
+    pub symbol: Option<String>,
+    pub idle: Duration,
+}
+
+/// Time of the last data message of every symbol, shared with the handler so
+/// that unsubscribed symbols stop being watched
+pub(crate) type SymbolActivity = Arc<Mutex<HashMap<String, Instant>>>;
+
+/// Activity of a single WebSocket session
+pub(crate) struct FeedMonitor {
+    config: Watchdog,
+    last_frame: Instant,
+    symbols: SymbolActivity,
+}
+
+impl FeedMonitor {
+    pub(crate) fn new(config: Watchdog, symbols: SymbolActivity) -> Self {
+        symbols
+            .lock()
+            .expect("symbol activity lock poisoned")
+            .clear();
+        Self {
+            config,
+            last_frame: Instant::now(),
+            symbols,
+        }
+    }
+
+    /// Record an inbound frame of any kind
+    pub(crate) fn frame(&mut self) {
+        self.last_frame = Instant::now();
+    }
+
+    /// Discount time spent waiting for the consumer to make room in the buffer,
+    /// the socket is not read meanwhile so the silence is not the feed's fault
+    pub(crate) fn pause(&mut self, blocked: Duration) {
+        let now = Instant::now();
+        self.last_frame = (self.last_frame + blocked).min(now);
+        let mut symbols = self.symbols.lock().expect("symbol activity lock poisoned");
+        for last in symbols.values_mut() {
+            *last = (*last + blocked).min(now);
+        }
+    }
+
+    /// Record a market data message for the symbol
+    pub(crate) fn data(&self, symbol: &str) {
+        let mut symbols = self.symbols.lock().expect("symbol activity lock poisoned");
+        match symbols.get_mut(symbol) {
+            Some(last) => *last = Instant::now(),
+            None => {
+                symbols.insert(symbol.to_string(), Instant::now());
+            }
+        }
+    }
+
+    pub(crate) fn check(&self) -> Option<Stale> {
+        let idle = self.last_frame.elapsed();
+        if idle >= self.config.idle_timeout {
+            return Some(Stale { symbol: None, idle });
+        }
+
+        let timeout = self.config.symbol_timeout?;
+        let symbols = self.symbols.lock().expect("symbol activity lock poisoned");
+        symbols
+            .iter()
+            .map(|(symbol, last)| (symbol, last.elapsed()))
+            .find(|(_, idle)| *idle >= timeout)
+            .map(|(symbol, idle)| Stale {
+                symbol: Some(symbol.clone()),
+                idle,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::time::advance;
+
+    use super::*;
+
+    const IDLE: Duration = Duration::from_secs(10);
+
+    fn monitor(config: Watchdog) -> FeedMonitor {
+        FeedMonitor::new(config, SymbolActivity::default())
+    }
+
+    #[test]
+    fn check_period_follows_the_shortest_timeout() {
+        assert_eq!(
+            Watchdog::new(IDLE).check_period(),
+            Duration::from_millis(2500)
+        );
+        let config = Watchdog::new(IDLE).with_symbol_timeout(Duration::from_secs(2));
+        assert_eq!(config.check_period(), Duration::from_millis(500));
+        let config = Watchdog::new(Duration::from_millis(20));
+        assert_eq!(config.check_period(), Duration::from_millis(10));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn silent_connection_is_stale_after_the_idle_timeout() {
+        let monitor = monitor(Watchdog::new(IDLE));
+
+        advance(IDLE - Duration::from_millis(1)).await;
+        assert!(monitor.check().is_none());
+
+        advance(Duration::from_millis(1)).await;
+        let stale = monitor.check().unwrap();
+        assert_eq!(stale.symbol, None);
+        assert_eq!(stale.idle, IDLE);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn any_frame_keeps_the_connection_alive() {
+        let mut monitor = monitor(Watchdog::new(IDLE));
+
+        for _ in 0..5 {
+            advance(IDLE / 2).await;
+            // Heartbeat pongs only count as frames
+            monitor.frame();
+            assert!(monitor.check().is_none());
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn heartbeat_frames_do_not_refresh_symbols() {
+        let timeout = Duration::from_secs(3);
+        let mut monitor = monitor(Watchdog::new(IDLE).with_symbol_timeout(timeout));
+        monitor.frame();
+        monitor.data("BTCUSDT");
+
+        advance(Duration::from_secs(2)).await;
+        monitor.frame();
+        monitor.data("ETHUSDT");
+        assert!(monitor.check().is_none());
+
+        advance(Duration::from_secs(1)).await;
+        monitor.frame();
+        let stale = monitor.check().unwrap();
+        assert_eq!(stale.symbol.as_deref(), Some("BTCUSDT"));
+        assert_eq!(stale.idle, timeout);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn symbols_without_data_are_not_watched() {
+        let monitor = monitor(Watchdog::new(IDLE).with_symbol_timeout(Duration::from_secs(1)));
+        advance(Duration::from_secs(5)).await;
+        assert!(monitor.check().is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn new_session_forgets_previous_symbols() {
+        let symbols = SymbolActivity::default();
+        let config = Watchdog::new(IDLE).with_symbol_timeout(Duration::from_secs(1));
+        FeedMonitor::new(config, symbols.clone()).data("BTCUSDT");
+
+        let monitor = FeedMonitor::new(config, symbols);
+        advance(Duration::from_secs(5)).await;
+        assert!(monitor.check().is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn blocked_time_is_not_idle_time() {
+        let mut monitor = monitor(Watchdog::new(IDLE).with_symbol_timeout(IDLE));
+        monitor.data("BTCUSDT");
+
+        advance(Duration::from_secs(8)).await;
+        monitor.pause(Duration::from_secs(6));
+        advance(Duration::from_secs(7)).await;
+        assert!(monitor.check().is_none());
+
+        advance(Duration::from_secs(1)).await;
+        assert!(monitor.check().is_some());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pause_never_moves_activity_into_the_future() {
+        let mut monitor = monitor(Watchdog::new(IDLE));
+
+        advance(Duration::from_secs(1)).await;
+        monitor.pause(Duration::from_secs(60));
+        advance(IDLE).await;
+        assert!(monitor.check().is_some());
+    }
+}
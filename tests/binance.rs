@@ -0,0 +1,227 @@
+use exstreamer::models::normalized::{BookUpdate, Exchange, MarketData, Normalize, PriceLevel};
+use exstreamer::models::{
+    BinanceMarket, BinanceMessage, BinanceRequest, BinanceStream, ExchangeMessage, Instrument,
+    InstrumentKind, NumDecimal, SubscriptionRequest,
+};
+
+const AGG_TRADE: &str = r#"{"e":"aggTrade","E":1672515782136,"s":"BNBBTC","a":12345,"p":"0.001","q":"100","f":100,"l":105,"T":1672515782136,"m":true,"M":true}"#;
+const BOOK_TICKER: &str = r#"{"u":400900217,"s":"BNBUSDT","b":"25.35190000","B":"31.21000000","a":"25.36520000","A":"40.66000000"}"#;
+const DEPTH_UPDATE: &str = r#"{"e":"depthUpdate","E":1672515782136,"s":"BNBBTC","U":157,"u":160,"b":[["0.0024","10"]],"a":[["0.0026","0"]]}"#;
+const KLINE: &str = r#"{"e":"kline","E":1672515782136,"s":"BNBBTC","k":{"t":1672515780000,"T":1672515839999,"s":"BNBBTC","i":"1m","f":100,"L":200,"o":"0.0010","c":"0.0020","h":"0.0025","l":"0.0015","v":"1000","n":100,"x":false,"q":"1.0000","V":"500","Q":"0.500","B":"123456"}}"#;
+const MINI_TICKER: &str = r#"{"e":"24hrMiniTicker","E":1672515782136,"s":"BNBBTC","c":"0.0025","o":"0.0010","h":"0.0025","l":"0.0010","v":"10000","q":"18"}"#;
+const COMBINED_DEPTH: &str = r#"{"stream":"bnbbtc@depth5@100ms","data":{"lastUpdateId":160,"bids":[["0.0024","10"]],"asks":[["0.0026","100"]]}}"#;
+const FUTURES_DEPTH5: &str = include_str!("fixtures/binance/futures_depth5.json");
+
+/// Same number as the normalized one, whether the `decimal` feature is enabled or not
+fn num(value: &str) -> NumDecimal {
+    value.parse().unwrap()
+}
+
+fn parse(json: &str) -> BinanceMessage {
+    serde_json::from_str(json).unwrap()
+}
+
+fn trade(symbol: &str) -> BinanceMessage {
+    parse(&format!(
+        r#"{{"e":"trade","E":1672515782136,"s":"{symbol}","t":12345,"p":"0.001","q":"100","T":1672515782136,"m":true}}"#
+    ))
+}
+
+#[test]
+fn coin_margined_perpetual_symbols_round_trip() {
+    let instrument = Instrument::parse(Exchange::Binance, "BTCUSD_PERP", InstrumentKind::Spot);
+    assert_eq!(instrument, Some(Instrument::perpetual("BTC", "USD")));
+    assert_eq!(instrument.unwrap().symbol(Exchange::Binance), "BTCUSD_PERP");
+
+    let instrument = Instrument::parse(Exchange::Binance, "ethusd_perp", InstrumentKind::Spot);
+    assert_eq!(instrument, Some(Instrument::perpetual("ETH", "USD")));
+
+    for instrument in [
+        Instrument::perpetual("BTC", "USDT"),
+        Instrument::spot("BTC", "USDT"),
+        Instrument::spot("BTC", "USD"),
+    ] {
+        let symbol = instrument.symbol(Exchange::Binance);
+        assert!(!symbol.ends_with("_PERP"));
+        assert_eq!(
+            Instrument::parse(Exchange::Binance, &symbol, instrument.kind),
+            Some(instrument)
+        );
+    }
+}
+
+#[test]
+fn instrument_kind_follows_the_market() {
+    let message = trade("BTCUSDT");
+    assert_eq!(
+        message.instrument_in(BinanceMarket::Spot),
+        Some(Instrument::spot("BTC", "USDT"))
+    );
+    assert_eq!(
+        message.instrument_in(BinanceMarket::UsdM),
+        Some(Instrument::perpetual("BTC", "USDT"))
+    );
+
+    let message = trade("BTCUSD_PERP");
+    assert_eq!(
+        message.instrument(),
+        Some(Instrument::perpetual("BTC", "USD"))
+    );
+    assert_eq!(
+        message.instrument_in(BinanceMarket::CoinM),
+        Some(Instrument::perpetual("BTC", "USD"))
+    );
+
+    // Dated futures have no instrument
+    assert_eq!(
+        trade("BTCUSD_250627").instrument_in(BinanceMarket::CoinM),
+        None
+    );
+    assert_eq!(
+        trade("BTCUSDT_250627").instrument_in(BinanceMarket::UsdM),
+        None
+    );
+}
+
+#[test]
+fn streams_are_named_after_the_symbol() {
+    let request = BinanceRequest::new_subscribe()
+        .with_stream("BNBBTC", BinanceStream::PartialDepth(5))
+        .with_stream("bnbbtc", BinanceStream::DiffDepth)
+        .with_stream("bnbbtc", BinanceStream::Kline("1m".to_string()))
+        .with_streams(vec!["bnbbtc", "ethbtc"], BinanceStream::BookTicker);
+    assert_eq!(
+        request.params,
+        [
+            "bnbbtc@depth5@100ms",
+            "bnbbtc@depth@100ms",
+            "bnbbtc@kline_1m",
+            "bnbbtc@bookTicker",
+            "ethbtc@bookTicker",
+        ]
+    );
+
+    // The topic keeps the whole stream name after the symbol
+    let topics = request.topics();
+    assert_eq!(topics[0].channel, "depth5@100ms");
+    assert_eq!(topics[0].symbol, "bnbbtc");
+}
+
+#[test]
+fn messages_are_told_apart_by_their_event_type() {
+    let BinanceMessage::AggTrade(trade) = parse(AGG_TRADE) else {
+        panic!("not an aggregate trade");
+    };
+    assert_eq!((trade.first_trade_id, trade.last_trade_id), (100, 105));
+
+    let BinanceMessage::DepthUpdate(update) = parse(DEPTH_UPDATE) else {
+        panic!("not a depth update");
+    };
+    assert_eq!((update.first_update_id, update.final_update_id), (157, 160));
+
+    let BinanceMessage::Kline(kline) = parse(KLINE) else {
+        panic!("not a kline");
+    };
+    assert_eq!(kline.kline.interval, "1m");
+    assert!(!kline.kline.is_closed);
+
+    assert!(matches!(parse(MINI_TICKER), BinanceMessage::MiniTicker(_)));
+
+    let unknown = AGG_TRADE.replace("aggTrade", "somethingElse");
+    assert!(serde_json::from_str::<BinanceMessage>(&unknown).is_err());
+}
+
+#[test]
+fn messages_without_event_type_are_told_apart_by_their_fields() {
+    let BinanceMessage::BookTicker(ticker) = parse(BOOK_TICKER) else {
+        panic!("not a book ticker");
+    };
+    assert_eq!(ticker.update_id, 400900217);
+    assert_eq!(ticker.symbol, "BNBUSDT");
+
+    assert!(matches!(
+        parse(r#"{"result":null,"id":1}"#),
+        BinanceMessage::SubscriptionAck(_)
+    ));
+    assert!(matches!(
+        parse(r#"{"code":2,"msg":"Invalid request","id":1}"#),
+        BinanceMessage::Error(_)
+    ));
+}
+
+#[test]
+fn combined_messages_carry_their_stream() {
+    let message = parse(COMBINED_DEPTH);
+    assert_eq!(message.stream(), Some("bnbbtc@depth5@100ms"));
+    assert_eq!(message.symbol(), Some("BNBBTC"));
+    let BinanceMessage::PartialDepth(depth) = message.data() else {
+        panic!("not a partial depth");
+    };
+    assert_eq!(depth.last_update_id, 160);
+
+    // Partial books only get their symbol from the stream name
+    let data = message.normalize().unwrap();
+    let [MarketData::BookUpdate(book)] = data.as_slice() else {
+        panic!("{data:?}");
+    };
+    assert_eq!(
+        *book,
+        BookUpdate {
+            symbol: "BNBBTC".to_string(),
+            is_snapshot: true,
+            bids: vec![PriceLevel {
+                price: num("0.0024"),
+                size: num("10"),
+            }],
+            asks: vec![PriceLevel {
+                price: num("0.0026"),
+                size: num("100"),
+            }],
+            sequence: Some(160),
+            ..book.clone()
+        }
+    );
+
+    // The raw endpoint sends the same book without the stream name
+    let raw = parse(r#"{"lastUpdateId":160,"bids":[["0.0024","10"]],"asks":[["0.0026","100"]]}"#);
+    assert_eq!(raw.symbol(), None);
+    assert!(raw.normalize().is_err());
+}
+
+#[test]
+fn futures_partial_books_are_snapshots() {
+    let message = parse(FUTURES_DEPTH5);
+    let BinanceMessage::PartialDepth(depth) = message.data() else {
+        panic!("not a partial depth");
+    };
+    assert_eq!(depth.last_update_id, 390497878);
+
+    let data = message.normalize().unwrap();
+    let [MarketData::BookUpdate(book)] = data.as_slice() else {
+        panic!("{data:?}");
+    };
+    assert_eq!(
+        *book,
+        BookUpdate {
+            symbol: "BTCUSDT".to_string(),
+            is_snapshot: true,
+            bids: vec![PriceLevel {
+                price: num("7403.89"),
+                size: num("0.002"),
+            }],
+            asks: vec![PriceLevel {
+                price: num("7405.96"),
+                size: num("3.340"),
+            }],
+            sequence: Some(390497878),
+            ..book.clone()
+        }
+    );
+
+    // The diff stream of the same symbol stays a depth update
+    let diff = FUTURES_DEPTH5.replace("@depth5", "@depth@100ms");
+    assert!(matches!(
+        parse(&diff).data(),
+        BinanceMessage::DepthUpdate(_)
+    ));
+}
@@ -0,0 +1,510 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use exstreamer::StreamBuilder;
+use exstreamer::error::ExStreamError;
+use exstreamer::models::{
+    BinanceDepthUpdate, BinanceMessage, BinancePartialDepth, BinanceStream, NumDecimal,
+};
+use exstreamer::orderbook::{
+    BinanceOrderBooks, BinanceRestSnapshot, BinanceSnapshotFetcher, BookEvent,
+};
+use exstreamer::transport::{ReconnectPolicy, WsMsgStream};
+use futures_util::{SinkExt as _, StreamExt as _};
+use serde_json::json;
+use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+
+const SYMBOL: &str = "BNBBTC";
+
+fn num(value: &str) -> NumDecimal {
+    value.parse().unwrap()
+}
+
+fn snapshot_json(last_update_id: u64, bid: &str, ask: &str) -> serde_json::Value {
+    json!({
+        "lastUpdateId": last_update_id,
+        "bids": [[bid, "1.0"]],
+        "asks": [[ask, "1.0"]],
+    })
+}
+
+fn diff_json(first: u64, last: u64, bids: &[[&str; 2]], asks: &[[&str; 2]]) -> serde_json::Value {
+    json!({
+        "e": "depthUpdate",
+        "E": 1700000000000u64,
+        "s": SYMBOL,
+        "U": first,
+        "u": last,
+        "b": bids,
+        "a": asks,
+    })
+}
+
+fn snapshot(last_update_id: u64, bid: &str, ask: &str) -> BinancePartialDepth {
+    serde_json::from_value(snapshot_json(last_update_id, bid, ask)).unwrap()
+}
+
+fn diff(first: u64, last: u64, bids: &[[&str; 2]], asks: &[[&str; 2]]) -> BinanceDepthUpdate {
+    serde_json::from_value(diff_json(first, last, bids, asks)).unwrap()
+}
+
+#[test]
+fn diffs_are_buffered_until_the_snapshot() {
+    let mut books = BinanceOrderBooks::new();
+
+    let stale = diff(95, 99, &[["10.0", "5.0"]], &[]);
+    let straddling = diff(100, 102, &[["10.0", "2.0"]], &[]);
+    let next = diff(103, 104, &[], &[["11.5", "3.0"]]);
+    for update in [&stale, &straddling, &next] {
+        assert_eq!(books.apply_update(update).unwrap(), None);
+    }
+    assert!(!books.is_synced(SYMBOL));
+
+    let event = books
+        .apply_snapshot(SYMBOL, &snapshot(100, "10.0", "11.0"))
+        .unwrap();
+    assert_eq!(
+        event,
+        Some(BookEvent::Snapshot {
+            symbol: SYMBOL.to_string()
+        })
+    );
+    assert!(books.is_synced(SYMBOL));
+    assert_eq!(books.update_id(SYMBOL), Some(104));
+
+    let (bids, asks) = books.top(SYMBOL, 10).unwrap();
+    assert_eq!(bids.len(), 1);
+    assert_eq!(bids[0].price, num("10.0"));
+    assert_eq!(bids[0].size, num("2.0"));
+    assert_eq!(asks.len(), 2);
+    assert_eq!(asks[1].price, num("11.5"));
+    assert_eq!(asks[1].size, num("3.0"));
+}
+
+#[test]
+fn snapshot_older_than_the_buffer_is_rejected() {
+    let mut books = BinanceOrderBooks::new();
+    books.apply_update(&diff(120, 125, &[], &[])).unwrap();
+
+    let event = books
+        .apply_snapshot(SYMBOL, &snapshot(100, "10.0", "11.0"))
+        .unwrap();
+    assert_eq!(event, None);
+    assert!(!books.is_synced(SYMBOL));
+
+    let event = books
+        .apply_snapshot(SYMBOL, &snapshot(122, "10.0", "11.0"))
+        .unwrap();
+    assert!(matches!(event, Some(BookEvent::Snapshot { .. })));
+    assert_eq!(books.update_id(SYMBOL), Some(125));
+}
+
+#[test]
+fn gap_drops_the_book() {
+    let mut books = BinanceOrderBooks::new();
+    books.apply_update(&diff(100, 101, &[], &[])).unwrap();
+    books
+        .apply_snapshot(SYMBOL, &snapshot(100, "10.0", "11.0"))
+        .unwrap();
+
+    let removal = diff(102, 103, &[["10.0", "0"]], &[]);
+    assert_eq!(
+        books.apply_update(&removal).unwrap(),
+        Some(BookEvent::Updated {
+            symbol: SYMBOL.to_string()
+        })
+    );
+    assert_eq!(books.best_bid(SYMBOL), None);
+
+    // Already applied
+    assert_eq!(books.apply_update(&removal).unwrap(), None);
+
+    let event = books.apply_update(&diff(106, 107, &[], &[])).unwrap();
+    assert_eq!(
+        event,
+        Some(BookEvent::OutOfSync {
+            symbol: SYMBOL.to_string(),
+            expected: 104,
+            received: 106,
+        })
+    );
+    assert!(!books.is_synced(SYMBOL));
+    assert!(books.book(SYMBOL).unwrap().is_empty());
+}
+
+#[test]
+fn futures_diffs_follow_the_previous_update_id() {
+    let futures_diff = |first: u64, last: u64, previous: u64| {
+        let mut json = diff_json(first, last, &[], &[["11.0", "2.0"]]);
+        json["pu"] = json!(previous);
+        serde_json::from_value::<BinanceDepthUpdate>(json).unwrap()
+    };
+    let mut books = BinanceOrderBooks::new();
+
+    // Futures update ids are not consecutive
+    books.apply_update(&futures_diff(95, 98, 90)).unwrap();
+    books.apply_update(&futures_diff(105, 110, 98)).unwrap();
+    let event = books
+        .apply_snapshot(SYMBOL, &snapshot(100, "10.0", "11.0"))
+        .unwrap();
+    assert!(matches!(event, Some(BookEvent::Snapshot { .. })));
+    assert_eq!(books.update_id(SYMBOL), Some(110));
+
+    let event = books.apply_update(&futures_diff(120, 125, 110)).unwrap();
+    assert!(matches!(event, Some(BookEvent::Updated { .. })));
+
+    let event = books.apply_update(&futures_diff(140, 145, 130)).unwrap();
+    assert_eq!(
+        event,
+        Some(BookEvent::OutOfSync {
+            symbol: SYMBOL.to_string(),
+            expected: 126,
+            received: 131,
+        })
+    );
+}
+
+/// Serve the snapshots in order over HTTP, recording the request lines
+async fn snapshot_server(snapshots: Vec<serde_json::Value>) -> (String, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let recorded = requests.clone();
+
+    tokio::spawn(async move {
+        for snapshot in snapshots {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let read = socket.read(&mut buffer).await.unwrap();
+                request.extend_from_slice(&buffer[..read]);
+            }
+            let request = String::from_utf8(request).unwrap();
+            recorded
+                .lock()
+                .unwrap()
+                .push(request.lines().next().unwrap().to_string());
+
+            let body = snapshot.to_string();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+
+    (format!("http://{address}"), requests)
+}
+
+/// Acknowledge the subscription, then send the diffs, pausing before the late ones
+async fn depth_server(diffs: Vec<serde_json::Value>, late: Vec<serde_json::Value>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+        let request = loop {
+            if let Some(Ok(Message::Text(text))) = ws.next().await {
+                break serde_json::from_str::<serde_json::Value>(&text).unwrap();
+            }
+        };
+        assert_eq!(request["params"], json!(["bnbbtc@depth@100ms"]));
+        let ack = json!({"result": null, "id": request["id"]});
+        ws.send(Message::text(ack.to_string())).await.unwrap();
+
+        for diff in diffs {
+            ws.send(Message::text(diff.to_string())).await.unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        for diff in late {
+            ws.send(Message::text(diff.to_string())).await.unwrap();
+        }
+        // Keep the connection open until the client goes away
+        while ws.next().await.is_some() {}
+    });
+
+    format!("ws://{address}")
+}
+
+#[tokio::test]
+async fn track_resyncs_after_a_gap() {
+    let (rest_endpoint, requests) = snapshot_server(vec![
+        snapshot_json(100, "10.0", "11.0"),
+        snapshot_json(110, "9.0", "12.0"),
+    ])
+    .await;
+    let ws_endpoint = depth_server(
+        vec![
+            diff_json(95, 99, &[["10.0", "5.0"]], &[]),
+            diff_json(100, 102, &[["10.0", "2.0"]], &[]),
+            diff_json(103, 104, &[], &[["11.5", "3.0"]]),
+        ],
+        vec![
+            diff_json(110, 111, &[["9.0", "4.0"]], &[]),
+            diff_json(112, 112, &[], &[["12.0", "0"]]),
+        ],
+    )
+    .await;
+
+    let (stream, _handler) = StreamBuilder::binance()
+        .with_stream(SYMBOL, BinanceStream::DiffDepth)
+        .with_endpoint(ws_endpoint)
+        .connect()
+        .await
+        .unwrap();
+    let (books, mut events) = BinanceOrderBooks::new()
+        .with_limit(50)
+        .track(stream, BinanceRestSnapshot::new(rest_endpoint));
+
+    let mut next_event = async || {
+        tokio::time::timeout(Duration::from_secs(5), events.next())
+            .await
+            .expect("no book event")
+            .expect("book events ended")
+            .unwrap()
+    };
+
+    // Diffs arriving before the snapshot are applied along with it
+    let mut event = next_event().await;
+    assert_eq!(
+        event,
+        BookEvent::Snapshot {
+            symbol: SYMBOL.to_string()
+        }
+    );
+    while books.read().unwrap().update_id(SYMBOL) != Some(104) {
+        event = next_event().await;
+        assert!(matches!(event, BookEvent::Updated { .. }));
+    }
+    assert_eq!(
+        books.read().unwrap().best_bid(SYMBOL).unwrap().size,
+        num("2.0")
+    );
+
+    assert_eq!(
+        next_event().await,
+        BookEvent::OutOfSync {
+            symbol: SYMBOL.to_string(),
+            expected: 105,
+            received: 110,
+        }
+    );
+    assert!(matches!(next_event().await, BookEvent::Snapshot { .. }));
+    if books.read().unwrap().update_id(SYMBOL) != Some(112) {
+        assert!(matches!(next_event().await, BookEvent::Updated { .. }));
+    }
+
+    let books = books.read().unwrap();
+    assert!(books.is_synced(SYMBOL));
+    let bid = books.best_bid(SYMBOL).unwrap();
+    assert_eq!(bid.price, num("9.0"));
+    assert_eq!(bid.size, num("4.0"));
+    assert_eq!(books.best_ask(SYMBOL), None);
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert!(requests[0].starts_with("GET /api/v3/depth?symbol=BNBBTC&limit=50 "));
+}
+
+/// Answer the fetches with the snapshots in order, `None` failing the fetch, recording when
+/// each fetch was made
+#[derive(Default)]
+struct MockFetcher {
+    snapshots: Mutex<VecDeque<Option<BinancePartialDepth>>>,
+    fetches: Arc<Mutex<Vec<tokio::time::Instant>>>,
+}
+
+impl MockFetcher {
+    fn new(snapshots: Vec<Option<BinancePartialDepth>>) -> Self {
+        Self {
+            snapshots: Mutex::new(snapshots.into()),
+            fetches: Arc::default(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl BinanceSnapshotFetcher for MockFetcher {
+    async fn fetch(
+        &self,
+        _symbol: &str,
+        _limit: u64,
+    ) -> Result<BinancePartialDepth, ExStreamError> {
+        self.fetches
+            .lock()
+            .unwrap()
+            .push(tokio::time::Instant::now());
+        match self.snapshots.lock().unwrap().pop_front().flatten() {
+            Some(snapshot) => Ok(snapshot),
+            None => Err(ExStreamError::RestError(
+                "503 Service Unavailable".to_string(),
+            )),
+        }
+    }
+}
+
+/// Diffs of a stream that stays open after them
+fn diff_stream(diffs: Vec<serde_json::Value>) -> WsMsgStream<BinanceMessage> {
+    let messages = diffs
+        .into_iter()
+        .map(|diff| Ok(serde_json::from_value::<BinanceMessage>(diff).unwrap()));
+    Box::pin(futures_util::stream::iter(messages).chain(futures_util::stream::pending()))
+}
+
+fn snapshot_retry(max_attempts: u32) -> ReconnectPolicy {
+    ReconnectPolicy::default()
+        .with_initial_backoff(Duration::from_millis(100))
+        .with_multiplier(2.0)
+        .with_jitter(0.0)
+        .with_max_attempts(Some(max_attempts))
+}
+
+fn gaps(fetches: &[tokio::time::Instant]) -> Vec<Duration> {
+    fetches.windows(2).map(|pair| pair[1] - pair[0]).collect()
+}
+
+#[tokio::test(start_paused = true)]
+async fn failed_snapshots_are_retried_with_backoff() {
+    let fetcher = MockFetcher::new(vec![None, None, None, Some(snapshot(100, "10.0", "11.0"))]);
+    let fetches = fetcher.fetches.clone();
+    let stream = diff_stream(vec![diff_json(100, 102, &[["10.0", "2.0"]], &[])]);
+
+    let (books, mut events) = BinanceOrderBooks::new()
+        .with_snapshot_retry(snapshot_retry(5))
+        .track(stream, fetcher);
+
+    for _ in 0..3 {
+        let event = events.next().await.unwrap();
+        assert!(
+            matches!(event, Err(ExStreamError::RestError(_))),
+            "{event:?}"
+        );
+    }
+    assert!(matches!(
+        events.next().await.unwrap(),
+        Ok(BookEvent::Snapshot { .. })
+    ));
+    assert!(books.read().unwrap().is_synced(SYMBOL));
+    assert_eq!(
+        gaps(&fetches.lock().unwrap()),
+        [100, 200, 400].map(Duration::from_millis)
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn stale_snapshots_are_retried_with_backoff() {
+    let fetcher = MockFetcher::new(vec![
+        Some(snapshot(50, "10.0", "11.0")),
+        Some(snapshot(60, "10.0", "11.0")),
+        Some(snapshot(101, "10.0", "11.0")),
+    ]);
+    let fetches = fetcher.fetches.clone();
+    let stream = diff_stream(vec![diff_json(100, 102, &[["10.0", "2.0"]], &[])]);
+
+    let (_books, mut events) = BinanceOrderBooks::new()
+        .with_snapshot_retry(snapshot_retry(5))
+        .track(stream, fetcher);
+
+    // Snapshots older than the diffs are not errors
+    assert!(matches!(
+        events.next().await.unwrap(),
+        Ok(BookEvent::Snapshot { .. })
+    ));
+    assert_eq!(
+        gaps(&fetches.lock().unwrap()),
+        [100, 200].map(Duration::from_millis)
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn book_is_given_up_when_the_retries_are_exhausted() {
+    let fetcher = MockFetcher::new(Vec::new());
+    let fetches = fetcher.fetches.clone();
+    let stream = diff_stream(vec![diff_json(100, 102, &[["10.0", "2.0"]], &[])]);
+
+    let (books, mut events) = BinanceOrderBooks::new()
+        .with_snapshot_retry(snapshot_retry(2))
+        .track(stream, fetcher);
+
+    // The first fetch and two retries
+    for _ in 0..3 {
+        let event = events.next().await.unwrap();
+        assert!(
+            matches!(event, Err(ExStreamError::RestError(_))),
+            "{event:?}"
+        );
+    }
+    let event = events.next().await.unwrap();
+    assert!(
+        matches!(
+            &event,
+            Err(ExStreamError::SnapshotFailed { symbol, attempts: 3 }) if symbol == SYMBOL
+        ),
+        "{event:?}"
+    );
+
+    tokio::time::sleep(Duration::from_secs(60)).await;
+    assert_eq!(fetches.lock().unwrap().len(), 3);
+    assert!(!books.read().unwrap().is_synced(SYMBOL));
+}
+
+#[tokio::test(start_paused = true)]
+async fn given_up_book_is_fetched_again_after_the_cooldown() {
+    let fetcher = MockFetcher::new(vec![None, None, Some(snapshot(104, "10.0", "11.0"))]);
+    let fetches = fetcher.fetches.clone();
+    let (diffs_tx, diffs_rx) = tokio::sync::mpsc::unbounded_channel();
+    let stream: WsMsgStream<BinanceMessage> = Box::pin(
+        tokio_stream::wrappers::UnboundedReceiverStream::new(diffs_rx),
+    );
+    let send = |first, last| {
+        let diff = diff_json(first, last, &[["10.0", "2.0"]], &[]);
+        diffs_tx
+            .send(Ok(serde_json::from_value(diff).unwrap()))
+            .unwrap();
+    };
+
+    let (books, mut events) = BinanceOrderBooks::new()
+        .with_snapshot_retry(snapshot_retry(1))
+        .with_failure_cooldown(Duration::from_secs(30))
+        .track(stream, fetcher);
+
+    send(100, 102);
+    for _ in 0..2 {
+        let event = events.next().await.unwrap();
+        assert!(
+            matches!(event, Err(ExStreamError::RestError(_))),
+            "{event:?}"
+        );
+    }
+    let event = events.next().await.unwrap();
+    assert!(
+        matches!(
+            event,
+            Err(ExStreamError::SnapshotFailed { attempts: 2, .. })
+        ),
+        "{event:?}"
+    );
+
+    // Diffs are ignored during the cooldown
+    send(103, 103);
+    tokio::time::sleep(Duration::from_secs(10)).await;
+    assert_eq!(fetches.lock().unwrap().len(), 2);
+    assert!(!books.read().unwrap().is_synced(SYMBOL));
+
+    // The first diff after it fetches a new snapshot
+    tokio::time::sleep(Duration::from_secs(20)).await;
+    send(104, 105);
+    assert!(matches!(
+        events.next().await.unwrap(),
+        Ok(BookEvent::Snapshot { .. })
+    ));
+    assert_eq!(fetches.lock().unwrap().len(), 3);
+    assert_eq!(books.read().unwrap().update_id(SYMBOL), Some(105));
+}
@@ -0,0 +1,403 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use exstreamer::StreamBuilder;
+use exstreamer::builders::BinanceListenKeyRest;
+use exstreamer::models::{
+    BinanceExecutionType, BinanceOrderSide, BinanceOrderStatus, BinanceUserDataMessage, StrDecimal,
+};
+use exstreamer::transport::{Backpressure, ReconnectPolicy};
+use futures_util::{SinkExt as _, StreamExt as _};
+use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::handshake::server::{
+    Callback, ErrorResponse, Request, Response,
+};
+
+const EXECUTION_REPORT: &str = include_str!("fixtures/binance/execution_report.json");
+const ACCOUNT_POSITION: &str = include_str!("fixtures/binance/account_position.json");
+const BALANCE_UPDATE: &str = include_str!("fixtures/binance/balance_update.json");
+
+const API_KEY: &str = "test-api-key";
+
+fn dec(value: &str) -> StrDecimal {
+    value.parse().unwrap()
+}
+
+fn parse(json: &str) -> BinanceUserDataMessage {
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+fn execution_report() {
+    let BinanceUserDataMessage::ExecutionReport(report) = parse(EXECUTION_REPORT) else {
+        panic!("expected an execution report");
+    };
+
+    assert_eq!(report.symbol, "ETHBTC");
+    assert_eq!(report.client_order_id, "mUvoqJxFIILMdfAW5iGSOW");
+    assert_eq!(report.side, BinanceOrderSide::Buy);
+    assert_eq!(report.order_type, "LIMIT");
+    assert_eq!(report.execution_type, BinanceExecutionType::Trade);
+    assert_eq!(report.order_status, BinanceOrderStatus::PartiallyFilled);
+    assert_eq!(report.order_id, 4293153);
+    assert_eq!(report.trade_id, 284210);
+    assert_eq!(report.price, dec("0.10264410"));
+    assert_eq!(report.last_executed_quantity, dec("0.25000000"));
+    assert_eq!(report.last_executed_price, dec("0.10264400"));
+    assert_eq!(report.commission_asset.as_deref(), Some("BNB"));
+    assert!(report.is_maker);
+}
+
+#[test]
+fn account_position() {
+    let BinanceUserDataMessage::AccountPosition(position) = parse(ACCOUNT_POSITION) else {
+        panic!("expected an account position");
+    };
+
+    assert_eq!(position.last_update_time, 1564034571073);
+    assert_eq!(position.balances.len(), 2);
+    assert_eq!(position.balances[1].asset, "BTC");
+    assert_eq!(position.balances[1].free, dec("0.74335900"));
+    assert_eq!(position.balances[1].locked, dec("0.02566100"));
+}
+
+#[test]
+fn balance_update() {
+    let BinanceUserDataMessage::BalanceUpdate(update) = parse(BALANCE_UPDATE) else {
+        panic!("expected a balance update");
+    };
+
+    assert_eq!(update.asset, "BTC");
+    assert_eq!(update.delta, dec("100.00000000"));
+    assert_eq!(update.clear_time, 1573200697068);
+}
+
+/// Stand-in for the listen key REST endpoint, handing out `key-1`, `key-2`, ...
+/// and recording `METHOD target` of every request
+async fn listen_key_server(
+    failing_keepalive: Option<&'static str>,
+) -> (String, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let recorded = requests.clone();
+
+    tokio::spawn(async move {
+        let mut created = 0;
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let read = socket.read(&mut buffer).await.unwrap();
+                request.extend_from_slice(&buffer[..read]);
+            }
+            let request = String::from_utf8(request).unwrap();
+            assert!(
+                request
+                    .to_lowercase()
+                    .contains(&format!("x-mbx-apikey: {API_KEY}"))
+            );
+
+            let mut line = request.lines().next().unwrap().split(' ');
+            let (method, target) = (line.next().unwrap(), line.next().unwrap());
+            let (status, body) = match method {
+                "POST" => {
+                    created += 1;
+                    ("200 OK", format!(r#"{{"listenKey":"key-{created}"}}"#))
+                }
+                "PUT" if failing_keepalive.is_some_and(|key| target.ends_with(key)) => (
+                    "400 Bad Request",
+                    r#"{"code":-1125,"msg":"This listenKey does not exist."}"#.to_string(),
+                ),
+                _ => ("200 OK", "{}".to_string()),
+            };
+            recorded.lock().unwrap().push(format!("{method} {target}"));
+
+            let response = format!(
+                "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+
+    (format!("http://{address}"), requests)
+}
+
+/// Handshake callback recording the path of the request
+struct RecordPath(Arc<Mutex<Vec<String>>>);
+
+impl Callback for RecordPath {
+    fn on_request(self, request: &Request, response: Response) -> Result<Response, ErrorResponse> {
+        self.0
+            .lock()
+            .unwrap()
+            .push(request.uri().path().to_string());
+        Ok(response)
+    }
+}
+
+/// Stand-in for the user data WebSocket, sending the scripted messages of each
+/// connection in turn and recording the paths connected to
+async fn user_data_server(
+    sessions: Vec<Vec<(Duration, String)>>,
+) -> (String, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let paths = Arc::new(Mutex::new(Vec::new()));
+    let recorded = paths.clone();
+
+    tokio::spawn(async move {
+        for messages in sessions {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_hdr_async(socket, RecordPath(recorded.clone()))
+                .await
+                .unwrap();
+            tokio::spawn(async move {
+                for (delay, message) in messages {
+                    tokio::time::sleep(delay).await;
+                    if ws.send(Message::text(message)).await.is_err() {
+                        return;
+                    }
+                }
+                while ws.next().await.is_some() {}
+            });
+        }
+    });
+
+    (format!("ws://{address}"), paths)
+}
+
+async fn next_message(
+    stream: &mut exstreamer::transport::WsMsgStream<BinanceUserDataMessage>,
+) -> BinanceUserDataMessage {
+    tokio::time::timeout(Duration::from_secs(5), stream.next())
+        .await
+        .expect("no user data message")
+        .expect("user data stream ended")
+        .unwrap()
+}
+
+#[tokio::test]
+async fn listen_key_is_kept_alive_and_renewed_on_expiry() {
+    let (rest_endpoint, requests) = listen_key_server(None).await;
+    let expired = r#"{"e":"listenKeyExpired","E":1576653824250,"listenKey":"key-1"}"#;
+    let (ws_endpoint, paths) = user_data_server(vec![
+        vec![
+            (Duration::ZERO, EXECUTION_REPORT.to_string()),
+            (Duration::ZERO, ACCOUNT_POSITION.to_string()),
+            (Duration::ZERO, BALANCE_UPDATE.to_string()),
+            (Duration::from_millis(350), expired.to_string()),
+        ],
+        vec![(Duration::ZERO, BALANCE_UPDATE.to_string())],
+    ])
+    .await;
+
+    let client = BinanceListenKeyRest::new(API_KEY).with_endpoint(rest_endpoint);
+    let (mut stream, handler) = StreamBuilder::binance_user_data(client)
+        .with_endpoint(ws_endpoint)
+        .with_keepalive_interval(Duration::from_millis(100))
+        .connect()
+        .await
+        .unwrap();
+    assert_eq!(handler.listen_key(), "key-1");
+
+    assert!(matches!(
+        next_message(&mut stream).await,
+        BinanceUserDataMessage::ExecutionReport(_)
+    ));
+    assert!(matches!(
+        next_message(&mut stream).await,
+        BinanceUserDataMessage::AccountPosition(_)
+    ));
+    assert!(matches!(
+        next_message(&mut stream).await,
+        BinanceUserDataMessage::BalanceUpdate(_)
+    ));
+    let BinanceUserDataMessage::ListenKeyExpired(expired) = next_message(&mut stream).await else {
+        panic!("expected the listen key to expire");
+    };
+    assert_eq!(expired.listen_key, "key-1");
+
+    // Events keep flowing on a connection with a new key
+    assert!(matches!(
+        next_message(&mut stream).await,
+        BinanceUserDataMessage::BalanceUpdate(_)
+    ));
+    assert_eq!(handler.listen_key(), "key-2");
+    assert_eq!(*paths.lock().unwrap(), vec!["/ws/key-1", "/ws/key-2"]);
+
+    handler.shutdown(Duration::from_secs(5)).await.unwrap();
+    let requests = requests.lock().unwrap();
+    let keepalives = requests
+        .iter()
+        .filter(|request| *request == "PUT /api/v3/userDataStream?listenKey=key-1")
+        .count();
+    assert!(keepalives >= 2, "{requests:?}");
+    assert_eq!(requests[0], "POST /api/v3/userDataStream");
+    assert_eq!(
+        requests
+            .iter()
+            .filter(|request| request.starts_with("POST"))
+            .count(),
+        2
+    );
+    // The expired key is closed once the new connection is open
+    let renewed = requests
+        .iter()
+        .rposition(|request| request.starts_with("POST"))
+        .unwrap();
+    assert!(
+        requests[renewed..].contains(&"DELETE /api/v3/userDataStream?listenKey=key-1".to_string()),
+        "{requests:?}"
+    );
+    assert_eq!(
+        requests.last().unwrap(),
+        "DELETE /api/v3/userDataStream?listenKey=key-2"
+    );
+}
+
+#[tokio::test]
+async fn buffer_policy_applies_to_the_user_data_stream() {
+    let (rest_endpoint, _requests) = listen_key_server(None).await;
+    let (ws_endpoint, _paths) = user_data_server(vec![vec![
+        (Duration::ZERO, EXECUTION_REPORT.to_string()),
+        (Duration::ZERO, ACCOUNT_POSITION.to_string()),
+        (Duration::ZERO, BALANCE_UPDATE.to_string()),
+    ]])
+    .await;
+
+    let client = BinanceListenKeyRest::new(API_KEY).with_endpoint(rest_endpoint);
+    let (mut stream, handler) = StreamBuilder::binance_user_data(client)
+        .with_endpoint(ws_endpoint)
+        .with_buffer(1, Backpressure::DropNewest)
+        .connect()
+        .await
+        .unwrap();
+
+    // Only the first message fits while the consumer is not reading
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(matches!(
+        next_message(&mut stream).await,
+        BinanceUserDataMessage::ExecutionReport(_)
+    ));
+    assert!(
+        tokio::time::timeout(Duration::from_millis(200), stream.next())
+            .await
+            .is_err()
+    );
+
+    handler.shutdown(Duration::from_secs(5)).await.unwrap();
+}
+
+#[tokio::test]
+async fn failed_keepalive_renews_the_listen_key() {
+    let (rest_endpoint, requests) = listen_key_server(Some("key-1")).await;
+    let (ws_endpoint, paths) = user_data_server(vec![
+        Vec::new(),
+        vec![(Duration::ZERO, BALANCE_UPDATE.to_string())],
+    ])
+    .await;
+
+    let client = BinanceListenKeyRest::new(API_KEY).with_endpoint(rest_endpoint);
+    let (mut stream, handler) = StreamBuilder::binance_user_data(client)
+        .with_endpoint(ws_endpoint)
+        .with_keepalive_interval(Duration::from_millis(100))
+        .connect()
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        next_message(&mut stream).await,
+        BinanceUserDataMessage::BalanceUpdate(_)
+    ));
+    assert_eq!(handler.listen_key(), "key-2");
+    assert_eq!(*paths.lock().unwrap(), vec!["/ws/key-1", "/ws/key-2"]);
+    assert_eq!(
+        requests.lock().unwrap()[..4],
+        [
+            "POST /api/v3/userDataStream",
+            "PUT /api/v3/userDataStream?listenKey=key-1",
+            "POST /api/v3/userDataStream",
+            "DELETE /api/v3/userDataStream?listenKey=key-1",
+        ]
+    );
+}
+
+/// Expire the first listen key, the next connections are refused
+async fn expiring_user_data_server() -> String {
+    let expired = r#"{"e":"listenKeyExpired","E":1576653824250,"listenKey":"key-1"}"#;
+    let (ws_endpoint, _) =
+        user_data_server(vec![vec![(Duration::ZERO, expired.to_string())]]).await;
+    ws_endpoint
+}
+
+#[tokio::test]
+async fn listen_keys_are_closed_when_giving_up_renewing() {
+    let (rest_endpoint, requests) = listen_key_server(None).await;
+    let client = BinanceListenKeyRest::new(API_KEY).with_endpoint(rest_endpoint);
+    let (mut stream, handler) = StreamBuilder::binance_user_data(client)
+        .with_endpoint(expiring_user_data_server().await)
+        .with_reconnect_policy(
+            ReconnectPolicy::default()
+                .with_initial_backoff(Duration::from_millis(10))
+                .with_max_attempts(Some(1)),
+        )
+        .connect()
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        next_message(&mut stream).await,
+        BinanceUserDataMessage::ListenKeyExpired(_)
+    ));
+    // Both renewals fail to connect, then the stream ends
+    for _ in 0..2 {
+        let message = tokio::time::timeout(Duration::from_secs(5), stream.next()).await;
+        assert!(matches!(message, Ok(Some(Err(_)))));
+    }
+    let end = tokio::time::timeout(Duration::from_secs(5), stream.next()).await;
+    assert!(matches!(end, Ok(None)));
+    assert!(!handler.tasks_running());
+
+    let requests = requests.lock().unwrap();
+    for key in ["key-1", "key-2", "key-3"] {
+        let close = format!("DELETE /api/v3/userDataStream?listenKey={key}");
+        assert!(requests.contains(&close), "{requests:?}");
+    }
+    assert_eq!(
+        requests.last().unwrap(),
+        "DELETE /api/v3/userDataStream?listenKey=key-1"
+    );
+}
+
+#[tokio::test]
+async fn listen_key_is_closed_on_shutdown_while_renewing() {
+    let (rest_endpoint, requests) = listen_key_server(None).await;
+    let client = BinanceListenKeyRest::new(API_KEY).with_endpoint(rest_endpoint);
+    let (mut stream, handler) = StreamBuilder::binance_user_data(client)
+        .with_endpoint(expiring_user_data_server().await)
+        .with_reconnect_policy(
+            ReconnectPolicy::default().with_initial_backoff(Duration::from_secs(60)),
+        )
+        .connect()
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        next_message(&mut stream).await,
+        BinanceUserDataMessage::ListenKeyExpired(_)
+    ));
+    // The first renewal fails, the next one waits for the backoff
+    let message = tokio::time::timeout(Duration::from_secs(5), stream.next()).await;
+    assert!(matches!(message, Ok(Some(Err(_)))));
+
+    handler.shutdown(Duration::from_secs(5)).await.unwrap();
+    assert_eq!(
+        requests.lock().unwrap().last().unwrap(),
+        "DELETE /api/v3/userDataStream?listenKey=key-1"
+    );
+}
@@ -0,0 +1,409 @@
+use std::time::Duration;
+
+use exstreamer::StreamBuilder;
+use exstreamer::error::ExStreamError;
+use exstreamer::models::{
+    BybitCategory, BybitDataType, BybitMessage, BybitRequest, BybitTickDirection, BybitTickers,
+    ExchangeMessage, Instrument, RequestKind, StrDecimal, Subscription, SubscriptionRequest,
+    SubscriptionStatus,
+    normalized::{MarketData, Normalize},
+};
+use futures_util::{SinkExt as _, StreamExt as _};
+use serde_json::json;
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+
+const TICKER_SNAPSHOT: &str = include_str!("fixtures/bybit/ticker_snapshot.json");
+const TICKER_DELTA: &str = include_str!("fixtures/bybit/ticker_delta.json");
+const KLINE: &str = include_str!("fixtures/bybit/kline.json");
+const ALL_LIQUIDATION: &str = include_str!("fixtures/bybit/all_liquidation.json");
+const LT_NAV: &str = include_str!("fixtures/bybit/lt_nav.json");
+
+fn dec(value: &str) -> StrDecimal {
+    value.parse().unwrap()
+}
+
+fn parse(json: &str) -> BybitMessage {
+    serde_json::from_str(json).unwrap()
+}
+
+/// Accept one connection and keep it open until the client goes away
+async fn idle_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+        while ws.next().await.is_some() {}
+    });
+
+    format!("ws://{address}")
+}
+
+#[tokio::test]
+async fn orderbook_depth_is_checked_against_the_category() {
+    let result = StreamBuilder::bybit()
+        .with_orderbook("btcusdt", 500)
+        .connect()
+        .await;
+    assert!(matches!(result, Err(ExStreamError::UnsupportedRequest(_))));
+
+    let result = StreamBuilder::bybit()
+        .with_category(BybitCategory::Option)
+        .with_orderbook("BTC-27DEC24-60000-C", 50)
+        .connect()
+        .await;
+    assert!(matches!(result, Err(ExStreamError::UnsupportedRequest(_))));
+
+    let result = StreamBuilder::bybit()
+        .with_liquidation("btcusdt")
+        .connect()
+        .await;
+    assert!(matches!(result, Err(ExStreamError::UnsupportedRequest(_))));
+
+    let (_stream, handler) = StreamBuilder::bybit()
+        .with_category(BybitCategory::Linear)
+        .with_orderbook("btcusdt", 500)
+        .with_endpoint(idle_server().await)
+        .connect()
+        .await
+        .unwrap();
+    assert!(handler.is_alive());
+
+    // Subscriptions added later are checked against the category of the connection
+    let result = handler.subscribe_orderbook("ethusdt", 25);
+    assert!(matches!(result, Err(ExStreamError::UnsupportedRequest(_))));
+    let request = BybitRequest::new_subscribe()
+        .with_trade("ethusdt")
+        .with_orderbook("ethusdt", 25);
+    let result = handler.subscribe(request.clone());
+    assert!(matches!(result, Err(ExStreamError::UnsupportedRequest(_))));
+    let result = handler.subscribe_with_ack(request).await;
+    assert!(matches!(result, Err(ExStreamError::UnsupportedRequest(_))));
+    let result = handler.subscribe(BybitRequest::new_subscribe().with_lt_nav("EOS3LUSDT"));
+    assert!(matches!(result, Err(ExStreamError::UnsupportedRequest(_))));
+    assert_eq!(handler.subscriptions().len(), 1);
+
+    handler.subscribe_orderbook("ethusdt", 50).unwrap();
+    assert_eq!(handler.subscriptions().len(), 2);
+}
+
+#[test]
+fn linear_trade() {
+    let message = parse(
+        r#"{"topic":"publicTrade.BTCUSDT","type":"snapshot","ts":1672304486868,"data":[{"T":1672304486865,"s":"BTCUSDT","S":"Buy","v":"0.001","p":"16578.50","L":"PlusTick","i":"20f43950-d8dd-5b31-9112-a178eb6023af","BT":false,"seq":1783284617}]}"#,
+    );
+    let BybitMessage::Trade(trade) = &message else {
+        panic!("expected a trade");
+    };
+
+    let data = &trade.data[0];
+    assert_eq!(data.tick_direction, Some(BybitTickDirection::PlusTick));
+    assert_eq!(data.sequence, Some(1783284617));
+    assert_eq!(data.mark_price, None);
+    assert_eq!(
+        message.instrument(),
+        Some(Instrument::perpetual("BTC", "USDT"))
+    );
+}
+
+#[test]
+fn option_trade() {
+    let message = parse(
+        r#"{"id":"publicTrade.BTC-3450346-1680247776040","topic":"publicTrade.BTC","type":"snapshot","ts":1680247776040,"data":[{"p":"985","v":"0.01","i":"19b9c1e7-40d8-5d5b-bd1f-7a7a0c1f4e07","T":1680247776036,"BT":false,"s":"BTC-31MAR23-27000-C","S":"Sell","mP":"1015.76","iP":"28356.68","mIv":"0.5046","iv":"0.4838","seq":1680247776036}]}"#,
+    );
+    let BybitMessage::Trade(trade) = &message else {
+        panic!("expected a trade");
+    };
+
+    let data = &trade.data[0];
+    assert_eq!(data.symbol, "BTC-31MAR23-27000-C");
+    assert_eq!(data.tick_direction, None);
+    assert_eq!(data.mark_price, Some(dec("1015.76")));
+    assert_eq!(data.index_price, Some(dec("28356.68")));
+    assert_eq!(data.mark_iv, Some(dec("0.5046")));
+    assert_eq!(data.iv, Some(dec("0.4838")));
+    assert_eq!(message.instrument(), None);
+}
+
+#[test]
+fn ticker_deltas_are_merged_into_the_snapshot() {
+    let BybitMessage::Ticker(snapshot) = parse(TICKER_SNAPSHOT) else {
+        panic!("expected a ticker");
+    };
+    let BybitMessage::Ticker(delta) = parse(TICKER_DELTA) else {
+        panic!("expected a ticker");
+    };
+    assert_eq!(delta.data_type, BybitDataType::Delta);
+    assert_eq!(delta.data.last_price, None);
+    assert_eq!(snapshot.data.basis_rate, None);
+
+    let mut tickers = BybitTickers::new();
+    tickers.apply(&snapshot);
+    let ticker = tickers.apply(&delta);
+    assert_eq!(ticker.last_price, Some(dec("17216.00")));
+    assert_eq!(ticker.mark_price, Some(dec("17218.00")));
+    assert_eq!(ticker.index_price, Some(dec("17227.00")));
+    assert_eq!(ticker.open_interest, Some(dec("68750.000")));
+    assert_eq!(ticker.open_interest_value, Some(dec("1183601235.91")));
+    assert_eq!(ticker.funding_rate, Some(dec("-0.000210")));
+    assert_eq!(ticker.tick_direction, Some(BybitTickDirection::PlusTick));
+    assert_eq!(
+        tickers.get("BTCUSDT").unwrap().bid1_size,
+        Some(dec("84.000"))
+    );
+
+    let message = parse(TICKER_SNAPSHOT);
+    assert_eq!(
+        message.instrument(),
+        Some(Instrument::perpetual("BTC", "USDT"))
+    );
+    let [MarketData::Ticker(normalized)] = &message.normalize().unwrap()[..] else {
+        panic!("expected a normalized ticker");
+    };
+    assert_eq!(normalized.symbol, "BTCUSDT");
+    assert_eq!(normalized.exchange_time, 1673272861686);
+    assert!(parse(TICKER_DELTA).normalize().unwrap().is_empty());
+}
+
+/// Send a ticker snapshot then a delta once the client subscribes
+async fn ticker_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+        ws.next().await.unwrap().unwrap();
+        for message in [TICKER_SNAPSHOT, TICKER_DELTA] {
+            ws.send(Message::text(message)).await.unwrap();
+        }
+        while ws.next().await.is_some() {}
+    });
+
+    format!("ws://{address}")
+}
+
+#[tokio::test]
+async fn normalized_ticker_deltas_keep_the_24h_statistics() {
+    let (mut stream, _handler) = StreamBuilder::bybit()
+        .with_category(BybitCategory::Linear)
+        .with_ticker("BTCUSDT")
+        .with_endpoint(ticker_server().await)
+        .connect_normalized()
+        .await
+        .unwrap();
+
+    let mut tickers = Vec::new();
+    while tickers.len() < 2 {
+        let message = tokio::time::timeout(Duration::from_secs(5), stream.next())
+            .await
+            .expect("no ticker")
+            .expect("stream ended")
+            .unwrap();
+        if let MarketData::Ticker(ticker) = message {
+            tickers.push(ticker);
+        }
+    }
+
+    let (snapshot, delta) = (&tickers[0], &tickers[1]);
+    assert_eq!(delta.exchange_time, 1673272861786);
+    assert_eq!(delta.last_price, snapshot.last_price);
+    assert!(snapshot.high_24h.is_some());
+    assert_eq!(delta.open_24h, snapshot.open_24h);
+    assert_eq!(delta.high_24h, snapshot.high_24h);
+    assert_eq!(delta.low_24h, snapshot.low_24h);
+    assert_eq!(delta.volume_24h, snapshot.volume_24h);
+}
+
+#[test]
+fn kline() {
+    let message = parse(KLINE);
+    assert_eq!(message.symbol(), Some("BTCUSDT"));
+    let BybitMessage::Kline(kline) = message else {
+        panic!("expected a kline");
+    };
+
+    let data = &kline.data[0];
+    assert_eq!(data.interval, "D");
+    assert_eq!(data.open, dec("16649.5"));
+    assert_eq!(data.volume, Some(dec("2.081")));
+    assert!(!data.confirm);
+}
+
+#[test]
+fn kline_topics_keep_their_interval() {
+    let request = BybitRequest::new_subscribe()
+        .with_kline("btcusdt", "D")
+        .with_lt_kline("eos3lusdt", "5")
+        .with_orderbook("btcusdt", 50);
+    let topics = request.topics();
+    assert_eq!(topics.len(), 3);
+    assert_eq!(topics[0].interval.as_deref(), Some("D"));
+    assert_eq!(topics[0].depth, None);
+    assert_eq!(topics[2].depth, Some(50));
+
+    let resubscribe = BybitRequest::from_topics(RequestKind::Subscribe, &topics, &[]);
+    assert_eq!(resubscribe[0].params, request.params);
+}
+
+#[test]
+fn liquidation() {
+    let message = parse(ALL_LIQUIDATION);
+    assert_eq!(
+        message.instrument(),
+        Some(Instrument::perpetual("ROSE", "USDT"))
+    );
+    let BybitMessage::Liquidation(liquidation) = message else {
+        panic!("expected a liquidation");
+    };
+
+    let data = &liquidation.data[0];
+    assert_eq!(data.symbol, "ROSEUSDT");
+    assert_eq!(data.side, "Sell");
+    assert_eq!(data.size, dec("20000"));
+    assert_eq!(data.price, dec("0.04499"));
+}
+
+#[test]
+fn leveraged_token_nav() {
+    let BybitMessage::LtNav(nav) = parse(LT_NAV) else {
+        panic!("expected a leveraged token nav");
+    };
+
+    assert_eq!(nav.data.symbol, "EOS3LUSDT");
+    assert_eq!(nav.data.time, 1672325446847);
+    assert_eq!(nav.data.nav, dec("0.001346149440083506"));
+    assert_eq!(nav.data.leverage, dec("2.9966740800483853"));
+    assert_eq!(nav.data.basket_position, dec("1.2338339554996522"));
+    assert_eq!(nav.data.basket_loan, dec("-1.6347460935418005"));
+    assert_eq!(nav.data.circulation, dec("3216.6545302766686"));
+}
+
+#[test]
+fn option_subscription_answer() {
+    let message = parse(
+        r#"{"success":true,"conn_id":"aa01fbfffe80af37-00000001-000b37b3-a0ba0f45cd0b7f95-0a2f3ddf","req_id":"7","data":{"failTopics":[],"successTopics":["orderbook.100.BTC-6JAN23-18000-C"]},"type":"COMMAND_RESP"}"#,
+    );
+    assert!(matches!(
+        message,
+        BybitMessage::SubscriptionAck { success: true, .. }
+    ));
+    let ack = message.ack().unwrap();
+    assert_eq!(ack.request_id, "7");
+    assert_eq!(ack.result, Ok(()));
+
+    let message = parse(
+        r#"{"success":true,"conn_id":"aa01fbfffe80af37-00000001-000b37b3-a0ba0f45cd0b7f95-0a2f3ddf","req_id":"8","data":{"failTopics":["orderbook.100.BTC-6JAN23-99999-C"],"successTopics":[]},"type":"COMMAND_RESP"}"#,
+    );
+    let ack = message.ack().unwrap();
+    assert_eq!(ack.request_id, "8");
+    assert_eq!(
+        ack.result,
+        Err("failed topics orderbook.100.BTC-6JAN23-99999-C".to_string())
+    );
+    assert_eq!(
+        ack.rejected,
+        vec![Subscription::new("orderbook", "BTC-6JAN23-99999-C").with_depth(100)]
+    );
+}
+
+/// Answer every request the way the option endpoint does, without `op`.
+/// Topics of the `99999` strike fail.
+async fn option_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+        while let Some(Ok(message)) = ws.next().await {
+            let Message::Text(text) = message else {
+                continue;
+            };
+            let request: serde_json::Value = serde_json::from_str(&text).unwrap();
+            let (failed, succeeded): (Vec<_>, Vec<_>) = request["args"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .partition(|topic| topic.as_str().unwrap().contains("-99999-"));
+            let reply = json!({
+                "success": true,
+                "conn_id": "aa01fbfffe80af37-00000001-000b37b3-a0ba0f45cd0b7f95-0a2f3ddf",
+                "req_id": request["req_id"],
+                "data": {"failTopics": failed, "successTopics": succeeded},
+                "type": "COMMAND_RESP",
+            });
+            if ws.send(Message::text(reply.to_string())).await.is_err() {
+                return;
+            }
+        }
+    });
+
+    format!("ws://{address}")
+}
+
+#[tokio::test]
+async fn option_subscriptions_are_acknowledged() {
+    let (_stream, handler) = StreamBuilder::bybit()
+        .with_category(BybitCategory::Option)
+        .with_orderbook("BTC-6JAN23-18000-C", 25)
+        .with_endpoint(option_server().await)
+        .with_ack_timeout(Duration::from_secs(5))
+        .connect()
+        .await
+        .unwrap();
+
+    handler
+        .subscribe_with_ack(BybitRequest::new_subscribe().with_orderbook("BTC-6JAN23-20000-C", 100))
+        .await
+        .unwrap();
+
+    let subscriptions = handler.subscriptions();
+    assert_eq!(subscriptions.len(), 2);
+    assert!(
+        subscriptions
+            .iter()
+            .all(|entry| entry.status == SubscriptionStatus::Confirmed),
+        "{subscriptions:?}"
+    );
+}
+
+#[tokio::test]
+async fn partly_rejected_option_request_keeps_the_accepted_topics() {
+    let (_stream, handler) = StreamBuilder::bybit()
+        .with_category(BybitCategory::Option)
+        .with_orderbook("BTC-6JAN23-18000-C", 25)
+        .with_endpoint(option_server().await)
+        .with_ack_timeout(Duration::from_secs(5))
+        .connect()
+        .await
+        .unwrap();
+
+    let result = handler
+        .subscribe_with_ack(
+            BybitRequest::new_subscribe()
+                .with_orderbook("BTC-6JAN23-20000-C", 100)
+                .with_orderbook("BTC-6JAN23-99999-C", 100),
+        )
+        .await;
+    assert!(matches!(
+        result,
+        Err(ExStreamError::SubscriptionRejected { reason }) if reason.contains("99999")
+    ));
+
+    // Only the failed topic is dropped, the accepted one is confirmed
+    let subscriptions = handler.subscriptions();
+    let symbols = subscriptions
+        .iter()
+        .map(|entry| (entry.subscription.symbol.as_str(), entry.status))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        symbols,
+        vec![
+            ("BTC-6JAN23-18000-C", SubscriptionStatus::Confirmed),
+            ("BTC-6JAN23-20000-C", SubscriptionStatus::Confirmed),
+        ]
+    );
+}
@@ -0,0 +1,278 @@
+use std::time::Duration;
+
+use exstreamer::StreamBuilder;
+use exstreamer::models::normalized::PriceLevel;
+use exstreamer::models::{BybitMessage, BybitOrderBook, NumDecimal};
+use exstreamer::orderbook::{BookEvent, BybitOrderBooks};
+use futures_util::{SinkExt as _, StreamExt as _};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+
+const SNAPSHOT: &str = include_str!("fixtures/bybit/orderbook_snapshot.json");
+const DELTA: &str = include_str!("fixtures/bybit/orderbook_delta.json");
+const SYMBOL: &str = "BTCUSDT";
+
+fn num(value: &str) -> NumDecimal {
+    value.parse().unwrap()
+}
+
+fn book(json: &str) -> BybitOrderBook {
+    match serde_json::from_str(json).unwrap() {
+        BybitMessage::OrderBook(book) => book,
+        message => panic!("expected an order book, got {message:?}"),
+    }
+}
+
+/// The delta fixture with another update id
+fn delta(update_id: u64) -> BybitOrderBook {
+    let mut delta = book(DELTA);
+    delta.data.update_id = update_id;
+    delta
+}
+
+/// The fixture moved to another depth
+fn at_depth(mut book: BybitOrderBook, depth: u64) -> BybitOrderBook {
+    book.topic = format!("orderbook.{depth}.{SYMBOL}");
+    book
+}
+
+fn levels(levels: Vec<PriceLevel>) -> Vec<(NumDecimal, NumDecimal)> {
+    levels
+        .into_iter()
+        .map(|level| (level.price, level.size))
+        .collect()
+}
+
+#[test]
+fn snapshot_then_delta() {
+    let mut books = BybitOrderBooks::new();
+
+    let event = books.apply(&book(SNAPSHOT)).unwrap();
+    assert_eq!(
+        event,
+        BookEvent::Snapshot {
+            symbol: SYMBOL.to_string()
+        }
+    );
+    assert_eq!(books.update_id(SYMBOL), Some(18521288));
+    assert!(books.is_synced(SYMBOL));
+    assert_eq!(books.best_bid(SYMBOL).unwrap().price, num("16493.50"));
+    assert_eq!(books.best_ask(SYMBOL).unwrap().price, num("16611.00"));
+
+    let event = books.apply(&book(DELTA)).unwrap();
+    assert_eq!(
+        event,
+        BookEvent::Updated {
+            symbol: SYMBOL.to_string()
+        }
+    );
+    assert_eq!(books.update_id(SYMBOL), Some(18521289));
+
+    // Size 0 removes the level, other sizes replace it
+    let (bids, asks) = books.top(SYMBOL, 10).unwrap();
+    assert_eq!(
+        levels(bids),
+        vec![
+            (num("16494.00"), num("0.500")),
+            (num("16493.00"), num("0.100")),
+            (num("16492.50"), num("1.200")),
+        ]
+    );
+    assert_eq!(
+        levels(asks),
+        vec![
+            (num("16611.00"), num("0.100")),
+            (num("16612.50"), num("0.500")),
+        ]
+    );
+}
+
+#[test]
+fn top_is_capped_at_the_depth() {
+    let mut books = BybitOrderBooks::new();
+    books.apply(&book(SNAPSHOT)).unwrap();
+
+    let (bids, asks) = books.top(SYMBOL, 2).unwrap();
+    assert_eq!(
+        levels(bids),
+        vec![
+            (num("16493.50"), num("0.006")),
+            (num("16493.00"), num("0.100")),
+        ]
+    );
+    assert_eq!(
+        levels(asks),
+        vec![
+            (num("16611.00"), num("0.029")),
+            (num("16612.00"), num("0.213")),
+        ]
+    );
+    assert!(books.top("ETHUSDT", 2).is_none());
+}
+
+#[test]
+fn delta_before_the_snapshot_is_skipped() {
+    let mut books = BybitOrderBooks::new();
+
+    let event = books.apply(&book(DELTA)).unwrap();
+    assert!(matches!(event, BookEvent::Unsynced { .. }));
+    assert!(!books.is_synced(SYMBOL));
+    assert!(books.best_bid(SYMBOL).is_none());
+}
+
+#[test]
+fn update_id_gap_waits_for_a_new_snapshot() {
+    let mut books = BybitOrderBooks::new();
+    books.apply(&book(SNAPSHOT)).unwrap();
+
+    let event = books.apply(&delta(18521290)).unwrap();
+    assert!(matches!(
+        event,
+        BookEvent::OutOfSync {
+            expected: 18521289,
+            received: 18521290,
+            ..
+        }
+    ));
+    assert!(!books.is_synced(SYMBOL));
+    assert_eq!(books.best_bid(SYMBOL).unwrap().price, num("16493.50"));
+
+    // Even the missing update is not applied any more
+    let event = books.apply(&delta(18521289)).unwrap();
+    assert!(matches!(event, BookEvent::Unsynced { .. }));
+
+    books.apply(&book(SNAPSHOT)).unwrap();
+    assert!(books.is_synced(SYMBOL));
+    assert!(matches!(
+        books.apply(&book(DELTA)).unwrap(),
+        BookEvent::Updated { .. }
+    ));
+}
+
+#[test]
+fn replayed_update_is_out_of_sync() {
+    let mut books = BybitOrderBooks::new();
+    books.apply(&book(SNAPSHOT)).unwrap();
+    books.apply(&book(DELTA)).unwrap();
+
+    let event = books.apply(&book(DELTA)).unwrap();
+    assert!(matches!(
+        event,
+        BookEvent::OutOfSync {
+            expected: 18521290,
+            received: 18521289,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn update_id_1_resets_the_book() {
+    let mut books = BybitOrderBooks::new();
+    books.apply(&book(SNAPSHOT)).unwrap();
+
+    // A delta typed message with u=1 is the snapshot sent after a service restart
+    let event = books.apply(&delta(1)).unwrap();
+    assert!(matches!(event, BookEvent::Snapshot { .. }));
+    assert_eq!(books.update_id(SYMBOL), Some(1));
+    let (bids, asks) = books.top(SYMBOL, 10).unwrap();
+    assert_eq!(levels(bids), vec![(num("16494.00"), num("0.500"))]);
+    assert_eq!(levels(asks), vec![(num("16611.00"), num("0.100"))]);
+
+    assert!(matches!(
+        books.apply(&delta(2)).unwrap(),
+        BookEvent::Updated { .. }
+    ));
+}
+
+#[test]
+fn depths_are_separate_books() {
+    let mut books = BybitOrderBooks::new();
+    let mut top = at_depth(book(SNAPSHOT), 1);
+    top.data.update_id = 500;
+    top.data.bids.truncate(1);
+    top.data.asks.truncate(1);
+    let mut top_delta = at_depth(delta(501), 1);
+    top_delta.data.bids.truncate(1);
+    top_delta.data.asks.truncate(1);
+
+    // Each depth follows its own update ids
+    let events = [book(SNAPSHOT), top, delta(18521289), top_delta]
+        .iter()
+        .map(|message| books.apply(message).unwrap())
+        .collect::<Vec<_>>();
+    assert!(
+        events.iter().all(|event| matches!(
+            event,
+            BookEvent::Snapshot { .. } | BookEvent::Updated { .. }
+        )),
+        "{events:?}"
+    );
+
+    assert_eq!(books.book_at_depth(SYMBOL, 1).unwrap().bids(10).len(), 2);
+    assert_eq!(books.book_at_depth(SYMBOL, 50).unwrap().bids(10).len(), 3);
+    // Accessors without depth use the deepest book
+    assert_eq!(books.update_id(SYMBOL), Some(18521289));
+    assert_eq!(books.book(SYMBOL).unwrap().bids(10).len(), 3);
+}
+
+#[tokio::test]
+async fn track_maintains_the_books_and_resubscribes_on_a_gap() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+
+    let (requests_tx, mut requests) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+        // Wait for the initial subscription before sending the book
+        ws.next().await.unwrap().unwrap();
+        let mut gap: serde_json::Value = serde_json::from_str(DELTA).unwrap();
+        gap["data"]["u"] = 18521300.into();
+        let gap = gap.to_string();
+        for json in [SNAPSHOT.to_string(), DELTA.to_string(), gap] {
+            ws.send(Message::text(json)).await.unwrap();
+        }
+        while let Some(Ok(message)) = ws.next().await {
+            if let Message::Text(text) = message {
+                let request: serde_json::Value = serde_json::from_str(&text).unwrap();
+                requests_tx.send(request).unwrap();
+            }
+        }
+    });
+
+    let (stream, handler) = StreamBuilder::bybit()
+        .with_orderbook(SYMBOL, 50)
+        .with_orderbook(SYMBOL, 1)
+        .with_endpoint(endpoint)
+        .connect()
+        .await
+        .unwrap();
+
+    let (books, mut events) = BybitOrderBooks::track(stream, handler.handle());
+    let mut next = async || {
+        tokio::time::timeout(Duration::from_secs(2), events.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap()
+    };
+    assert!(matches!(next().await, BookEvent::Snapshot { .. }));
+    assert!(matches!(next().await, BookEvent::Updated { .. }));
+    assert_eq!(
+        books.read().unwrap().best_bid(SYMBOL).unwrap().price,
+        num("16494.00")
+    );
+    assert!(matches!(next().await, BookEvent::OutOfSync { .. }));
+
+    // The gap resubscribes the book to get a new snapshot, the other depth is kept
+    for op in ["unsubscribe", "subscribe"] {
+        let request = tokio::time::timeout(Duration::from_secs(2), requests.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(request["op"], op);
+        assert_eq!(request["args"], serde_json::json!(["orderbook.50.BTCUSDT"]));
+    }
+}
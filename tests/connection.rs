@@ -0,0 +1,1105 @@
+use std::time::Duration;
+
+use exstreamer::StreamBuilder;
+use exstreamer::error::ExStreamError;
+use exstreamer::models::{
+    Binance, BinanceMessage, BinanceRequest, KrakenChannel, KrakenMessage, RequestKind,
+};
+use exstreamer::transport::{
+    Backpressure, BufferPolicy, ConnectionConfig, ConnectionEvent, Heartbeat, ReconnectPolicy,
+    Watchdog, connect_ws,
+};
+use futures_util::{SinkExt as _, StreamExt as _};
+use serde_json::json;
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+
+fn fast_reconnect() -> ReconnectPolicy {
+    ReconnectPolicy::default()
+        .with_initial_backoff(Duration::from_millis(10))
+        .with_jitter(0.0)
+}
+
+fn config(reconnect: ReconnectPolicy) -> ConnectionConfig {
+    ConnectionConfig {
+        reconnect,
+        ..ConnectionConfig::default()
+    }
+}
+
+/// Topics of a Binance request
+fn params(text: &str) -> serde_json::Value {
+    serde_json::from_str::<serde_json::Value>(text).unwrap()["params"].clone()
+}
+
+/// Collect the requests received within `window` of each other
+async fn requests_within<T>(requests: &mut mpsc::UnboundedReceiver<T>, window: Duration) -> Vec<T> {
+    let mut received = Vec::new();
+    while let Ok(Some(request)) = tokio::time::timeout(window, requests.recv()).await {
+        received.push(request);
+    }
+    received
+}
+
+/// Accept every session, reporting the pings it receives with the session number.
+/// Pings are answered with `pong`, except in the first `silent` sessions.
+async fn ping_server(
+    ping: &'static str,
+    pong: &'static str,
+    silent: usize,
+) -> (String, mpsc::UnboundedReceiver<(usize, Instant)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+    let (pings_tx, pings_rx) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        for session in 0.. {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+            let pings_tx = pings_tx.clone();
+            tokio::spawn(async move {
+                while let Some(Ok(message)) = ws.next().await {
+                    let Message::Text(text) = message else {
+                        continue;
+                    };
+                    if text.as_str() != ping {
+                        continue;
+                    }
+                    let _ = pings_tx.send((session, Instant::now()));
+                    if session >= silent && ws.send(Message::text(pong)).await.is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+    });
+
+    (endpoint, pings_rx)
+}
+
+/// Collect the pings received during `window`
+async fn pings_within(
+    pings: &mut mpsc::UnboundedReceiver<(usize, Instant)>,
+    window: Duration,
+) -> Vec<(usize, Instant)> {
+    let deadline = Instant::now() + window;
+    let mut received = Vec::new();
+    while let Ok(Some(ping)) = tokio::time::timeout_at(deadline, pings.recv()).await {
+        received.push(ping);
+    }
+    received
+}
+
+fn assert_spaced(pings: &[(usize, Instant)], interval: Duration) {
+    for pair in pings.windows(2) {
+        let gap = pair[1].1 - pair[0].1;
+        assert!(
+            gap >= interval - Duration::from_millis(20),
+            "pings {gap:?} apart"
+        );
+    }
+}
+
+/// Receive events until `last` matches, or fail after two seconds
+async fn events_until(
+    events: &mut tokio::sync::broadcast::Receiver<ConnectionEvent>,
+    last: impl Fn(&ConnectionEvent) -> bool,
+) -> Vec<ConnectionEvent> {
+    let mut received = Vec::new();
+    tokio::time::timeout(Duration::from_secs(2), async {
+        loop {
+            let event = events.recv().await.unwrap();
+            let done = last(&event);
+            received.push(event);
+            if done {
+                return;
+            }
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("missing event after {received:?}"));
+    received
+}
+
+#[tokio::test]
+async fn reconnect_replays_the_active_subscriptions_on_the_same_stream() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+    let (requests_tx, mut requests_rx) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        // First session: take the initial request and the two changes, then drop the connection
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+        for _ in 0..3 {
+            let message = ws.next().await.unwrap().unwrap();
+            requests_tx
+                .send(params(message.to_text().unwrap()))
+                .unwrap();
+        }
+        let ack = json!({"result": null, "id": 1}).to_string();
+        ws.send(Message::text(ack)).await.unwrap();
+        drop(ws);
+
+        // Second session: record the replay and answer it
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+        for _ in 0..2 {
+            let message = ws.next().await.unwrap().unwrap();
+            requests_tx
+                .send(params(message.to_text().unwrap()))
+                .unwrap();
+        }
+        let ack = json!({"result": null, "id": 2}).to_string();
+        ws.send(Message::text(ack)).await.unwrap();
+        while ws.next().await.is_some() {}
+    });
+
+    let (mut stream, handler) = connect_ws::<Binance>(
+        endpoint,
+        vec![BinanceRequest::new_subscribe().with_trades(vec!["btcusdt", "ethusdt"])],
+        config(fast_reconnect()),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        requests_rx.recv().await.unwrap(),
+        json!(["btcusdt@trade", "ethusdt@trade"])
+    );
+    handler
+        .subscribe(BinanceRequest::new_subscribe().with_trade("solusdt"))
+        .unwrap();
+    handler
+        .unsubscribe(BinanceRequest::new_unsubscribe().with_trade("btcusdt"))
+        .unwrap();
+
+    for id in [1, 2] {
+        let message = tokio::time::timeout(Duration::from_secs(2), stream.next())
+            .await
+            .expect("the stream stalled")
+            .unwrap()
+            .unwrap();
+        assert!(
+            matches!(message, BinanceMessage::SubscriptionAck(ref ack) if ack.id == Some(id)),
+            "{message:?}"
+        );
+    }
+    assert_eq!(
+        requests_within(&mut requests_rx, Duration::from_millis(100)).await,
+        vec![
+            json!(["solusdt@trade"]),
+            json!(["btcusdt@trade"]),
+            json!(["ethusdt@trade"]),
+            json!(["solusdt@trade"]),
+        ]
+    );
+    assert!(handler.is_alive());
+}
+
+#[tokio::test]
+async fn reconnect_replays_every_kraken_channel_still_subscribed() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+    let (requests_tx, mut requests_rx) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        // First session: take the three subscriptions and the unsubscribe, then drop the connection
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+        for _ in 0..4 {
+            let message = ws.next().await.unwrap().unwrap();
+            let request: serde_json::Value =
+                serde_json::from_str(message.to_text().unwrap()).unwrap();
+            requests_tx.send((1, request)).unwrap();
+        }
+        drop(ws);
+
+        // Second session: record the replay
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+        while let Some(Ok(message)) = ws.next().await {
+            if let Message::Text(text) = message {
+                let request = serde_json::from_str(text.as_str()).unwrap();
+                requests_tx.send((2, request)).unwrap();
+            }
+        }
+    });
+
+    let (_stream, handler) = StreamBuilder::kraken(KrakenChannel::Trade)
+        .with_symbol("BTC/USD")
+        .with_channel(KrakenChannel::Book)
+        .with_symbol("BTC/USD")
+        .with_depth(10)
+        .with_channel(KrakenChannel::Ticker)
+        .with_symbol("BTC/USD")
+        .with_endpoint(endpoint)
+        .with_reconnect_policy(fast_reconnect())
+        .without_heartbeat()
+        .connect()
+        .await
+        .unwrap();
+
+    let channels = |requests: &[(usize, serde_json::Value)]| {
+        requests
+            .iter()
+            .map(|(session, request)| {
+                let params = &request["params"];
+                assert_eq!(params["symbol"], json!(["BTC/USD"]));
+                (
+                    *session,
+                    request["method"].as_str().unwrap().to_string(),
+                    params["channel"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut initial = Vec::new();
+    for _ in 0..3 {
+        let request = tokio::time::timeout(Duration::from_secs(2), requests_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        initial.push(request);
+    }
+    let mut initial = channels(&initial);
+    initial.sort();
+    assert_eq!(
+        initial,
+        [
+            (1, "subscribe".to_string(), "book".to_string()),
+            (1, "subscribe".to_string(), "ticker".to_string()),
+            (1, "subscribe".to_string(), "trade".to_string()),
+        ]
+    );
+
+    handler.unsubscribe_ticker("BTC/USD").unwrap();
+    let mut replayed =
+        channels(&requests_within(&mut requests_rx, Duration::from_millis(200)).await);
+    assert_eq!(
+        replayed.remove(0),
+        (1, "unsubscribe".to_string(), "ticker".to_string())
+    );
+    replayed.sort();
+    assert_eq!(
+        replayed,
+        [
+            (2, "subscribe".to_string(), "book".to_string()),
+            (2, "subscribe".to_string(), "trade".to_string()),
+        ]
+    );
+    assert!(handler.is_alive());
+}
+
+#[tokio::test]
+async fn subscription_during_the_reconnect_handshake_reaches_the_new_session() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+    let (requests_tx, mut requests_rx) = mpsc::unbounded_channel();
+    let (dropped_tx, dropped_rx) = oneshot::channel();
+
+    tokio::spawn(async move {
+        // First session: take the subscription, then drop the connection
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+        ws.next().await.unwrap().unwrap();
+        drop(ws);
+        dropped_tx.send(()).unwrap();
+
+        // Second session: hold the handshake while the client subscribes
+        let (socket, _) = listener.accept().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+        while let Some(Ok(message)) = ws.next().await {
+            if let Message::Text(text) = message {
+                requests_tx.send(params(&text)).unwrap();
+            }
+        }
+    });
+
+    let (_stream, handler) = connect_ws::<Binance>(
+        endpoint,
+        vec![BinanceRequest::new_subscribe().with_trade("btcusdt")],
+        config(fast_reconnect()),
+    )
+    .await
+    .unwrap();
+
+    // Past the reconnect delay, the new session is waiting for its handshake
+    dropped_rx.await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    handler
+        .subscribe(BinanceRequest::new_subscribe().with_trade("ethusdt"))
+        .unwrap();
+
+    assert_eq!(
+        requests_within(&mut requests_rx, Duration::from_millis(500)).await,
+        vec![json!(["btcusdt@trade"]), json!(["ethusdt@trade"])]
+    );
+}
+
+#[tokio::test]
+async fn stream_ends_when_giving_up() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+        ws.next().await.unwrap().unwrap();
+        ws.close(None).await.unwrap();
+        // No more sessions are accepted
+        drop(listener);
+    });
+
+    let (mut stream, handler) = connect_ws::<Binance>(
+        endpoint,
+        vec![BinanceRequest::new_subscribe().with_trade("btcusdt")],
+        config(ReconnectPolicy::disabled()),
+    )
+    .await
+    .unwrap();
+
+    let next = tokio::time::timeout(Duration::from_secs(2), stream.next())
+        .await
+        .expect("the stream was kept open");
+    assert!(next.is_none(), "{next:?}");
+    assert!(!handler.is_alive());
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!handler.tasks_running());
+}
+
+#[tokio::test]
+async fn lifecycle_events_follow_a_reconnect() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+    let (go_away_tx, go_away_rx) = oneshot::channel::<()>();
+
+    tokio::spawn(async move {
+        // First session: close with a code once the client listens to the events
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+        ws.next().await.unwrap().unwrap();
+        go_away_rx.await.unwrap();
+        let frame = CloseFrame {
+            code: CloseCode::Away,
+            reason: "maintenance".into(),
+        };
+        ws.close(Some(frame)).await.unwrap();
+
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+        while ws.next().await.is_some() {}
+    });
+
+    let (_stream, handler) = connect_ws::<Binance>(
+        endpoint.clone(),
+        vec![
+            BinanceRequest::new_subscribe()
+                .with_trade("btcusdt")
+                .with_id(7),
+        ],
+        config(fast_reconnect()),
+    )
+    .await
+    .unwrap();
+    let mut events = handler.events();
+    go_away_tx.send(()).unwrap();
+
+    // The initial subscription may still be reported first
+    let mut received = events_until(&mut events, |event| {
+        matches!(event, ConnectionEvent::Disconnected { .. })
+    })
+    .await;
+    received.retain(|event| matches!(event, ConnectionEvent::Disconnected { .. }));
+    received.extend(
+        events_until(&mut events, |event| {
+            matches!(event, ConnectionEvent::SubscriptionSent { .. })
+        })
+        .await,
+    );
+    assert_eq!(
+        received,
+        vec![
+            ConnectionEvent::Disconnected {
+                reason: "maintenance".to_string(),
+                code: Some(1001),
+            },
+            ConnectionEvent::Reconnecting {
+                attempt: 1,
+                delay: Duration::from_millis(10),
+            },
+            ConnectionEvent::Connecting { endpoint },
+            ConnectionEvent::Connected,
+            ConnectionEvent::SubscriptionSent {
+                kind: RequestKind::Subscribe,
+                request: r#"{"method":"SUBSCRIBE","params":["btcusdt@trade"],"id":7}"#.to_string(),
+            },
+        ]
+    );
+
+    handler
+        .unsubscribe(BinanceRequest::new_unsubscribe().with_trade("btcusdt"))
+        .unwrap();
+    let received = events_until(&mut events, |_| true).await;
+    assert!(
+        matches!(
+            &received[0],
+            ConnectionEvent::SubscriptionSent { kind: RequestKind::Unsubscribe, request }
+                if request.contains("btcusdt@trade")
+        ),
+        "{received:?}"
+    );
+}
+
+#[tokio::test]
+async fn giving_up_emits_shut_down() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+    let (go_away_tx, go_away_rx) = oneshot::channel::<()>();
+
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+        go_away_rx.await.unwrap();
+        drop(ws);
+    });
+
+    let (_stream, handler) = connect_ws::<Binance>(
+        endpoint,
+        vec![BinanceRequest::new_subscribe().with_trade("btcusdt")],
+        config(ReconnectPolicy::disabled()),
+    )
+    .await
+    .unwrap();
+    let mut events = handler.events();
+    go_away_tx.send(()).unwrap();
+
+    let received = events_until(&mut events, |event| {
+        matches!(event, ConnectionEvent::ShutDown)
+    })
+    .await;
+    assert!(
+        matches!(
+            received.as_slice(),
+            [
+                ..,
+                ConnectionEvent::Disconnected { code: None, .. },
+                ConnectionEvent::ShutDown
+            ]
+        ),
+        "{received:?}"
+    );
+}
+
+#[tokio::test]
+async fn slow_consumer_fails_the_stream_with_lagged() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+        for id in 0..3 {
+            let ack = json!({"result": null, "id": id}).to_string();
+            ws.send(Message::text(ack)).await.unwrap();
+        }
+        while ws.next().await.is_some() {}
+    });
+
+    let config = ConnectionConfig {
+        buffer: Some(BufferPolicy::new(1, Backpressure::Fail)),
+        ..ConnectionConfig::default()
+    };
+    let (mut stream, _handler) = connect_ws::<Binance>(
+        endpoint,
+        vec![BinanceRequest::new_subscribe().with_trade("btcusdt")],
+        config,
+    )
+    .await
+    .unwrap();
+
+    // Let every message arrive before consuming
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(matches!(
+        stream.next().await,
+        Some(Ok(BinanceMessage::SubscriptionAck(_)))
+    ));
+    assert!(matches!(
+        stream.next().await,
+        Some(Err(ExStreamError::Lagged { dropped: 1 }))
+    ));
+    assert!(stream.next().await.is_none());
+}
+
+#[tokio::test]
+async fn bybit_pings_on_the_interval() {
+    let (endpoint, mut pings) = ping_server(
+        r#"{"op":"ping"}"#,
+        r#"{"success":true,"ret_msg":"pong","conn_id":"0970e817-426e-429a-a679-ff7f55e0b16a","op":"ping"}"#,
+        0,
+    )
+    .await;
+    let interval = Duration::from_millis(100);
+
+    let (mut stream, handler) = StreamBuilder::bybit()
+        .with_trade("btcusdt")
+        .with_endpoint(endpoint)
+        .with_heartbeat(Heartbeat::bybit().with_interval(interval))
+        .connect()
+        .await
+        .unwrap();
+    let mut events = handler.events();
+
+    let received = pings_within(&mut pings, Duration::from_millis(550)).await;
+    assert!(
+        (4..=6).contains(&received.len()),
+        "{} pings",
+        received.len()
+    );
+    assert_spaced(&received, interval);
+
+    // Pongs are consumed by the connection, the session stays up
+    let next = tokio::time::timeout(Duration::from_millis(100), stream.next()).await;
+    assert!(next.is_err(), "{next:?}");
+    while let Ok(event) = events.try_recv() {
+        assert!(
+            !matches!(event, ConnectionEvent::Disconnected { .. }),
+            "{event:?}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn kraken_pings_on_the_interval() {
+    let (endpoint, mut pings) = ping_server(
+        r#"{"method":"ping"}"#,
+        r#"{"method":"pong","time_in":"2023-09-24T14:10:23.799685Z","time_out":"2023-09-24T14:10:23.799703Z"}"#,
+        0,
+    )
+    .await;
+    let interval = Duration::from_millis(100);
+
+    let (mut stream, handler) = StreamBuilder::kraken(KrakenChannel::Trade)
+        .with_symbol("BTC/USD")
+        .with_endpoint(endpoint)
+        .with_heartbeat(Heartbeat::kraken().with_interval(interval))
+        .connect()
+        .await
+        .unwrap();
+    let mut events = handler.events();
+
+    let received = pings_within(&mut pings, Duration::from_millis(550)).await;
+    assert!(
+        (4..=6).contains(&received.len()),
+        "{} pings",
+        received.len()
+    );
+    assert_spaced(&received, interval);
+
+    let next = tokio::time::timeout(Duration::from_millis(100), stream.next()).await;
+    assert!(next.is_err(), "{next:?}");
+    while let Ok(event) = events.try_recv() {
+        assert!(
+            !matches!(event, ConnectionEvent::Disconnected { .. }),
+            "{event:?}"
+        );
+    }
+}
+
+#[test]
+fn kraken_pong_is_a_message() {
+    let pong = r#"{"method":"pong","req_id":7,"time_in":"2023-09-24T14:10:23.799685Z","time_out":"2023-09-24T14:10:23.799703Z"}"#;
+    let message = serde_json::from_str::<KrakenMessage>(pong).unwrap();
+    assert!(
+        matches!(
+            message,
+            KrakenMessage::Pong {
+                req_id: Some(7),
+                ..
+            }
+        ),
+        "{message:?}"
+    );
+}
+
+#[tokio::test]
+async fn missing_pong_reconnects() {
+    let (endpoint, mut pings) = ping_server(r#"{"op":"ping"}"#, r#"{"op":"pong"}"#, 1).await;
+
+    let (_stream, handler) = StreamBuilder::bybit()
+        .with_trade("btcusdt")
+        .with_endpoint(endpoint)
+        .with_reconnect_policy(fast_reconnect())
+        .with_heartbeat(
+            Heartbeat::bybit()
+                .with_interval(Duration::from_millis(50))
+                .with_timeout(Duration::from_millis(100)),
+        )
+        .connect()
+        .await
+        .unwrap();
+    let mut events = handler.events();
+
+    let disconnected = tokio::time::timeout(Duration::from_secs(2), async {
+        loop {
+            if let ConnectionEvent::Disconnected { reason, .. } = events.recv().await.unwrap() {
+                return reason;
+            }
+        }
+    })
+    .await
+    .expect("the silent session was kept");
+    assert_eq!(disconnected, "heartbeat timed out");
+
+    tokio::time::timeout(Duration::from_secs(2), async {
+        loop {
+            if let ConnectionEvent::Connected = events.recv().await.unwrap() {
+                return;
+            }
+        }
+    })
+    .await
+    .expect("no new session");
+
+    // The new session answers, so it keeps pinging without another disconnect
+    let received = pings_within(&mut pings, Duration::from_millis(400)).await;
+    assert!(received.iter().any(|(session, _)| *session == 1));
+    while let Ok(event) = events.try_recv() {
+        assert!(
+            !matches!(event, ConnectionEvent::Disconnected { .. }),
+            "{event:?}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn blocked_consumer_does_not_make_the_feed_stale() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+        for id in 0.. {
+            let ack = json!({"result": null, "id": id}).to_string();
+            if ws.send(Message::text(ack)).await.is_err() {
+                return;
+            }
+            // Nothing left to read when the consumer catches up, only the watchdog runs
+            let pause = if id == 1 { 700 } else { 20 };
+            tokio::time::sleep(Duration::from_millis(pause)).await;
+        }
+    });
+
+    let (mut stream, handler) = StreamBuilder::binance()
+        .with_trade("btcusdt")
+        .with_endpoint(endpoint)
+        .with_buffer(1, Backpressure::Block)
+        .with_watchdog(Watchdog::new(Duration::from_millis(200)))
+        .connect()
+        .await
+        .unwrap();
+    let mut events = handler.events();
+
+    // Leave the buffer full for longer than the idle timeout
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    for _ in 0..10 {
+        tokio::time::timeout(Duration::from_secs(2), stream.next())
+            .await
+            .expect("the feed was dropped as stale")
+            .unwrap()
+            .unwrap();
+    }
+
+    while let Ok(event) = events.try_recv() {
+        assert!(
+            !matches!(
+                event,
+                ConnectionEvent::Stale { .. } | ConnectionEvent::Disconnected { .. }
+            ),
+            "{event:?}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn silent_feed_reconnects() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+
+    tokio::spawn(async move {
+        loop {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+            tokio::spawn(async move { while ws.next().await.is_some() {} });
+        }
+    });
+
+    let (_stream, handler) = StreamBuilder::binance()
+        .with_trade("btcusdt")
+        .with_endpoint(endpoint)
+        .with_reconnect_policy(fast_reconnect())
+        .with_watchdog(Watchdog::new(Duration::from_millis(100)))
+        .connect()
+        .await
+        .unwrap();
+    let mut events = handler.events();
+
+    let mut received = Vec::new();
+    tokio::time::timeout(Duration::from_secs(2), async {
+        loop {
+            let event = events.recv().await.unwrap();
+            let connected = matches!(event, ConnectionEvent::Connected);
+            received.push(event);
+            if connected {
+                return;
+            }
+        }
+    })
+    .await
+    .expect("the silent feed was kept");
+
+    let stale = received
+        .iter()
+        .position(|event| matches!(event, ConnectionEvent::Stale { .. }))
+        .expect("no stale event");
+    assert!(matches!(
+        &received[stale],
+        ConnectionEvent::Stale { symbol: None, idle } if *idle >= Duration::from_millis(100)
+    ));
+    assert!(matches!(
+        &received[stale + 1],
+        ConnectionEvent::Disconnected { reason, .. } if reason == "stale feed"
+    ));
+    assert!(
+        received
+            .iter()
+            .any(|event| matches!(event, ConnectionEvent::Reconnecting { attempt: 1, .. }))
+    );
+}
+
+#[tokio::test]
+async fn pongs_keep_the_connection_but_not_a_silent_symbol() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+        let trade = r#"{"topic":"publicTrade.BTCUSDT","type":"snapshot","ts":1672304486868,"data":[{"T":1672304486865,"s":"BTCUSDT","S":"Buy","v":"0.001","p":"16578.50","L":"PlusTick","i":"20f43950-d8dd-5b31-9112-a178eb6023af","BT":false,"RPI":false}]}"#;
+        ws.send(Message::text(trade)).await.unwrap();
+        while let Some(Ok(message)) = ws.next().await {
+            if message.to_text().unwrap() == r#"{"op":"ping"}"# {
+                let pong = r#"{"success":true,"ret_msg":"pong","op":"ping"}"#;
+                if ws.send(Message::text(pong)).await.is_err() {
+                    return;
+                }
+            }
+        }
+    });
+
+    let started = Instant::now();
+    let (_stream, handler) = StreamBuilder::bybit()
+        .with_trade("btcusdt")
+        .with_endpoint(endpoint)
+        .with_heartbeat(Heartbeat::bybit().with_interval(Duration::from_millis(30)))
+        .with_watchdog(
+            Watchdog::new(Duration::from_millis(150))
+                .with_symbol_timeout(Duration::from_millis(400)),
+        )
+        .connect()
+        .await
+        .unwrap();
+    let mut events = handler.events();
+
+    // Pongs arrive well within the idle timeout, only the symbol goes stale
+    let stale = tokio::time::timeout(Duration::from_secs(2), async {
+        loop {
+            if let ConnectionEvent::Stale { symbol, .. } = events.recv().await.unwrap() {
+                return symbol;
+            }
+        }
+    })
+    .await
+    .expect("the silent symbol was not noticed");
+    assert_eq!(stale.as_deref(), Some("BTCUSDT"));
+    assert!(started.elapsed() >= Duration::from_millis(400));
+}
+
+#[tokio::test]
+async fn unsubscribing_keeps_watching_the_other_symbols() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+        for symbol in ["BTCUSDT", "ETHUSDT"] {
+            let trade = format!(
+                r#"{{"topic":"publicTrade.{symbol}","type":"snapshot","ts":1672304486868,"data":[{{"T":1672304486865,"s":"{symbol}","S":"Buy","v":"0.001","p":"16578.50","L":"PlusTick","i":"20f43950-d8dd-5b31-9112-a178eb6023af","BT":false,"RPI":false}}]}}"#
+            );
+            ws.send(Message::text(trade)).await.unwrap();
+        }
+        while let Some(Ok(message)) = ws.next().await {
+            if message.to_text().unwrap() == r#"{"op":"ping"}"# {
+                let pong = r#"{"success":true,"ret_msg":"pong","op":"ping"}"#;
+                if ws.send(Message::text(pong)).await.is_err() {
+                    return;
+                }
+            }
+        }
+    });
+
+    let (mut stream, handler) = StreamBuilder::bybit()
+        .with_trade("btcusdt")
+        .with_trade("ethusdt")
+        .with_endpoint(endpoint)
+        .with_heartbeat(Heartbeat::bybit().with_interval(Duration::from_millis(30)))
+        .with_watchdog(
+            Watchdog::new(Duration::from_millis(150))
+                .with_symbol_timeout(Duration::from_millis(400)),
+        )
+        .connect()
+        .await
+        .unwrap();
+    let mut events = handler.events();
+    for _ in 0..2 {
+        stream.next().await.unwrap().unwrap();
+    }
+
+    // Only the unsubscribed symbol stops being watched
+    handler.unsubscribe_trade("ethusdt").unwrap();
+    let stale = tokio::time::timeout(Duration::from_secs(2), async {
+        loop {
+            if let ConnectionEvent::Stale { symbol, .. } = events.recv().await.unwrap() {
+                return symbol;
+            }
+        }
+    })
+    .await
+    .expect("the silent symbol was not noticed");
+    assert_eq!(stale.as_deref(), Some("BTCUSDT"));
+}
+
+#[tokio::test]
+async fn typed_handler_sends_the_exchange_requests() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+    let (requests_tx, mut requests_rx) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+        while let Some(Ok(message)) = ws.next().await {
+            let request: serde_json::Value =
+                serde_json::from_str(message.to_text().unwrap()).unwrap();
+            let _ = requests_tx.send(request);
+        }
+    });
+
+    let (_stream, handler) = StreamBuilder::bybit()
+        .with_trade("btcusdt")
+        .with_endpoint(endpoint)
+        .connect()
+        .await
+        .unwrap();
+    // Requests queued before the session starts are covered by the replay
+    let initial = tokio::time::timeout(Duration::from_secs(2), requests_rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(initial["args"], json!(["publicTrade.BTCUSDT"]));
+
+    handler.subscribe_orderbook("ethusdt", 50).unwrap();
+    handler.unsubscribe_trade("btcusdt").unwrap();
+
+    let requests = requests_within(&mut requests_rx, Duration::from_millis(200)).await;
+    let requests: Vec<_> = requests
+        .iter()
+        .map(|request| (request["op"].clone(), request["args"].clone()))
+        .collect();
+    assert_eq!(
+        requests,
+        [
+            (json!("subscribe"), json!(["orderbook.50.ETHUSDT"])),
+            (json!("unsubscribe"), json!(["publicTrade.BTCUSDT"])),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn rejected_unsubscribe_keeps_the_topics() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+    let (requests_tx, mut requests_rx) = mpsc::unbounded_channel();
+
+    // Accept subscriptions, reject unsubscriptions
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+        while let Some(Ok(message)) = ws.next().await {
+            let request: serde_json::Value =
+                serde_json::from_str(message.to_text().unwrap()).unwrap();
+            let answer = match request["method"].as_str() {
+                Some("SUBSCRIBE") => json!({"result": null, "id": request["id"]}),
+                _ => json!({"error": {"code": 2, "msg": "Invalid request"}, "id": request["id"]}),
+            };
+            let _ = requests_tx.send(request);
+            if ws.send(Message::text(answer.to_string())).await.is_err() {
+                return;
+            }
+        }
+    });
+
+    let (_stream, handler) = StreamBuilder::binance()
+        .with_trade("btcusdt")
+        .with_endpoint(endpoint)
+        .connect()
+        .await
+        .unwrap();
+    let topics = || {
+        handler
+            .subscriptions()
+            .into_iter()
+            .map(|entry| entry.subscription)
+            .collect::<Vec<_>>()
+    };
+    let subscribed = topics();
+    let initial = tokio::time::timeout(Duration::from_secs(2), requests_rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(initial["method"], "SUBSCRIBE");
+
+    let result = handler
+        .unsubscribe_with_ack(
+            BinanceRequest::new_unsubscribe()
+                .with_trade("btcusdt")
+                .with_id(42),
+        )
+        .await;
+    assert!(
+        matches!(result, Err(ExStreamError::SubscriptionRejected { .. })),
+        "{result:?}"
+    );
+    assert_eq!(topics(), subscribed);
+
+    // The caller's request id is sent as is
+    let requests = requests_within(&mut requests_rx, Duration::from_millis(100)).await;
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0]["method"], "UNSUBSCRIBE");
+    assert_eq!(requests[0]["id"], 42);
+}
+
+/// Accept a single session, reporting its requests and when the client closed it
+async fn session_server() -> (
+    String,
+    mpsc::UnboundedReceiver<serde_json::Value>,
+    oneshot::Receiver<()>,
+) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+    let (requests_tx, requests_rx) = mpsc::unbounded_channel();
+    let (closed_tx, closed_rx) = oneshot::channel();
+
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+        while let Some(Ok(message)) = ws.next().await {
+            if let Message::Text(text) = message {
+                let _ = requests_tx.send(serde_json::from_str(text.as_str()).unwrap());
+            }
+        }
+        let _ = closed_tx.send(());
+    });
+
+    (endpoint, requests_rx, closed_rx)
+}
+
+#[tokio::test]
+async fn cloned_handles_control_the_connection_from_other_tasks() {
+    let (endpoint, mut requests, _closed) = session_server().await;
+    let (_stream, handler) = StreamBuilder::binance()
+        .with_trade("btcusdt")
+        .with_endpoint(endpoint)
+        .connect()
+        .await
+        .unwrap();
+    let initial = tokio::time::timeout(Duration::from_secs(2), requests.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(initial["params"], json!(["btcusdt@trade"]));
+
+    let handle = handler.handle();
+    tokio::spawn(async move { handle.subscribe_trade("ethusdt") })
+        .await
+        .unwrap()
+        .unwrap();
+
+    let request = tokio::time::timeout(Duration::from_secs(2), requests.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(request["params"], json!(["ethusdt@trade"]));
+    assert_eq!(
+        handler
+            .subscriptions()
+            .iter()
+            .map(|entry| entry.subscription.symbol.as_str())
+            .collect::<Vec<_>>(),
+        ["btcusdt", "ethusdt"]
+    );
+}
+
+#[tokio::test]
+async fn dropping_the_handler_closes_the_connection() {
+    let (endpoint, _requests, closed) = session_server().await;
+    let (mut stream, handler) = StreamBuilder::binance()
+        .with_trade("btcusdt")
+        .with_endpoint(endpoint)
+        .connect()
+        .await
+        .unwrap();
+    let handle = handler.handle();
+    assert!(handle.is_alive());
+
+    drop(handler);
+    tokio::time::timeout(Duration::from_secs(2), closed)
+        .await
+        .expect("the connection was kept open")
+        .unwrap();
+    assert!(!handle.is_alive());
+    let next = tokio::time::timeout(Duration::from_secs(2), stream.next())
+        .await
+        .expect("the stream was kept open");
+    assert!(next.is_none(), "{next:?}");
+}
+
+#[tokio::test]
+async fn shutdown_waits_for_the_tasks() {
+    let (endpoint, _requests, closed) = session_server().await;
+    let (mut stream, handler) = StreamBuilder::binance()
+        .with_trade("btcusdt")
+        .with_endpoint(endpoint)
+        .connect()
+        .await
+        .unwrap();
+    let handle = handler.handle();
+
+    handler.shutdown(Duration::from_secs(2)).await.unwrap();
+    assert!(!handle.is_alive());
+    assert!(matches!(
+        handle.subscribe_trade("ethusdt"),
+        Err(ExStreamError::StreamClosed)
+    ));
+    tokio::time::timeout(Duration::from_secs(2), closed)
+        .await
+        .expect("the connection was kept open")
+        .unwrap();
+    assert!(stream.next().await.is_none());
+}
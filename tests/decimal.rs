@@ -0,0 +1,68 @@
+use exstreamer::models::{
+    BinanceMessage, BybitMessage, KrakenData, KrakenMessage, KrakenTradeData,
+};
+
+const BINANCE_TRADE: &str = r#"{"e":"trade","E":1672515782136,"s":"BNBBTC","t":12345,"p":"0.00100000","q":"100.00000000","T":1672515782136,"m":true,"M":true}"#;
+const BYBIT_BOOK: &str = r#"{"topic":"orderbook.50.BTCUSDT","type":"snapshot","ts":1687940967466,"data":{"s":"BTCUSDT","b":[["30247.20","30.028"]],"a":[["30248.70","0.1"]],"u":177400507,"seq":66544703342},"cts":1687940967464}"#;
+// Neither number has an exact f64 representation
+const KRAKEN_TRADE: &str = r#"{"channel":"trade","type":"update","data":[{"symbol":"BTC/USD","side":"sell","price":26403.100000000001,"qty":0.30000000000000004,"ord_type":"market","trade_id":73912883,"timestamp":"2023-09-25T07:49:37.708706Z"}]}"#;
+
+fn kraken_trade() -> KrakenTradeData {
+    let KrakenMessage::Event(event) = serde_json::from_str(KRAKEN_TRADE).unwrap() else {
+        panic!("not an event");
+    };
+    match event.data.into_iter().next() {
+        Some(KrakenData::Trade(trade)) => trade,
+        other => panic!("{other:?}"),
+    }
+}
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn prices_are_kept_as_the_exchange_sent_them() {
+    let BinanceMessage::Trade(trade) = serde_json::from_str(BINANCE_TRADE).unwrap() else {
+        panic!("not a trade");
+    };
+    assert_eq!(trade.price, "0.00100000");
+    assert_eq!(trade.quantity, "100.00000000");
+
+    let BybitMessage::OrderBook(book) = serde_json::from_str(BYBIT_BOOK).unwrap() else {
+        panic!("not a book");
+    };
+    assert_eq!(book.data.bids, [["30247.20", "30.028"]]);
+
+    let trade = kraken_trade();
+    assert_eq!(trade.price, 26403.1);
+}
+
+#[cfg(feature = "decimal")]
+#[test]
+fn prices_are_exact_decimals() {
+    use rust_decimal::Decimal;
+
+    let dec = |value: &str| value.parse::<Decimal>().unwrap();
+
+    let BinanceMessage::Trade(trade) = serde_json::from_str(BINANCE_TRADE).unwrap() else {
+        panic!("not a trade");
+    };
+    assert_eq!(trade.price, dec("0.001"));
+    // The exchange precision is kept
+    assert_eq!(trade.price.scale(), 8);
+
+    let BybitMessage::OrderBook(book) = serde_json::from_str(BYBIT_BOOK).unwrap() else {
+        panic!("not a book");
+    };
+    assert_eq!(book.data.asks, [[dec("30248.70"), dec("0.1")]]);
+
+    // Kraken numbers are read from the JSON text, not through f64
+    let trade = kraken_trade();
+    assert_eq!(trade.price, dec("26403.100000000001"));
+    assert_eq!(trade.size, dec("0.30000000000000004"));
+}
+
+#[cfg(feature = "decimal")]
+#[test]
+fn invalid_prices_fail_to_parse() {
+    let trade = BINANCE_TRADE.replace("0.00100000", "n/a");
+    assert!(serde_json::from_str::<BinanceMessage>(&trade).is_err());
+}
@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+use exstreamer::StreamBuilder;
+use exstreamer::models::KrakenChannel;
+use futures_util::StreamExt as _;
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+
+/// Local WebSocket server reporting the first request of the first session
+async fn mock_server() -> (String, oneshot::Receiver<serde_json::Value>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+    let (request_tx, request_rx) = oneshot::channel();
+
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+        let message = ws.next().await.unwrap().unwrap();
+        let request = serde_json::from_str(message.to_text().unwrap()).unwrap();
+        request_tx.send(request).unwrap();
+        while ws.next().await.is_some() {}
+    });
+
+    (endpoint, request_rx)
+}
+
+async fn received(request: oneshot::Receiver<serde_json::Value>) -> serde_json::Value {
+    tokio::time::timeout(Duration::from_secs(2), request)
+        .await
+        .expect("no request on the custom endpoint")
+        .unwrap()
+}
+
+#[tokio::test]
+async fn binance_connects_to_the_custom_endpoint() {
+    let (endpoint, request) = mock_server().await;
+    let (_stream, _handler) = StreamBuilder::binance()
+        .with_trade("btcusdt")
+        .with_endpoint(endpoint)
+        .connect()
+        .await
+        .unwrap();
+
+    assert_eq!(received(request).await["params"][0], "btcusdt@trade");
+}
+
+#[tokio::test]
+async fn bybit_connects_to_the_custom_endpoint() {
+    let (endpoint, request) = mock_server().await;
+    let (_stream, _handler) = StreamBuilder::bybit()
+        .with_orderbook("ethusdt", 50)
+        .with_endpoint(endpoint)
+        .connect()
+        .await
+        .unwrap();
+
+    assert_eq!(received(request).await["args"][0], "orderbook.50.ETHUSDT");
+}
+
+#[tokio::test]
+async fn coinbase_connects_to_the_custom_endpoint() {
+    let (endpoint, request) = mock_server().await;
+    let (_stream, _handler) = StreamBuilder::coinbase()
+        .with_trade("ETH-USD")
+        .with_endpoint(endpoint)
+        .connect()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        received(request).await["channels"][0]["product_ids"][0],
+        "ETH-USD"
+    );
+}
+
+#[tokio::test]
+async fn kraken_picks_the_custom_endpoint_matching_the_channel() {
+    let (public, public_request) = mock_server().await;
+    let (auth, auth_request) = mock_server().await;
+
+    let (_stream, _handler) = StreamBuilder::kraken(KrakenChannel::Trade)
+        .with_symbol("BTC/USD")
+        .with_endpoint(public.clone())
+        .with_auth_endpoint(auth.clone())
+        .connect()
+        .await
+        .unwrap();
+    assert_eq!(received(public_request).await["params"]["channel"], "trade");
+
+    let (_stream, _handler) = StreamBuilder::kraken(KrakenChannel::L3)
+        .with_symbol("BTC/USD")
+        .with_token("token".to_string())
+        .with_endpoint(public)
+        .with_auth_endpoint(auth)
+        .connect()
+        .await
+        .unwrap();
+    let request = received(auth_request).await;
+    assert_eq!(request["params"]["channel"], "level3");
+    assert_eq!(request["params"]["token"], "token");
+}
@@ -0,0 +1,87 @@
+use exstreamer::models::normalized::Exchange;
+use exstreamer::models::{
+    BinanceRequest, BybitMessage, BybitRequest, CoinbaseMessage, CoinbaseRequest, ExchangeMessage,
+    Instrument, InstrumentKind, KrakenChannel, KrakenMessage, KrakenRequest,
+};
+
+fn request(request: &impl serde::Serialize) -> serde_json::Value {
+    serde_json::to_value(request).unwrap()
+}
+
+#[test]
+fn symbols_follow_the_exchange_format() {
+    let eth_btc = Instrument::spot("eth", "btc");
+    assert_eq!(eth_btc.symbol(Exchange::Binance), "ETHBTC");
+    assert_eq!(eth_btc.symbol(Exchange::Bybit), "ETHBTC");
+    assert_eq!(eth_btc.symbol(Exchange::Coinbase), "ETH-BTC");
+    assert_eq!(eth_btc.symbol(Exchange::Kraken), "ETH/BTC");
+    assert_eq!(eth_btc.to_string(), "ETH/BTC");
+}
+
+#[test]
+fn symbols_parse_back_into_instruments() {
+    let spot = InstrumentKind::Spot;
+    assert_eq!(
+        Instrument::parse(Exchange::Binance, "ethusdt", spot),
+        Some(Instrument::spot("ETH", "USDT"))
+    );
+    // The longest quote asset wins
+    assert_eq!(
+        Instrument::parse(Exchange::Bybit, "BTCFDUSD", spot),
+        Some(Instrument::spot("BTC", "FDUSD"))
+    );
+    assert_eq!(
+        Instrument::parse(Exchange::Coinbase, "SOL-EUR", spot),
+        Some(Instrument::spot("SOL", "EUR"))
+    );
+    assert_eq!(
+        Instrument::parse(Exchange::Kraken, "XBT/USD", spot),
+        Some(Instrument::spot("BTC", "USD"))
+    );
+    assert_eq!(
+        Instrument::from_bybit_topic("orderbook.50.ETHUSDT", InstrumentKind::Perpetual),
+        Some(Instrument::perpetual("ETH", "USDT"))
+    );
+
+    assert_eq!(Instrument::parse(Exchange::Binance, "USDT", spot), None);
+    assert_eq!(Instrument::parse(Exchange::Binance, "BTCXYZ", spot), None);
+    assert_eq!(Instrument::parse(Exchange::Coinbase, "BTCUSD", spot), None);
+}
+
+#[test]
+fn requests_accept_instruments_and_strings() {
+    let eth_btc = Instrument::spot("eth", "btc");
+
+    let binance = BinanceRequest::new_subscribe()
+        .with_trade(&eth_btc)
+        .with_trade("solusdt");
+    assert_eq!(binance.params, ["ethbtc@trade", "solusdt@trade"]);
+
+    let bybit = BybitRequest::new_subscribe().with_trade(&eth_btc);
+    assert_eq!(request(&bybit)["args"][0], "publicTrade.ETHBTC");
+
+    let coinbase = CoinbaseRequest::new_subscribe().with_trade(eth_btc.clone());
+    assert_eq!(
+        request(&coinbase)["channels"][0]["product_ids"][0],
+        "ETH-BTC"
+    );
+
+    let mut kraken = KrakenRequest::new_subscribe(KrakenChannel::Trade);
+    kraken.add_symbol(&eth_btc);
+    assert_eq!(request(&kraken)["params"]["symbol"][0], "ETH/BTC");
+}
+
+#[test]
+fn market_data_reports_its_instrument() {
+    let bybit: BybitMessage = serde_json::from_str(r#"{"topic":"orderbook.50.ETHUSDT","type":"snapshot","ts":1687940967466,"data":{"s":"ETHUSDT","b":[],"a":[],"u":1,"seq":1},"cts":1687940967464}"#).unwrap();
+    assert_eq!(bybit.instrument(), Some(Instrument::spot("ETH", "USDT")));
+
+    let coinbase: CoinbaseMessage = serde_json::from_str(r#"{"type":"ticker","sequence":1,"product_id":"ETH-USD","price":"1285.22","open_24h":"1310.79","volume_24h":"1","low_24h":"1","high_24h":"1","volume_30d":"1","best_bid":"1","best_bid_size":"1","best_ask":"1","best_ask_size":"1","side":"buy","time":"2022-10-06T17:40:00Z","trade_id":1,"last_size":"1"}"#).unwrap();
+    assert_eq!(coinbase.instrument(), Some(Instrument::spot("ETH", "USD")));
+
+    let kraken: KrakenMessage = serde_json::from_str(r#"{"channel":"trade","type":"update","data":[{"symbol":"XBT/USD","side":"sell","price":26403.1,"qty":0.1,"ord_type":"market","trade_id":1,"timestamp":"2023-09-25T07:49:37.708706Z"}]}"#).unwrap();
+    assert_eq!(kraken.instrument(), Some(Instrument::spot("BTC", "USD")));
+
+    let heartbeat: KrakenMessage = serde_json::from_str(r#"{"channel":"heartbeat"}"#).unwrap();
+    assert_eq!(heartbeat.instrument(), None);
+}
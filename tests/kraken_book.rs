@@ -0,0 +1,163 @@
+use std::time::Duration;
+
+use exstreamer::StreamBuilder;
+use exstreamer::error::ExStreamError;
+use exstreamer::models::{
+    KrakenChannel, KrakenData, KrakenEventKind, KrakenInstruments, KrakenL2Book, KrakenMessage,
+};
+use exstreamer::orderbook::{BookEvent, KrakenOrderBooks, checksum};
+use futures_util::{SinkExt as _, StreamExt as _};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+
+/// Example of the Kraken book checksum guide
+const SNAPSHOT: &str = include_str!("fixtures/kraken/book_snapshot.json");
+const INSTRUMENT: &str = include_str!("fixtures/kraken/instrument.json");
+const SYMBOL: &str = "BTC/USD";
+const UPDATE: &str = r#"{"channel":"book","type":"update","data":[{"symbol":"BTC/USD","bids":[{"price":69440.0,"qty":0.5}],"asks":[],"checksum":1,"timestamp":"2024-05-19T09:54:01.345678Z"}]}"#;
+
+fn book(json: &str) -> (KrakenEventKind, KrakenL2Book) {
+    match serde_json::from_str(json).unwrap() {
+        KrakenMessage::Event(event) => match event.data.as_slice() {
+            [KrakenData::L2Book(book)] => (event.kind, book.clone()),
+            data => panic!("expected a single book, got {data:?}"),
+        },
+        message => panic!("expected an event, got {message:?}"),
+    }
+}
+
+fn instruments() -> KrakenInstruments {
+    match serde_json::from_str(INSTRUMENT).unwrap() {
+        KrakenMessage::Event(event) => match event.data.as_slice() {
+            [KrakenData::Instrument(instruments)] => instruments.clone(),
+            data => panic!("expected instruments, got {data:?}"),
+        },
+        message => panic!("expected an event, got {message:?}"),
+    }
+}
+
+#[test]
+fn checksum_of_the_documented_book() {
+    let (_, snapshot) = book(SNAPSHOT);
+    let levels = snapshot
+        .asks
+        .iter()
+        .chain(&snapshot.bids)
+        .map(|level| (level.price, level.size));
+    assert_eq!(checksum(levels, 1, 8), 3310070434);
+}
+
+#[test]
+fn snapshot_is_verified() {
+    let mut books = KrakenOrderBooks::new(10).with_precision(SYMBOL, 1, 8);
+    let (kind, snapshot) = book(SNAPSHOT);
+
+    let event = books.apply(kind, &snapshot).unwrap();
+    assert_eq!(
+        event,
+        BookEvent::Snapshot {
+            symbol: SYMBOL.to_string()
+        }
+    );
+    assert!(books.is_synced(SYMBOL));
+}
+
+#[test]
+fn corrupted_snapshot_is_a_mismatch() {
+    let mut books = KrakenOrderBooks::new(10).with_precision(SYMBOL, 1, 8);
+    let (kind, mut snapshot) = book(SNAPSHOT);
+    snapshot.asks[0].size = "0.002".parse().unwrap();
+
+    let event = books.apply(kind, &snapshot).unwrap();
+    assert!(matches!(
+        event,
+        BookEvent::ChecksumMismatch { expected: 3310070434, computed, .. } if computed != 3310070434
+    ));
+    assert!(!books.is_synced(SYMBOL));
+}
+
+#[test]
+fn precision_is_required_to_verify() {
+    let mut books = KrakenOrderBooks::new(10);
+    let (kind, snapshot) = book(SNAPSHOT);
+
+    let result = books.apply(kind, &snapshot);
+    assert!(matches!(result, Err(ExStreamError::MissingPrecision(symbol)) if symbol == SYMBOL));
+    assert!(!books.is_synced(SYMBOL));
+
+    let mut books = KrakenOrderBooks::new(10).with_instruments(&instruments());
+    assert!(matches!(
+        books.apply(kind, &snapshot).unwrap(),
+        BookEvent::Snapshot { .. }
+    ));
+
+    let mut books = KrakenOrderBooks::new(10).without_checksum();
+    let (kind, mut snapshot) = book(SNAPSHOT);
+    snapshot.checksum = 1;
+    assert!(matches!(
+        books.apply(kind, &snapshot).unwrap(),
+        BookEvent::Snapshot { .. }
+    ));
+}
+
+#[test]
+fn update_before_the_snapshot_is_unsynced() {
+    let mut books = KrakenOrderBooks::new(10).with_precision(SYMBOL, 1, 8);
+    let (kind, update) = book(UPDATE);
+
+    assert_eq!(
+        books.apply(kind, &update).unwrap(),
+        BookEvent::Unsynced {
+            symbol: SYMBOL.to_string()
+        }
+    );
+    assert!(!books.is_synced(SYMBOL));
+    assert!(books.best_bid(SYMBOL).is_none());
+}
+
+#[tokio::test]
+async fn book_waiting_for_its_precision_is_resubscribed() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+        ws.next().await.unwrap().unwrap();
+
+        // The book snapshot comes before the precision is known
+        ws.send(Message::text(SNAPSHOT)).await.unwrap();
+        ws.send(Message::text(INSTRUMENT)).await.unwrap();
+        while let Some(Ok(message)) = ws.next().await {
+            let request: serde_json::Value =
+                serde_json::from_str(message.to_text().unwrap()).unwrap();
+            if request["method"] == "subscribe" && request["params"]["channel"] == "book" {
+                ws.send(Message::text(SNAPSHOT)).await.unwrap();
+            }
+        }
+    });
+
+    let (stream, handler) = StreamBuilder::kraken(KrakenChannel::Book)
+        .with_symbol(SYMBOL)
+        .with_depth(10)
+        .with_endpoint(endpoint)
+        .without_heartbeat()
+        .connect()
+        .await
+        .unwrap();
+    let (books, mut events) = KrakenOrderBooks::new(10).track(stream, handler.handle());
+
+    let mut received = Vec::new();
+    for _ in 0..2 {
+        let event = tokio::time::timeout(Duration::from_secs(2), events.next())
+            .await
+            .expect("the book was not resubscribed");
+        received.push(event.unwrap());
+    }
+    assert!(matches!(
+        received[0],
+        Err(ExStreamError::MissingPrecision(_))
+    ));
+    assert!(matches!(received[1], Ok(BookEvent::Snapshot { .. })));
+    assert!(books.read().unwrap().is_synced(SYMBOL));
+}
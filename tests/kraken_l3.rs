@@ -0,0 +1,155 @@
+use exstreamer::error::ExStreamError;
+use exstreamer::models::{
+    KrakenBook, KrakenBookUpdate, KrakenChannel, KrakenData, KrakenEventKind, KrakenMessage,
+    KrakenOrderEvent, NumDecimal,
+};
+use exstreamer::orderbook::{BookEvent, KrakenL3Books};
+
+const SNAPSHOT: &str = include_str!("fixtures/kraken/l3_snapshot.json");
+const UPDATE_ADD: &str = include_str!("fixtures/kraken/l3_update_add.json");
+const UPDATE_MODIFY: &str = include_str!("fixtures/kraken/l3_update_modify.json");
+const UPDATE_DELETE: &str = include_str!("fixtures/kraken/l3_update_delete.json");
+
+fn num(value: &str) -> NumDecimal {
+    value.parse().unwrap()
+}
+
+fn snapshot(json: &str) -> KrakenBook {
+    match serde_json::from_str(json).unwrap() {
+        KrakenMessage::Event(event) => {
+            assert!(matches!(event.channel, KrakenChannel::L3));
+            assert_eq!(event.kind, KrakenEventKind::Snapshot);
+            match event.data.as_slice() {
+                [KrakenData::Book(book)] => book.clone(),
+                data => panic!("expected a single snapshot, got {data:?}"),
+            }
+        }
+        message => panic!("expected an event, got {message:?}"),
+    }
+}
+
+fn update(json: &str) -> KrakenBookUpdate {
+    match serde_json::from_str(json).unwrap() {
+        KrakenMessage::Event(event) => {
+            assert!(matches!(event.channel, KrakenChannel::L3));
+            assert_eq!(event.kind, KrakenEventKind::Update);
+            match event.data.as_slice() {
+                [KrakenData::BookUpdate(update)] => update.clone(),
+                data => panic!("expected a single update, got {data:?}"),
+            }
+        }
+        message => panic!("expected an event, got {message:?}"),
+    }
+}
+
+#[test]
+fn snapshot_holds_resting_orders() {
+    let book = snapshot(SNAPSHOT);
+
+    assert_eq!(book.symbol, "BTC/USD");
+    assert_eq!(book.checksum, 281817320);
+    assert_eq!(
+        book.timestamp.as_deref(),
+        Some("2024-05-19T09:53:58.000000Z")
+    );
+    assert_eq!(book.bids.len(), 2);
+    assert_eq!(book.bids[0].order_id, "OZYA6B-OE3BH-YJ4PY5");
+    assert_eq!(book.bids[0].price, num("69440.1"));
+    assert_eq!(book.bids[0].size, num("0.25"));
+    assert_eq!(book.bids[1].order_id, "OKR5U2-4KDRT-UBEO5K");
+    assert_eq!(book.asks.len(), 1);
+    assert_eq!(book.asks[0].price, num("69440.2"));
+    assert_eq!(book.asks[0].size, num("1.5"));
+}
+
+#[test]
+fn update_add() {
+    let update = update(UPDATE_ADD);
+
+    assert_eq!(update.symbol, "BTC/USD");
+    assert_eq!(update.checksum, 2143854316);
+    assert!(update.asks.is_empty());
+    let order = &update.bids[0];
+    assert_eq!(order.event, KrakenOrderEvent::Add);
+    assert_eq!(order.order_id, "OSUGP4-ZTKJK-GVEBEG");
+    assert_eq!(order.price, num("69439.9"));
+    assert_eq!(order.size, num("0.5"));
+    assert_eq!(order.timestamp, "2024-05-19T09:54:01.345678Z");
+}
+
+#[test]
+fn update_modify() {
+    let update = update(UPDATE_MODIFY);
+
+    let order = &update.bids[0];
+    assert_eq!(order.event, KrakenOrderEvent::Modify);
+    assert_eq!(order.order_id, "OZYA6B-OE3BH-YJ4PY5");
+    assert_eq!(order.size, num("0.1"));
+}
+
+#[test]
+fn update_delete() {
+    let update = update(UPDATE_DELETE);
+
+    let order = &update.bids[0];
+    assert_eq!(order.event, KrakenOrderEvent::Delete);
+    assert_eq!(order.order_id, "OKR5U2-4KDRT-UBEO5K");
+    assert_eq!(order.price, num("69440.1"));
+}
+
+#[test]
+fn update_without_event_is_rejected() {
+    let json = UPDATE_ADD.replace(r#""event": "add", "#, "");
+    assert!(serde_json::from_str::<KrakenMessage>(&json).is_err());
+}
+
+#[test]
+fn unknown_event_is_rejected() {
+    let json = UPDATE_ADD.replace(r#""event": "add""#, r#""event": "replace""#);
+    assert!(serde_json::from_str::<KrakenMessage>(&json).is_err());
+}
+
+#[test]
+fn update_before_the_snapshot_is_unsynced() {
+    let mut books = KrakenL3Books::new().with_precision("BTC/USD", 1, 8);
+
+    let event = books.apply_update(&update(UPDATE_ADD)).unwrap();
+    assert_eq!(
+        event,
+        BookEvent::Unsynced {
+            symbol: "BTC/USD".to_string()
+        }
+    );
+    assert!(!books.is_synced("BTC/USD"));
+}
+
+#[test]
+fn updates_after_a_mismatch_are_unsynced() {
+    let mut books = KrakenL3Books::new().with_precision("BTC/USD", 1, 8);
+    let mut corrupted = snapshot(SNAPSHOT);
+    corrupted.checksum += 1;
+
+    assert!(matches!(
+        books.apply_snapshot(&corrupted).unwrap(),
+        BookEvent::ChecksumMismatch { .. }
+    ));
+    assert!(matches!(
+        books.apply_update(&update(UPDATE_ADD)).unwrap(),
+        BookEvent::Unsynced { .. }
+    ));
+}
+
+#[test]
+fn snapshot_without_precision_is_rejected() {
+    let mut books = KrakenL3Books::new();
+
+    let result = books.apply_snapshot(&snapshot(SNAPSHOT));
+    assert!(matches!(result, Err(ExStreamError::MissingPrecision(symbol)) if symbol == "BTC/USD"));
+    assert!(!books.is_synced("BTC/USD"));
+
+    let mut books = KrakenL3Books::new().without_checksum();
+    assert!(matches!(
+        books.apply_snapshot(&snapshot(SNAPSHOT)).unwrap(),
+        BookEvent::Snapshot { .. }
+    ));
+}
@@ -0,0 +1,201 @@
+use std::time::Duration;
+
+use exstreamer::StreamBuilder;
+use exstreamer::models::normalized::{
+    BookUpdate, Exchange, MarketData, Normalize, PriceLevel, Side, canonical_symbol,
+};
+use exstreamer::models::{
+    BinanceMessage, BybitMessage, CoinbaseMessage, KrakenMessage, NumDecimal,
+};
+use futures_util::{SinkExt as _, StreamExt as _};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+
+const BINANCE_TRADE: &str = r#"{"e":"trade","E":1672515782136,"s":"BNBBTC","t":12345,"p":"0.001","q":"100","T":1672515782136,"m":true,"M":true}"#;
+const BYBIT_TRADE: &str = r#"{"topic":"publicTrade.BTCUSDT","type":"snapshot","ts":1672304486868,"data":[{"T":1672304486865,"s":"BTCUSDT","S":"Buy","v":"0.001","p":"16578.50","L":"PlusTick","i":"20f43950-d8dd-5b31-9112-a178eb6023af","BT":false,"RPI":false},{"T":1672304486866,"s":"BTCUSDT","S":"Sell","v":"0.002","p":"16578.00","L":"MinusTick","i":"20f43950-d8dd-5b31-9112-a178eb6023b0","BT":false,"RPI":false}]}"#;
+const BYBIT_BOOK: &str = r#"{"topic":"orderbook.50.BTCUSDT","type":"delta","ts":1687940967466,"data":{"s":"BTCUSDT","b":[["30247.20","30.028"],["30245.40","0"]],"a":[["30248.70","0"]],"u":177400507,"seq":66544703342},"cts":1687940967464}"#;
+const COINBASE_TICKER: &str = r#"{"type":"ticker","sequence":37475248783,"product_id":"ETH-USD","price":"1285.22","open_24h":"1310.79","volume_24h":"245532.79269678","low_24h":"1280.52","high_24h":"1313.8","volume_30d":"9788783.60117027","best_bid":"1285.04","best_bid_size":"0.46688654","best_ask":"1285.27","best_ask_size":"1.56637040","side":"buy","time":"2022-10-06T17:40:00Z","trade_id":370843401,"last_size":"11.4396987"}"#;
+const KRAKEN_TRADE: &str = r#"{"channel":"trade","type":"update","data":[{"symbol":"XBT/USD","side":"sell","price":26403.1,"qty":0.0012,"ord_type":"market","trade_id":73912883,"timestamp":"2023-09-25T07:49:37.708706Z"}]}"#;
+
+/// Same number as the normalized one, whether the `decimal` feature is enabled or not
+fn num(value: &str) -> NumDecimal {
+    value.parse().unwrap()
+}
+
+fn normalize<M: Normalize + serde::de::DeserializeOwned>(json: &str) -> Vec<MarketData> {
+    serde_json::from_str::<M>(json)
+        .unwrap()
+        .normalize()
+        .unwrap()
+}
+
+#[test]
+fn symbols_are_canonical_across_exchanges() {
+    assert_eq!(canonical_symbol(Exchange::Binance, "btcusdt"), "BTCUSDT");
+    assert_eq!(canonical_symbol(Exchange::Coinbase, "BTC-USD"), "BTCUSD");
+    assert_eq!(canonical_symbol(Exchange::Kraken, "XBT/USD"), "BTCUSD");
+    assert_eq!(canonical_symbol(Exchange::Bybit, "ETHUSDC"), "ETHUSDC");
+    assert_eq!(canonical_symbol(Exchange::Binance, "BTCUSD_PERP"), "BTCUSD");
+    // Unknown quote assets keep everything but the pair separator
+    assert_eq!(canonical_symbol(Exchange::Kraken, "xbtusd"), "BTCUSD");
+    assert_eq!(
+        canonical_symbol(Exchange::Binance, "BTCUSD_240329"),
+        "BTCUSD_240329"
+    );
+}
+
+#[test]
+fn binance_maker_buyer_is_a_sell() {
+    let data = normalize::<BinanceMessage>(BINANCE_TRADE);
+    let [MarketData::Trade(trade)] = data.as_slice() else {
+        panic!("{data:?}");
+    };
+    assert_eq!(trade.exchange, Exchange::Binance);
+    assert_eq!(trade.symbol, "BNBBTC");
+    assert_eq!((trade.price, trade.size), (num("0.001"), num("100")));
+    assert_eq!(trade.side, Side::Sell);
+    assert_eq!(trade.trade_id, "12345");
+    assert_eq!(trade.exchange_time, 1672515782136);
+}
+
+#[test]
+fn bybit_messages_carry_every_update() {
+    let data = normalize::<BybitMessage>(BYBIT_TRADE);
+    let sides = data
+        .iter()
+        .map(|data| match data {
+            MarketData::Trade(trade) => (trade.side, trade.price),
+            other => panic!("{other:?}"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        sides,
+        [(Side::Buy, num("16578.50")), (Side::Sell, num("16578.00"))]
+    );
+
+    let data = normalize::<BybitMessage>(BYBIT_BOOK);
+    let [MarketData::BookUpdate(book)] = data.as_slice() else {
+        panic!("{data:?}");
+    };
+    assert_eq!(
+        *book,
+        BookUpdate {
+            exchange: Exchange::Bybit,
+            symbol: "BTCUSDT".to_string(),
+            is_snapshot: false,
+            bids: vec![
+                PriceLevel {
+                    price: num("30247.20"),
+                    size: num("30.028"),
+                },
+                PriceLevel {
+                    price: num("30245.40"),
+                    size: num("0"),
+                },
+            ],
+            asks: vec![PriceLevel {
+                price: num("30248.70"),
+                size: num("0"),
+            }],
+            sequence: Some(177400507),
+            exchange_time: 1687940967466,
+            local_time: book.local_time,
+        }
+    );
+}
+
+#[test]
+fn coinbase_ticker_is_a_trade_a_bbo_and_a_ticker() {
+    let data = normalize::<CoinbaseMessage>(COINBASE_TICKER);
+    let [
+        MarketData::Trade(trade),
+        MarketData::BestBidOffer(bbo),
+        MarketData::Ticker(ticker),
+    ] = data.as_slice()
+    else {
+        panic!("{data:?}");
+    };
+
+    assert_eq!(trade.symbol, "ETHUSD");
+    assert_eq!(
+        (trade.price, trade.size),
+        (num("1285.22"), num("11.4396987"))
+    );
+    assert_eq!(trade.side, Side::Buy);
+    assert_eq!(trade.exchange_time, 1665078000000);
+    assert_eq!(
+        (bbo.bid_price, bbo.ask_price),
+        (num("1285.04"), num("1285.27"))
+    );
+    assert_eq!(ticker.open_24h, Some(num("1310.79")));
+    assert_eq!(ticker.volume_24h, Some(num("245532.79269678")));
+}
+
+#[test]
+fn kraken_trade_timestamps_are_epoch_milliseconds() {
+    let data = normalize::<KrakenMessage>(KRAKEN_TRADE);
+    let [MarketData::Trade(trade)] = data.as_slice() else {
+        panic!("{data:?}");
+    };
+    assert_eq!(trade.exchange, Exchange::Kraken);
+    assert_eq!(trade.symbol, "BTCUSD");
+    assert_eq!(trade.side, Side::Sell);
+    assert_eq!(trade.exchange_time, 1695628177708);
+}
+
+#[test]
+fn acks_normalize_to_nothing() {
+    let ack = r#"{"result":null,"id":1}"#;
+    assert!(normalize::<BinanceMessage>(ack).is_empty());
+}
+
+#[test]
+fn invalid_numbers_are_reported() {
+    let trade = BINANCE_TRADE.replace(r#""p":"0.001""#, r#""p":"n/a""#);
+    // Rejected when parsing the message with the `decimal` feature, when normalizing without
+    let rejected = match serde_json::from_str::<BinanceMessage>(&trade) {
+        Ok(message) => message.normalize().is_err(),
+        Err(_) => true,
+    };
+    assert!(rejected);
+}
+
+#[tokio::test]
+async fn connect_normalized_streams_market_data() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("ws://{}", listener.local_addr().unwrap());
+
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+        ws.next().await.unwrap().unwrap();
+        let ack = r#"{"success":true,"ret_msg":"","conn_id":"1","req_id":"1","op":"subscribe"}"#;
+        ws.send(Message::text(ack)).await.unwrap();
+        ws.send(Message::text(BYBIT_TRADE)).await.unwrap();
+        while ws.next().await.is_some() {}
+    });
+
+    let (mut stream, _handler) = StreamBuilder::bybit()
+        .with_trade("btcusdt")
+        .with_endpoint(endpoint)
+        .connect_normalized()
+        .await
+        .unwrap();
+
+    // The ack is skipped, the message with two trades yields two items
+    for expected in [
+        "20f43950-d8dd-5b31-9112-a178eb6023af",
+        "20f43950-d8dd-5b31-9112-a178eb6023b0",
+    ] {
+        let data = tokio::time::timeout(Duration::from_secs(2), stream.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let MarketData::Trade(trade) = data else {
+            panic!("{data:?}");
+        };
+        assert_eq!(trade.exchange, Exchange::Bybit);
+        assert_eq!(trade.trade_id, expected);
+    }
+}